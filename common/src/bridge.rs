@@ -0,0 +1,603 @@
+use crate::json_rpc::JsonRpcClient;
+use crate::json_rpc::JsonRpcClientError;
+use ethers_core::abi::{encode, AbiParser, Contract, RawLog, Token, Tokenizable};
+use ethers_core::types::{
+    Address, Bytes, Filter, Log, TransactionReceipt, TransactionRequest, ValueOrArray, H160, H256,
+    U256, U64,
+};
+use ethers_core::utils::keccak256;
+use ethers_signers::{LocalWallet, Signer};
+use hyper::Uri;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// The predeployed `ZkEvmL2MessageDeliverer` on L2.
+pub const L2_MESSAGE_DELIVERER: Address = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01, 0, 0,
+]);
+/// The predeployed `ZkEvmL2MessageDispatcher` on L2.
+pub const L2_MESSAGE_DISPATCHER: Address = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x02, 0, 0,
+]);
+/// Interval in ms for polling receipts and events.
+const POLL_INTERVAL: u64 = 500;
+
+/// Errors returned by `BridgeClient`.
+#[derive(Debug, Clone)]
+pub enum BridgeError {
+    /// The json-rpc request failed.
+    Rpc(JsonRpcClientError),
+    /// The transaction was mined but reverted.
+    Revert(H256),
+    /// The operation did not complete in time.
+    Timeout(String),
+    /// A response or log could not be decoded.
+    Decode(String),
+    /// Anything else, e.g. signing or abi encoding errors.
+    Other(String),
+}
+
+impl std::fmt::Display for BridgeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rpc(err) => write!(f, "rpc: {}", err),
+            Self::Revert(tx_hash) => write!(f, "transaction reverted: {:?}", tx_hash),
+            Self::Timeout(err) => write!(f, "timeout: {}", err),
+            Self::Decode(err) => write!(f, "decode: {}", err),
+            Self::Other(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for BridgeError {}
+
+impl From<JsonRpcClientError> for BridgeError {
+    fn from(err: JsonRpcClientError) -> Self {
+        Self::Rpc(err)
+    }
+}
+
+/// A side of the bridge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    L1,
+    L2,
+}
+
+impl Layer {
+    /// The other side of the bridge.
+    pub fn other(&self) -> Self {
+        match self {
+            Self::L1 => Self::L2,
+            Self::L2 => Self::L1,
+        }
+    }
+}
+
+// https://eips.ethereum.org/EIPS/eip-1186
+#[derive(Debug, serde::Deserialize)]
+pub struct ProofRequest {
+    pub address: Address,
+    #[serde(rename = "accountProof")]
+    pub account_proof: Vec<Bytes>,
+    pub balance: U256,
+    #[serde(rename = "codeHash")]
+    pub code_hash: H256,
+    pub nonce: U256,
+    #[serde(rename = "storageHash")]
+    pub storage_hash: H256,
+    #[serde(rename = "storageProof")]
+    pub storage_proof: Vec<StorageProof>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct StorageProof {
+    pub key: H256,
+    pub value: U256,
+    pub proof: Vec<Bytes>,
+}
+
+/// The proof that a message is pending in the dispatcher at `block_hash`.
+#[derive(Debug)]
+pub struct DeliveryProof {
+    pub block_hash: H256,
+    /// The `eth_getProof` response for the storage slot of the message.
+    pub proof: ProofRequest,
+    /// The proof as expected by `deliverMessageWithProof`, see `marshal_proof`.
+    pub data: Bytes,
+}
+
+/// The state of a message as seen from both sides of the bridge.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MessageStatus {
+    /// Not dispatched or dropped after the deadline passed.
+    Unknown,
+    /// Dispatched and waiting for delivery.
+    Pending,
+    /// Delivered by the transaction.
+    Delivered(H256),
+}
+
+/// A L2 block that is finalized on L1.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct FinalizedBlock {
+    pub hash: H256,
+    pub number: U64,
+    #[serde(rename = "stateRoot")]
+    pub state_root: H256,
+}
+
+/// A cross-layer message as dispatched by `dispatchMessage`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    pub fee: U256,
+    pub deadline: U256,
+    pub nonce: U256,
+    pub data: Bytes,
+}
+
+impl Message {
+    /// A message without fee and calldata, a random nonce and no deadline.
+    pub fn new(from: Address, to: Address, value: U256) -> Self {
+        Self {
+            from,
+            to,
+            value,
+            fee: U256::zero(),
+            deadline: U256::from(u64::MAX),
+            nonce: rand::random::<u64>().into(),
+            data: Bytes::default(),
+        }
+    }
+
+    pub fn with_fee(mut self, fee: U256) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: U256) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn with_nonce(mut self, nonce: U256) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    pub fn with_data(mut self, data: Bytes) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// Decodes a `MessageDispatched` event.
+    pub fn from_log(log: &Log) -> Result<Self, BridgeError> {
+        let evt = bridge_abi()
+            .event("MessageDispatched")
+            .and_then(|evt| evt.parse_log(RawLog::from((log.topics.clone(), log.data.to_vec()))))
+            .map_err(|e| BridgeError::Decode(format!("MessageDispatched: {}", e)))?;
+
+        macro_rules! param {
+            ($idx:expr, $into:ident) => {
+                evt.params
+                    .get($idx)
+                    .and_then(|p| p.value.to_owned().$into())
+                    .ok_or_else(|| {
+                        BridgeError::Decode(format!("MessageDispatched: param {}", $idx))
+                    })?
+            };
+        }
+
+        Ok(Self {
+            from: param!(0, into_address),
+            to: param!(1, into_address),
+            value: param!(2, into_uint),
+            fee: param!(3, into_uint),
+            deadline: param!(4, into_uint),
+            nonce: param!(5, into_uint),
+            data: param!(6, into_bytes).into(),
+        })
+    }
+
+    /// `keccak256(abi.encode(from, to, value, fee, deadline, nonce, data))`
+    pub fn id(&self) -> H256 {
+        keccak256(encode(&[
+            self.from.into_token(),
+            self.to.into_token(),
+            self.value.into_token(),
+            self.fee.into_token(),
+            self.deadline.into_token(),
+            self.nonce.into_token(),
+            self.data.clone().into_token(),
+        ]))
+        .into()
+    }
+
+    /// The storage slot of the message in the dispatcher.
+    pub fn storage_slot(&self) -> H256 {
+        message_storage_slot(&self.id())
+    }
+
+    /// The calldata of `dispatchMessage`, `value + fee` is the value of the transaction.
+    pub fn dispatch_calldata(&self) -> Vec<u8> {
+        bridge_calldata(
+            "dispatchMessage",
+            &[
+                self.to.into_token(),
+                self.fee.into_token(),
+                self.deadline.into_token(),
+                self.nonce.into_token(),
+                self.data.clone().into_token(),
+            ],
+        )
+    }
+
+    /// The calldata of `deliverMessageWithProof`.
+    pub fn delivery_calldata(&self, proof: Bytes) -> Vec<u8> {
+        bridge_calldata(
+            "deliverMessageWithProof",
+            &[
+                self.from.into_token(),
+                self.to.into_token(),
+                self.value.into_token(),
+                self.fee.into_token(),
+                self.deadline.into_token(),
+                self.nonce.into_token(),
+                self.data.clone().into_token(),
+                proof.into_token(),
+            ],
+        )
+    }
+}
+
+/// The abi of the bridge contracts on both layers.
+pub fn bridge_abi() -> Contract {
+    AbiParser::default()
+        .parse(&[
+            "event BlockFinalized(bytes32 blockHash)",
+            "event MessageDispatched(address from, address to, uint256 value, uint256 fee, uint256 deadline, uint256 nonce, bytes data)",
+            "event MessageDelivered(bytes32 id)",
+            "function dispatchMessage(address to, uint256 fee, uint256 deadline, uint256 nonce, bytes data) payable returns (bytes32)",
+            "function deliverMessageWithProof(address from, address to, uint256 value, uint256 fee, uint256 deadline, uint256 nonce, bytes data, bytes proof)",
+        ])
+        .expect("parse abi")
+}
+
+fn bridge_calldata(name: &str, tokens: &[Token]) -> Vec<u8> {
+    bridge_abi()
+        .function(name)
+        .and_then(|f| f.encode_input(tokens))
+        .expect("abi encode")
+}
+
+/// Calculates the storage slot of the message `id`, see `ZkEvmStorage._PENDING_MESSAGE_KEY`.
+pub fn message_storage_slot(id: &H256) -> H256 {
+    let mut buf: Vec<u8> = Vec::with_capacity(64);
+    let sig = 0x31df76a4_u32.to_be_bytes();
+
+    buf.resize(28, 0);
+    buf.extend(sig);
+    buf.extend(id.as_ref());
+
+    keccak256(buf).into()
+}
+
+/// encodes the proof from `eth_getProof` suitable for the PatriciaValidator contract.
+/// The contract expects the number of nodes of each proof as a single byte.
+pub fn marshal_proof(
+    account_proof: &[Bytes],
+    storage_proof: &[Bytes],
+) -> Result<Vec<u8>, BridgeError> {
+    let mut ret: Vec<u8> = Vec::new();
+
+    for proof in [account_proof, storage_proof] {
+        let len = u8::try_from(proof.len()).map_err(|_| {
+            BridgeError::Other(format!(
+                "marshal_proof: {} proof nodes exceed the limit of {}",
+                proof.len(),
+                u8::MAX
+            ))
+        })?;
+        ret.push(len);
+
+        for b in proof.iter() {
+            let data = b.as_ref();
+            ret.extend(data);
+        }
+    }
+
+    Ok(ret)
+}
+
+/// A client for sending and tracking messages across the bridge.
+#[derive(Clone, Debug)]
+pub struct BridgeClient {
+    pub client: JsonRpcClient,
+    pub l1_rpc_url: Uri,
+    pub l2_rpc_url: Uri,
+    /// The `ZkEvmL1Bridge` on L1.
+    pub l1_bridge: Address,
+}
+
+impl BridgeClient {
+    pub fn new(
+        client: JsonRpcClient,
+        l1_rpc_url: Uri,
+        l2_rpc_url: Uri,
+        l1_bridge: Address,
+    ) -> Self {
+        Self {
+            client,
+            l1_rpc_url,
+            l2_rpc_url,
+            l1_bridge,
+        }
+    }
+
+    pub fn rpc_url(&self, layer: Layer) -> &Uri {
+        match layer {
+            Layer::L1 => &self.l1_rpc_url,
+            Layer::L2 => &self.l2_rpc_url,
+        }
+    }
+
+    /// The contract that dispatches messages on `layer`.
+    pub fn dispatcher(&self, layer: Layer) -> Address {
+        match layer {
+            Layer::L1 => self.l1_bridge,
+            Layer::L2 => L2_MESSAGE_DISPATCHER,
+        }
+    }
+
+    /// The contract that delivers messages on `layer`.
+    pub fn deliverer(&self, layer: Layer) -> Address {
+        match layer {
+            Layer::L1 => self.l1_bridge,
+            Layer::L2 => L2_MESSAGE_DELIVERER,
+        }
+    }
+
+    pub async fn request<T: Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        layer: Layer,
+        method: &str,
+        params: T,
+    ) -> Result<R, BridgeError> {
+        Ok(self
+            .client
+            .request(self.rpc_url(layer), method, params)
+            .await?)
+    }
+
+    /// Dispatches `message` from L1 to L2 and returns the transaction hash.
+    pub async fn deposit(
+        &self,
+        wallet: &LocalWallet,
+        message: &Message,
+    ) -> Result<H256, BridgeError> {
+        self.dispatch(Layer::L1, wallet, message).await
+    }
+
+    /// Dispatches `message` from L2 to L1 and returns the transaction hash.
+    pub async fn withdraw(
+        &self,
+        wallet: &LocalWallet,
+        message: &Message,
+    ) -> Result<H256, BridgeError> {
+        self.dispatch(Layer::L2, wallet, message).await
+    }
+
+    /// Dispatches `message` on `layer` and returns the transaction hash.
+    /// `message.from` must be the address of `wallet`.
+    pub async fn dispatch(
+        &self,
+        layer: Layer,
+        wallet: &LocalWallet,
+        message: &Message,
+    ) -> Result<H256, BridgeError> {
+        if message.from != wallet.address() {
+            return Err(BridgeError::Other(format!(
+                "message.from {:?} is not the wallet {:?}",
+                message.from,
+                wallet.address()
+            )));
+        }
+
+        self.send_transaction(
+            layer,
+            wallet,
+            self.dispatcher(layer),
+            message.value + message.fee,
+            message.dispatch_calldata(),
+        )
+        .await
+    }
+
+    /// Signs and sends a transaction on `layer` and returns the transaction hash.
+    pub async fn send_transaction(
+        &self,
+        layer: Layer,
+        wallet: &LocalWallet,
+        to: Address,
+        value: U256,
+        calldata: Vec<u8>,
+    ) -> Result<H256, BridgeError> {
+        let chain_id: U64 = self.request(layer, "eth_chainId", ()).await?;
+        let nonce: U256 = self
+            .request(
+                layer,
+                "eth_getTransactionCount",
+                (wallet.address(), "pending"),
+            )
+            .await?;
+        let gas_price: U256 = self.request(layer, "eth_gasPrice", ()).await?;
+        let tx = TransactionRequest::new()
+            .chain_id(chain_id.as_u64())
+            .from(wallet.address())
+            .to(to)
+            .nonce(nonce)
+            .value(value)
+            .gas_price(gas_price)
+            .data(calldata);
+        let estimate: U256 = self.request(layer, "eth_estimateGas", [&tx]).await?;
+        let tx = tx.gas(estimate).into();
+        let sig = wallet
+            .sign_transaction(&tx)
+            .await
+            .map_err(|e| BridgeError::Other(format!("sign_transaction: {}", e)))?;
+
+        self.request(layer, "eth_sendRawTransaction", [tx.rlp_signed(&sig)])
+            .await
+    }
+
+    /// Waits up to `timeout` ms for the receipt of `tx_hash` on `layer`.
+    pub async fn wait_for_receipt(
+        &self,
+        layer: Layer,
+        tx_hash: H256,
+        timeout: u64,
+    ) -> Result<TransactionReceipt, BridgeError> {
+        let deadline = Instant::now() + Duration::from_millis(timeout);
+        loop {
+            let receipt: Option<TransactionReceipt> = self
+                .request(layer, "eth_getTransactionReceipt", [tx_hash])
+                .await?;
+            if let Some(receipt) = receipt {
+                if receipt.status != Some(U64::one()) {
+                    return Err(BridgeError::Revert(tx_hash));
+                }
+                return Ok(receipt);
+            }
+
+            if Instant::now() >= deadline {
+                return Err(BridgeError::Timeout(format!("receipt of {:?}", tx_hash)));
+            }
+            tokio::time::sleep(Duration::from_millis(POLL_INTERVAL)).await;
+        }
+    }
+
+    /// Returns the status of the message `id` that was dispatched on `layer`.
+    /// Deliveries are searched from block `from_block` on the other layer.
+    pub async fn message_status(
+        &self,
+        layer: Layer,
+        id: &H256,
+        from_block: U64,
+    ) -> Result<MessageStatus, BridgeError> {
+        let destination = layer.other();
+        let filter = Filter::new()
+            .address(ValueOrArray::Value(self.deliverer(destination)))
+            .topic0(ValueOrArray::Value(
+                bridge_abi()
+                    .event("MessageDelivered")
+                    .expect("MessageDelivered")
+                    .signature(),
+            ))
+            .from_block(from_block);
+        let logs: Vec<Log> = self.request(destination, "eth_getLogs", [&filter]).await?;
+        if let Some(log) = logs.iter().find(|log| log.data.as_ref() == id.as_bytes()) {
+            let tx_hash = log
+                .transaction_hash
+                .ok_or_else(|| BridgeError::Decode("log without transaction hash".to_string()))?;
+            return Ok(MessageStatus::Delivered(tx_hash));
+        }
+
+        let value: H256 = self
+            .request(
+                layer,
+                "eth_getStorageAt",
+                (self.dispatcher(layer), message_storage_slot(id), "latest"),
+            )
+            .await?;
+        match value.is_zero() {
+            true => Ok(MessageStatus::Unknown),
+            false => Ok(MessageStatus::Pending),
+        }
+    }
+
+    /// Requests the storage proof of `message`, dispatched on `layer`, at `block_hash`.
+    /// For messages from L2, `block_hash` must be finalized on L1, see `wait_for_finalization`.
+    pub async fn build_delivery_proof(
+        &self,
+        layer: Layer,
+        message: &Message,
+        block_hash: H256,
+    ) -> Result<DeliveryProof, BridgeError> {
+        let proof: ProofRequest = self
+            .request(
+                layer,
+                "eth_getProof",
+                (self.dispatcher(layer), [message.storage_slot()], block_hash),
+            )
+            .await?;
+        let storage_proof = proof.storage_proof.first().ok_or_else(|| {
+            BridgeError::Decode("eth_getProof: missing storage proof".to_string())
+        })?;
+        if storage_proof.value.is_zero() {
+            return Err(BridgeError::Other(format!(
+                "message {:?} is not pending at {:?}",
+                message.id(),
+                block_hash
+            )));
+        }
+        let data = marshal_proof(&proof.account_proof, &storage_proof.proof)?.into();
+
+        Ok(DeliveryProof {
+            block_hash,
+            proof,
+            data,
+        })
+    }
+
+    /// Waits up to `timeout` ms until L2 block `block_number` or a descendant is finalized on L1.
+    /// `BlockFinalized` events are searched from L1 block `from_block`.
+    pub async fn wait_for_finalization(
+        &self,
+        block_number: U64,
+        from_block: U64,
+        timeout: u64,
+    ) -> Result<FinalizedBlock, BridgeError> {
+        let deadline = Instant::now() + Duration::from_millis(timeout);
+        let topic = bridge_abi()
+            .event("BlockFinalized")
+            .expect("BlockFinalized")
+            .signature();
+        let mut from = from_block;
+        loop {
+            let latest: U64 = self.request(Layer::L1, "eth_blockNumber", ()).await?;
+            if from <= latest {
+                let filter = Filter::new()
+                    .address(ValueOrArray::Value(self.l1_bridge))
+                    .topic0(ValueOrArray::Value(topic))
+                    .from_block(from)
+                    .to_block(latest);
+                let logs: Vec<Log> = self.request(Layer::L1, "eth_getLogs", [&filter]).await?;
+                for log in logs.iter().rev() {
+                    if log.data.len() != 32 {
+                        return Err(BridgeError::Decode("BlockFinalized: data".to_string()));
+                    }
+                    let block_hash = H256::from_slice(log.data.as_ref());
+                    let block: FinalizedBlock = self
+                        .request(Layer::L2, "eth_getHeaderByHash", [block_hash])
+                        .await?;
+                    if block.number >= block_number {
+                        return Ok(block);
+                    }
+                }
+                from = latest + 1u64;
+            }
+
+            if Instant::now() >= deadline {
+                return Err(BridgeError::Timeout(format!(
+                    "finalization of block {}",
+                    block_number
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(POLL_INTERVAL)).await;
+        }
+    }
+}
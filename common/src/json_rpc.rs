@@ -4,17 +4,101 @@ use hyper::client::HttpConnector;
 use hyper::Body;
 use hyper::Request;
 use hyper::Uri;
+use hyper_rustls::HttpsConnector;
+use hyper_rustls::HttpsConnectorBuilder;
+use std::cmp;
+use std::future::Future;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
 
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Serialize;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcError {
     pub code: i32,
     pub message: String,
 }
 
+impl JsonRpcError {
+    /// Invalid JSON was received by the server.
+    pub const PARSE_ERROR: i32 = -32700;
+    /// The JSON sent is not a valid request object.
+    pub const INVALID_REQUEST: i32 = -32600;
+    /// The method does not exist / is not available.
+    pub const METHOD_NOT_FOUND: i32 = -32601;
+    /// Invalid method parameter(s).
+    pub const INVALID_PARAMS: i32 = -32602;
+    /// Internal json-rpc error.
+    pub const INTERNAL_ERROR: i32 = -32603;
+    /// Generic application error.
+    pub const SERVER_ERROR: i32 = -32000;
+
+    pub fn new<M: ToString>(code: i32, message: M) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<String> for JsonRpcError {
+    fn from(message: String) -> Self {
+        Self::new(Self::SERVER_ERROR, message)
+    }
+}
+
+impl From<&str> for JsonRpcError {
+    fn from(message: &str) -> Self {
+        Self::new(Self::SERVER_ERROR, message)
+    }
+}
+
+/// Errors returned by `JsonRpcClient`.
+#[derive(Debug, Clone)]
+pub enum JsonRpcClientError {
+    /// The request could not be sent or the response body could not be read.
+    Transport(String),
+    /// The request did not complete within the given timeout.
+    Timeout,
+    /// The response body is not a valid json-rpc response for the expected type.
+    Decode(String),
+    /// The node responded with a json-rpc error object.
+    Rpc(JsonRpcError),
+    /// The response contains neither `result` nor `error`.
+    NoResult,
+}
+
+impl std::fmt::Display for JsonRpcClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Transport(err) => write!(f, "transport error: {}", err),
+            Self::Timeout => write!(f, "request timed out"),
+            Self::Decode(err) => write!(f, "decode error: {}", err),
+            Self::Rpc(err) => write!(f, "{} (code={})", err.message, err.code),
+            Self::NoResult => write!(f, "no result in response"),
+        }
+    }
+}
+
+impl JsonRpcClientError {
+    /// Returns `true` if the request may succeed if it is sent again.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::Transport(_) | Self::Timeout)
+    }
+}
+
+impl std::error::Error for JsonRpcClientError {}
+
+impl From<JsonRpcClientError> for String {
+    fn from(err: JsonRpcClientError) -> Self {
+        err.to_string()
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct JsonRpcResponseError {
     pub jsonrpc: String,
@@ -37,64 +121,316 @@ pub struct JsonRpcRequest<T: Serialize> {
     pub params: T,
 }
 
-/// Invokes a `json-rpc` request with a timeout of `timeout` ms for the network
-/// and deserialize part.
-pub async fn jsonrpc_request_client<T: Serialize + Send + Sync, R: DeserializeOwned>(
-    timeout: u64,
-    client: &hyper::Client<HttpConnector>,
-    uri: &Uri,
-    method: &str,
-    params: T,
-) -> Result<R, String> {
-    #[derive(Debug, Deserialize)]
-    struct JsonRpcResponseInternal<T> {
-        result: Option<T>,
-        error: Option<JsonRpcError>,
-    }
-
-    let node_req = Request::post(uri);
-    let req_obj = JsonRpcRequest {
-        jsonrpc: "2.0".to_string(),
-        id: 0.into(),
-        method: method.to_string(),
-        params,
+/// Describes how often and how fast a failed request is retried.
+/// Only transport errors and timeouts are retried.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Maximum number of retries after the first attempt.
+    pub retries: u32,
+    /// Delay in ms before the first retry, doubles with each retry.
+    pub backoff: u64,
+    /// Upper bound for the delay in ms.
+    pub max_backoff: u64,
+}
+
+impl RetryPolicy {
+    /// Never retry.
+    pub const NONE: Self = Self {
+        retries: 0,
+        backoff: 0,
+        max_backoff: 0,
     };
 
-    let node_req = node_req
-        .header(hyper::header::CONTENT_TYPE, "application/json")
-        .body(Body::from(serde_json::to_vec(&req_obj).unwrap()))
-        .unwrap();
+    /// Returns the delay before retry number `attempt` (starting at 0).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let ms = self.backoff.saturating_mul(1 << cmp::min(attempt, 16));
+        Duration::from_millis(cmp::min(ms, self.max_backoff))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            backoff: 250,
+            max_backoff: 4000,
+        }
+    }
+}
+
+/// A json-rpc client for `http` and `https` endpoints.
+/// Cloning is cheap and clones share the connection pool and the request id counter.
+#[derive(Clone, Debug)]
+pub struct JsonRpcClient {
+    client: hyper::Client<HttpsConnector<HttpConnector>>,
+    /// Timeout in ms for a single attempt, covering the network and deserialize part.
+    timeout: u64,
+    retry: RetryPolicy,
+    /// Sent as `authorization: Bearer <auth_token>` if set.
+    auth_token: Option<Arc<String>>,
+    next_id: Arc<AtomicU64>,
+}
 
-    log::trace!("jsonrpc_request_client: {} {}", uri, method);
+impl Default for JsonRpcClient {
+    fn default() -> Self {
+        Self::new(30_000)
+    }
+}
+
+impl JsonRpcClient {
+    /// Creates a new client with a request timeout of `timeout` ms and no retries.
+    pub fn new(timeout: u64) -> Self {
+        let connector = HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_or_http()
+            .enable_http1()
+            .build();
 
-    let json = tokio::time::timeout(std::time::Duration::from_millis(timeout), async {
-        let err_str = &uri.to_string();
-        let resp = client.request(node_req).await.expect(err_str);
-        let body = hyper::body::aggregate(resp).await.expect(err_str);
-        let json: JsonRpcResponseInternal<R> =
-            serde_json::from_reader(body.reader()).expect(err_str);
+        Self {
+            client: hyper::Client::builder().build(connector),
+            timeout,
+            retry: RetryPolicy::NONE,
+            auth_token: None,
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
 
-        json
-    })
-    .await
-    .map_err(|e| e.to_string())?;
+    /// Sets the retry policy for transport errors and timeouts.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
 
-    if json.error.is_some() {
-        return Err(json.error.unwrap().message);
+    /// Sets the bearer token for servers that require authentication.
+    pub fn with_auth_token(mut self, auth_token: Option<String>) -> Self {
+        self.auth_token = auth_token.map(Arc::new);
+        self
     }
 
-    if json.result.is_none() {
-        return Err("no result in response".to_string());
+    /// The underlying http(s) client, e.g. for proxying raw requests.
+    pub fn http_client(&self) -> &hyper::Client<HttpsConnector<HttpConnector>> {
+        &self.client
     }
 
-    Ok(json.result.unwrap())
+    /// Invokes a `json-rpc` request and retries according to the configured `RetryPolicy`.
+    pub async fn request<T: Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        uri: &Uri,
+        method: &str,
+        params: T,
+    ) -> Result<R, JsonRpcClientError> {
+        self.request_with_timeout(self.timeout, uri, method, params)
+            .await
+    }
+
+    /// Like `request` but with a timeout of `timeout` ms for each attempt.
+    pub async fn request_with_timeout<T: Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        timeout: u64,
+        uri: &Uri,
+        method: &str,
+        params: T,
+    ) -> Result<R, JsonRpcClientError> {
+        self.with_retry_policy(uri, method, || async {
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+            let req_obj = JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                id: id.into(),
+                method: method.to_string(),
+                params: &params,
+            };
+
+            log::trace!("jsonrpc_request({}): {} {}", id, uri, method);
+
+            let json: JsonRpcResponseInternal = self.post(timeout, uri, method, &req_obj).await?;
+            if json.error.is_none() && json.id != id {
+                return Err(JsonRpcClientError::Decode(format!(
+                    "{} {}: response id {} does not match request id {}",
+                    uri, method, json.id, id
+                )));
+            }
+
+            json.into_result()
+        })
+        .await
+    }
+
+    /// Invokes `method` once for each entry in `params` within a single `json-rpc` batch request.
+    /// The outer `Result` fails if the batch as a whole fails, the inner results are in the
+    /// same order as `params`.
+    pub async fn batch_request<T: Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        uri: &Uri,
+        method: &str,
+        params: &[T],
+    ) -> Result<Vec<Result<R, JsonRpcClientError>>, JsonRpcClientError> {
+        if params.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.with_retry_policy(uri, method, || async {
+            let n = params.len() as u64;
+            let first_id = self.next_id.fetch_add(n, Ordering::Relaxed);
+            let req_obj: Vec<JsonRpcRequest<&T>> = params
+                .iter()
+                .zip(first_id..)
+                .map(|(params, id)| JsonRpcRequest {
+                    jsonrpc: "2.0".to_string(),
+                    id: id.into(),
+                    method: method.to_string(),
+                    params,
+                })
+                .collect();
+
+            log::trace!(
+                "jsonrpc_batch_request({}+{}): {} {}",
+                first_id,
+                n,
+                uri,
+                method
+            );
+
+            let responses: Vec<JsonRpcResponseInternal> =
+                self.post(self.timeout, uri, method, &req_obj).await?;
+            // the responses may arrive in any order
+            let mut results: Vec<Option<Result<R, JsonRpcClientError>>> =
+                params.iter().map(|_| None).collect();
+            for json in responses {
+                let index = json
+                    .id
+                    .as_u64()
+                    .and_then(|id| id.checked_sub(first_id))
+                    .filter(|index| *index < n);
+                match index {
+                    Some(index) => results[index as usize] = Some(json.into_result()),
+                    // a single error object without id, e.g. if batches are not supported
+                    None => json.into_result::<serde_json::Value>().map(|_| ())?,
+                }
+            }
+
+            results
+                .into_iter()
+                .zip(first_id..)
+                .map(|(res, id)| {
+                    res.ok_or_else(|| {
+                        JsonRpcClientError::Decode(format!(
+                            "{} {}: missing response for request id {}",
+                            uri, method, id
+                        ))
+                    })
+                })
+                .collect()
+        })
+        .await
+    }
+
+    /// Evaluates `f` again according to the configured `RetryPolicy`
+    /// as long as it returns a retryable error.
+    async fn with_retry_policy<F, Fut, R>(
+        &self,
+        uri: &Uri,
+        method: &str,
+        f: F,
+    ) -> Result<R, JsonRpcClientError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<R, JsonRpcClientError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match f().await {
+                Err(err) if err.is_retryable() && attempt < self.retry.retries => {
+                    log::debug!(
+                        "jsonrpc retry({}/{}): {} {}: {}",
+                        attempt + 1,
+                        self.retry.retries,
+                        uri,
+                        method,
+                        err
+                    );
+                    tokio::time::sleep(self.retry.delay(attempt)).await;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Posts `req_obj` to `uri` and deserializes the response body
+    /// with a timeout of `timeout` ms for the network and deserialize part.
+    async fn post<B: Serialize, R: DeserializeOwned>(
+        &self,
+        timeout: u64,
+        uri: &Uri,
+        method: &str,
+        req_obj: &B,
+    ) -> Result<R, JsonRpcClientError> {
+        let body =
+            serde_json::to_vec(req_obj).map_err(|e| JsonRpcClientError::Decode(e.to_string()))?;
+        let mut node_req =
+            Request::post(uri).header(hyper::header::CONTENT_TYPE, "application/json");
+        if let Some(auth_token) = &self.auth_token {
+            node_req = node_req.header(
+                hyper::header::AUTHORIZATION,
+                format!("Bearer {}", auth_token),
+            );
+        }
+        let node_req = node_req
+            .body(Body::from(body))
+            .map_err(|e| JsonRpcClientError::Transport(e.to_string()))?;
+
+        tokio::time::timeout(Duration::from_millis(timeout), async {
+            let resp = self
+                .client
+                .request(node_req)
+                .await
+                .map_err(|e| JsonRpcClientError::Transport(format!("{}: {}", uri, e)))?;
+            let status = resp.status();
+            let body = hyper::body::aggregate(resp)
+                .await
+                .map_err(|e| JsonRpcClientError::Transport(format!("{}: {}", uri, e)))?;
+            let json: R = serde_json::from_reader(body.reader()).map_err(|e| {
+                match status.is_success() {
+                    // e.g. a load balancer in front of the node
+                    false => JsonRpcClientError::Transport(format!("{}: http {}", uri, status)),
+                    true => JsonRpcClientError::Decode(format!("{} {}: {}", uri, method, e)),
+                }
+            })?;
+
+            Ok(json)
+        })
+        .await
+        .map_err(|_| JsonRpcClientError::Timeout)?
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcResponseInternal {
+    #[serde(default)]
+    id: serde_json::Value,
+    result: Option<serde_json::Value>,
+    error: Option<JsonRpcError>,
+}
+
+impl JsonRpcResponseInternal {
+    fn into_result<R: DeserializeOwned>(self) -> Result<R, JsonRpcClientError> {
+        if let Some(err) = self.error {
+            return Err(JsonRpcClientError::Rpc(err));
+        }
+
+        match self.result {
+            Some(result) => serde_json::from_value(result)
+                .map_err(|e| JsonRpcClientError::Decode(e.to_string())),
+            None => Err(JsonRpcClientError::NoResult),
+        }
+    }
 }
 
+/// Invokes a `json-rpc` request with a new client and a timeout of 30 seconds.
 pub async fn jsonrpc_request<T: Serialize + Send + Sync, R: DeserializeOwned>(
     uri: &Uri,
     method: &str,
     params: T,
-) -> Result<R, String> {
-    let client = hyper::Client::new();
-    jsonrpc_request_client(30_000, &client, uri, method, params).await
+) -> Result<R, JsonRpcClientError> {
+    JsonRpcClient::default().request(uri, method, params).await
 }
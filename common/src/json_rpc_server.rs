@@ -0,0 +1,287 @@
+/// A json-rpc 2.0 server on top of hyper with typed method registration.
+use crate::json_rpc::JsonRpcError;
+use crate::json_rpc::JsonRpcResponse;
+use crate::json_rpc::JsonRpcResponseError;
+use hyper::body::HttpBody;
+use hyper::header::HeaderValue;
+use hyper::HeaderMap;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use subtle::ConstantTimeEq;
+
+type MethodFuture = Pin<Box<dyn Future<Output = Result<serde_json::Value, JsonRpcError>> + Send>>;
+type MethodHandler<S> = Box<dyn Fn(S, serde_json::Value) -> MethodFuture + Send + Sync>;
+
+/// Parameters for methods that do not take any, accepts `[]` and a missing `params` field.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoParams;
+
+impl<'de> Deserialize<'de> for NoParams {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        serde::de::IgnoredAny::deserialize(deserializer).map(|_| NoParams)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcServerRequest {
+    /// `None` for notifications, `Some(Value::Null)` for `"id": null`.
+    #[serde(default, deserialize_with = "deserialize_id")]
+    id: Option<serde_json::Value>,
+    method: String,
+    #[serde(default)]
+    params: serde_json::Value,
+}
+
+/// Tells an explicit `"id": null` apart from a missing `id`, which `Option` alone maps to `None`.
+fn deserialize_id<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<serde_json::Value>, D::Error> {
+    serde_json::Value::deserialize(deserializer).map(Some)
+}
+
+/// Dispatches json-rpc requests to the registered methods.
+/// Each method is invoked with a clone of the state `S`.
+pub struct JsonRpcServer<S> {
+    methods: HashMap<String, MethodHandler<S>>,
+    /// Requests with a larger or unknown body size are rejected.
+    max_body_size: u64,
+    /// If set, requests must contain `authorization: Bearer <auth_token>`.
+    auth_token: Option<String>,
+    cors_origin: HeaderValue,
+}
+
+impl<S: Clone + Send + Sync + 'static> Default for JsonRpcServer<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Clone + Send + Sync + 'static> JsonRpcServer<S> {
+    pub fn new() -> Self {
+        Self {
+            methods: HashMap::new(),
+            max_body_size: 1 << 20,
+            auth_token: None,
+            cors_origin: HeaderValue::from_static("*"),
+        }
+    }
+
+    /// Sets the maximum request body size in bytes.
+    pub fn with_max_body_size(mut self, max_body_size: u64) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Requires a bearer token for json-rpc requests if `auth_token` is set.
+    pub fn with_auth_token(mut self, auth_token: Option<String>) -> Self {
+        self.auth_token = auth_token;
+        self
+    }
+
+    /// Sets the `access-control-allow-origin` header, defaults to `*`.
+    pub fn with_cors_origin(mut self, cors_origin: HeaderValue) -> Self {
+        self.cors_origin = cors_origin;
+        self
+    }
+
+    /// Registers `handler` for `name`.
+    /// The json-rpc `params` are deserialized into `P`, e.g. a tuple for positional params.
+    /// Errors are mapped into a `JsonRpcError`, a `String` becomes a generic server error.
+    pub fn method<P, R, E, F, Fut>(mut self, name: &str, handler: F) -> Self
+    where
+        P: DeserializeOwned + Send + 'static,
+        R: Serialize + 'static,
+        E: Into<JsonRpcError> + 'static,
+        F: Fn(S, P) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, E>> + Send + 'static,
+    {
+        let handler: MethodHandler<S> = Box::new(move |state: S, params: serde_json::Value| {
+            let params: P = match serde_json::from_value(params) {
+                Ok(params) => params,
+                Err(err) => {
+                    let err = JsonRpcError::new(JsonRpcError::INVALID_PARAMS, err);
+                    return Box::pin(async move { Err(err) });
+                }
+            };
+            let fut = handler(state, params);
+
+            Box::pin(async move {
+                let result = fut.await.map_err(Into::into)?;
+                serde_json::to_value(result)
+                    .map_err(|e| JsonRpcError::new(JsonRpcError::INTERNAL_ERROR, e))
+            })
+        });
+        self.methods.insert(name.to_string(), handler);
+        self
+    }
+
+    /// Sets default headers for CORS requests.
+    pub fn set_headers(&self, headers: &mut HeaderMap, extended: bool) {
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+        headers.insert("access-control-allow-origin", self.cors_origin.clone());
+
+        if extended {
+            headers.insert(
+                "access-control-allow-methods",
+                HeaderValue::from_static("post, get, options"),
+            );
+            headers.insert(
+                "access-control-allow-headers",
+                HeaderValue::from_static(
+                    "origin, content-type, accept, x-requested-with, authorization",
+                ),
+            );
+            headers.insert("access-control-max-age", HeaderValue::from_static("300"));
+        }
+    }
+
+    /// Returns `true` if `req` carries the configured bearer token or no token is required.
+    pub fn is_authorized<B>(&self, req: &Request<B>) -> bool {
+        let auth_token = match &self.auth_token {
+            None => return true,
+            Some(auth_token) => auth_token,
+        };
+
+        req.headers()
+            .get(hyper::header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(|v| v.as_bytes().ct_eq(auth_token.as_bytes()).into())
+            .unwrap_or(false)
+    }
+
+    /// Invokes `method` with `params`.
+    pub async fn call(
+        &self,
+        state: S,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, JsonRpcError> {
+        match self.methods.get(method) {
+            Some(handler) => handler(state, params).await,
+            None => Err(JsonRpcError::new(
+                JsonRpcError::METHOD_NOT_FOUND,
+                "this method is not available",
+            )),
+        }
+    }
+
+    /// Handles a json-rpc `POST` request, including batches, or a CORS preflight `OPTIONS`
+    /// request.
+    pub async fn handle(&self, state: S, req: Request<Body>) -> Response<Body> {
+        if req.method() == Method::OPTIONS {
+            let mut resp = Response::default();
+            self.set_headers(resp.headers_mut(), true);
+            return resp;
+        }
+
+        if req.method() != Method::POST {
+            let mut resp = Response::default();
+            *resp.status_mut() = StatusCode::METHOD_NOT_ALLOWED;
+            return resp;
+        }
+
+        if !self.is_authorized(&req) {
+            let mut resp = Response::default();
+            *resp.status_mut() = StatusCode::UNAUTHORIZED;
+            self.set_headers(resp.headers_mut(), false);
+            return resp;
+        }
+
+        // limits the request size
+        let content_length = req
+            .body()
+            .size_hint()
+            .upper()
+            .unwrap_or(self.max_body_size + 1);
+        if content_length > self.max_body_size {
+            let mut resp = Response::new(Body::from("request too large"));
+            *resp.status_mut() = StatusCode::PAYLOAD_TOO_LARGE;
+            return resp;
+        }
+
+        let payload = match hyper::body::to_bytes(req.into_body()).await {
+            Err(err) => Some(error_response(
+                serde_json::Value::Null,
+                JsonRpcError::new(JsonRpcError::PARSE_ERROR, err),
+            )),
+            Ok(body) => match serde_json::from_slice::<serde_json::Value>(&body) {
+                Err(err) => Some(error_response(
+                    serde_json::Value::Null,
+                    JsonRpcError::new(JsonRpcError::PARSE_ERROR, err),
+                )),
+                Ok(serde_json::Value::Array(batch)) if batch.is_empty() => Some(error_response(
+                    serde_json::Value::Null,
+                    JsonRpcError::new(JsonRpcError::INVALID_REQUEST, "empty batch"),
+                )),
+                Ok(serde_json::Value::Array(batch)) => {
+                    let mut responses = Vec::with_capacity(batch.len());
+                    for req in batch {
+                        if let Some(resp) = self.handle_value(state.clone(), req).await {
+                            responses.push(resp);
+                        }
+                    }
+                    match responses.is_empty() {
+                        true => None,
+                        false => Some(serde_json::Value::Array(responses)),
+                    }
+                }
+                Ok(req) => self.handle_value(state, req).await,
+            },
+        };
+
+        let mut resp = match payload {
+            // only notifications
+            None => {
+                let mut resp = Response::default();
+                *resp.status_mut() = StatusCode::NO_CONTENT;
+                resp
+            }
+            Some(payload) => Response::new(Body::from(serde_json::to_vec(&payload).unwrap())),
+        };
+        self.set_headers(resp.headers_mut(), false);
+        resp
+    }
+
+    /// Handles a single request object.
+    /// Returns `None` for notifications.
+    async fn handle_value(&self, state: S, req: serde_json::Value) -> Option<serde_json::Value> {
+        let req: JsonRpcServerRequest = match serde_json::from_value(req) {
+            Ok(req) => req,
+            Err(err) => {
+                return Some(error_response(
+                    serde_json::Value::Null,
+                    JsonRpcError::new(JsonRpcError::INVALID_REQUEST, err),
+                ))
+            }
+        };
+        let result = self.call(state, &req.method, req.params).await;
+        let id = req.id?;
+
+        Some(match result {
+            Err(err) => error_response(id, err),
+            Ok(result) => serde_json::to_value(JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id,
+                result: Some(result),
+            })
+            .unwrap(),
+        })
+    }
+}
+
+/// Returns a json-rpc error response object.
+pub fn error_response(id: serde_json::Value, error: JsonRpcError) -> serde_json::Value {
+    serde_json::to_value(JsonRpcResponseError {
+        jsonrpc: "2.0".to_string(),
+        id,
+        error,
+    })
+    .unwrap()
+}
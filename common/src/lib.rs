@@ -1,2 +1,5 @@
+pub mod bridge;
+pub mod circuit_autogen;
 pub mod json_rpc;
+pub mod json_rpc_server;
 pub mod prover;
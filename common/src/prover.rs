@@ -1,6 +1,11 @@
+use crate::circuit_autogen::CIRCUIT_CONFIGS;
 use eth_types::{Bytes, U256};
 use serde::{Deserialize, Serialize};
 
+/// The number of accumulator limbs at the start of the aggregation proof instance.
+/// With `ProofRequestOptions::expose_instances` the circuit instances follow.
+pub const AGGREGATION_ACCUMULATOR_LEN: usize = 16;
+
 #[derive(Clone, Default, Serialize, Deserialize)]
 pub struct ProofResult {
     /// The halo2 transcript
@@ -15,6 +20,9 @@ pub struct ProofResult {
     pub duration: u32,
     /// Circuit name / identifier
     pub label: String,
+    /// Hex encoded sha256 of the circuit parameters used
+    #[serde(default)]
+    pub params_hash: String,
 }
 
 impl std::fmt::Debug for ProofResult {
@@ -25,6 +33,7 @@ impl std::fmt::Debug for ProofResult {
             .field("k", &self.k)
             .field("randomness", &format!("{}", &self.randomness))
             .field("duration", &self.duration)
+            .field("params_hash", &self.params_hash)
             .finish()
     }
 }
@@ -39,6 +48,21 @@ pub struct Proofs {
     pub aggregation: ProofResult,
     /// Gas used. Determines the upper ceiling for circuit parameters
     pub gas: u64,
+    /// The circuit proof of each block if `ProofRequestOptions::last_block` is set,
+    /// `circuit` is empty in this case and `aggregation` covers all blocks.
+    #[serde(default)]
+    pub blocks: Vec<BlockProof>,
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct BlockProof {
+    /// The block number
+    pub block: u64,
+    /// Gas used by the block
+    pub gas: u64,
+    /// Proof result for the circuit of this block,
+    /// `instance` contains the public inputs of the block
+    pub circuit: ProofResult,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -64,6 +88,14 @@ pub struct ProofRequestOptions {
     /// Runs the MockProver if proofing fails.
     #[serde(default = "default_bool")]
     pub mock_feedback: bool,
+    /// If set, proves the blocks `block..=last_block` with the same circuit parameters
+    /// and aggregates them into one proof. Requires `aggregate`.
+    #[serde(default)]
+    pub last_block: Option<u64>,
+    /// Exposes the instances of the circuit proofs as public inputs of the aggregation proof,
+    /// following the accumulator limbs. Requires `aggregate`.
+    #[serde(default)]
+    pub expose_instances: bool,
 }
 
 impl PartialEq for ProofRequestOptions {
@@ -74,6 +106,8 @@ impl PartialEq for ProofRequestOptions {
             && self.circuit == other.circuit
             && self.mock == other.mock
             && self.aggregate == other.aggregate
+            && self.last_block == other.last_block
+            && self.expose_instances == other.expose_instances
     }
 }
 
@@ -81,8 +115,91 @@ impl PartialEq for ProofRequestOptions {
 pub struct ProofRequest {
     pub options: ProofRequestOptions,
     pub result: Option<Result<Proofs, String>>,
-    /// A counter to keep track of changes of the `result` field
+    /// A counter to keep track of changes of the `result` and `lease` fields
     pub edition: u64,
+    /// Unix timestamp in seconds of when `result` was set
+    #[serde(default)]
+    pub completed_at: Option<u64>,
+    /// The node working on this task, if any.
+    #[serde(default)]
+    pub lease: Option<TaskLease>,
+    /// Set if `result` was merged from another node and had to be verified.
+    #[serde(default)]
+    pub verification: Option<ProofVerification>,
+}
+
+/// The outcome of verifying the `Proofs` of another node.
+/// A rejected result is not merged, the task keeps its local state.
+/// Not recorded if the proofs could not be verified, e.g. because the block rpc
+/// is not available.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofVerification {
+    /// The id of the node the result was merged from.
+    pub peer: String,
+    /// The edition of the task on `peer`.
+    pub edition: u64,
+    /// Unix timestamp in seconds.
+    pub verified_at: u64,
+    /// The reason if the proofs were rejected.
+    pub error: Option<String>,
+}
+
+/// Ownership of a pending task, renewed by the owner while it is working on the task.
+/// Expired leases can be taken over by other nodes.
+/// Note: expects the clocks of the nodes to be roughly in sync.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskLease {
+    /// The id of the node that owns the task.
+    pub owner: String,
+    /// Unix timestamp in seconds.
+    pub expires_at: u64,
+}
+
+/// The error of a `ProofRequest` that was cancelled with the `cancel` rpc method.
+pub const TASK_CANCELLED: &str = "cancelled";
+
+impl ProofRequest {
+    /// Returns `true` if the task was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(&self.result, Some(Err(err)) if err == TASK_CANCELLED)
+    }
+
+    /// Returns `true` if the result of `peer` at `edition` was already rejected.
+    pub fn is_rejected(&self, peer: &str, edition: u64) -> bool {
+        matches!(
+            &self.verification,
+            Some(v) if v.peer == peer && v.edition == edition && v.error.is_some()
+        )
+    }
+
+    /// Returns the owner of the lease if it is not expired at unix timestamp `now`.
+    pub fn lease_owner(&self, now: u64) -> Option<&str> {
+        self.lease
+            .as_ref()
+            .filter(|e| e.expires_at > now)
+            .map(|e| e.owner.as_str())
+    }
+
+    /// Returns `true` if `self` replaces `other` when merging tasks.
+    /// Concurrent changes of the same edition are resolved in favour of a result,
+    /// and otherwise in favour of the lease with the lowest owner id.
+    /// Lease renewals keep the edition, the later expiry of the same owner wins.
+    pub fn supersedes(&self, other: &ProofRequest) -> bool {
+        if self.edition != other.edition {
+            return self.edition > other.edition;
+        }
+
+        match (&self.result, &other.result) {
+            (Some(_), None) => true,
+            (None, None) => match (&self.lease, &other.lease) {
+                (Some(a), Some(b)) if a.owner == b.owner => a.expires_at > b.expires_at,
+                (Some(a), Some(b)) => a.owner < b.owner,
+                (Some(_), None) => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -91,6 +208,30 @@ pub struct NodeInformation {
     pub tasks: Vec<ProofRequest>,
 }
 
+/// Authenticates a request to another node with the shared peer secret.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerRequest {
+    /// The id of the requesting node.
+    pub node_id: String,
+    /// The address of the requesting node, used by `join`.
+    pub addr: Option<String>,
+    /// A random value, the response is signed together with it.
+    pub nonce: String,
+    /// Unix timestamp in seconds.
+    pub timestamp: u64,
+    /// Hex encoded HMAC-SHA256 over the method name and the fields above.
+    pub signature: String,
+}
+
+/// The response of `info`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignedNodeInformation {
+    /// The json encoded `NodeInformation`.
+    pub payload: String,
+    /// Hex encoded HMAC-SHA256 over the nonce of the request and `payload`.
+    pub signature: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NodeStatus {
     pub id: String,
@@ -113,6 +254,20 @@ pub struct CircuitConfig {
     pub keccak_padding: usize,
 }
 
+impl CircuitConfig {
+    /// Returns the smallest circuit parameters that can prove a block with `gas_used`.
+    pub fn for_gas(gas_used: u64) -> Option<&'static CircuitConfig> {
+        CIRCUIT_CONFIGS
+            .iter()
+            .find(|config| gas_used <= config.block_gas_limit as u64)
+    }
+
+    /// Returns the largest available circuit parameters.
+    pub fn max() -> &'static CircuitConfig {
+        &CIRCUIT_CONFIGS[CIRCUIT_CONFIGS.len() - 1]
+    }
+}
+
 fn default_bool() -> bool {
     false
 }
@@ -68,13 +68,32 @@ contract ZkEvmL1Bridge is
 
     assembly {
       // verify commitment hash
-      // TODO: support aggregation circuit
       if gt(proof.length, 64) {
+        // skip `blockHash, address`
+        let offset := add(proof.offset, 64)
+        // the verifier label is `<circuit>-<gas>` for circuit proofs
+        // and `<circuit>-<gas>-a<blocks?><i?>` for aggregation proofs
+        let label := calldataload(add(proof.offset, 32))
+        // length of the last `-` separated segment of the label
+        let segmentLen := 0
+        for {} lt(segmentLen, 20) { segmentLen := add(segmentLen, 1) } {
+          if eq(byte(sub(31, segmentLen), label), 0x2d) {
+            break
+          }
+        }
+        if and(gt(segmentLen, 0), eq(byte(sub(32, segmentLen), label), 0x61)) {
+          // only single block aggregation proofs with the exposed instance of the block (`-ai`)
+          // contain the commitment, it follows the accumulator limbs of their instance.
+          if iszero(and(eq(segmentLen, 2), eq(and(label, 0xffff), 0x6169))) {
+            revert(0, 0)
+          }
+          // AGGREGATION_ACCUMULATOR_LEN * 32
+          offset := add(offset, 512)
+        }
         // 5 * 32
         let len := 160
         let ptr := mload(64)
-        // skip `blockHash, address`
-        calldatacopy(ptr, add(proof.offset, 64), len)
+        calldatacopy(ptr, offset, len)
         let hash := keccak256(ptr, len)
         if iszero(eq(hash, expectedCommitmentHash)) {
           revert(0, 0)
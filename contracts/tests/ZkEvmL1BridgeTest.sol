@@ -0,0 +1,15 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity <0.9.0;
+
+import '../ZkEvmL1Bridge.sol';
+
+contract ZkEvmL1BridgeTest is ZkEvmL1Bridge {
+  function testFinalizeBlock (bytes32 commitmentHash, bytes calldata proof) external {
+    bytes32 blockHash;
+    assembly {
+      blockHash := calldataload(proof.offset)
+    }
+    commitments[blockHash] = commitmentHash;
+    this.finalizeBlock(proof);
+  }
+}
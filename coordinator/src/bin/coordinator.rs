@@ -1,27 +1,28 @@
 use clap::Parser;
 use coordinator::config::Config;
+use coordinator::error::Error;
 use coordinator::faucet::Faucet;
 use coordinator::shared_state::SharedState;
 use coordinator::utils::*;
 use env_logger::Env;
-use ethers_core::types::{Address, U64};
-use hyper::body::Buf;
+use ethers_core::types::{Address, Bytes, U64};
 use hyper::body::HttpBody;
 use hyper::client::HttpConnector;
-use hyper::header::HeaderValue;
 use hyper::service::{make_service_fn, service_fn};
-use hyper::HeaderMap;
 use hyper::{Body, Method, Request, Response, Server, StatusCode, Uri};
 use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::task::spawn;
 use tokio::time::sleep;
 use zkevm_common::json_rpc::JsonRpcError;
-use zkevm_common::json_rpc::JsonRpcRequest;
-use zkevm_common::json_rpc::JsonRpcResponse;
-use zkevm_common::json_rpc::JsonRpcResponseError;
+use zkevm_common::json_rpc_server::error_response;
+use zkevm_common::json_rpc_server::JsonRpcServer;
+use zkevm_common::json_rpc_server::NoParams;
 
 const EVENT_LOOP_COOLDOWN: Duration = Duration::from_millis(3000);
+/// maximum size of a request body
+const MAX_BODY_SIZE: u64 = 4 << 20;
 /// allowed jsonrpc methods
 const PROXY_ALLOWED_METHODS: [&str; 40] = [
     "eth_chainId",
@@ -66,27 +67,11 @@ const PROXY_ALLOWED_METHODS: [&str; 40] = [
     "debug_getModifiedAccountsByHash",
 ];
 
-fn set_headers(headers: &mut HeaderMap, extended: bool) {
-    headers.insert("content-type", HeaderValue::from_static("application/json"));
-    headers.insert("access-control-allow-origin", HeaderValue::from_static("*"));
-
-    if extended {
-        headers.insert(
-            "access-control-allow-methods",
-            HeaderValue::from_static("post, get, options"),
-        );
-        headers.insert(
-            "access-control-allow-headers",
-            HeaderValue::from_static("origin, content-type, accept, x-requested-with"),
-        );
-        headers.insert("access-control-max-age", HeaderValue::from_static("300"));
-    }
-}
-
 async fn handle_request(
     shared_state: SharedState,
     faucet: Option<Faucet>,
     client: hyper::Client<HttpConnector>,
+    rpc: Arc<JsonRpcServer<SharedState>>,
     req: Request<Body>,
 ) -> Result<Response<Body>, hyper::Error> {
     // TODO: support deflate content encoding
@@ -95,11 +80,12 @@ async fn handle_request(
     struct ProxyRequest {
         id: serde_json::Value,
         method: String,
+        #[serde(default)]
+        params: serde_json::Value,
     }
 
     {
         // limits the request size
-        const MAX_BODY_SIZE: u64 = 4 << 20;
         let response_content_length = match req.body().size_hint().upper() {
             Some(v) => v,
             None => MAX_BODY_SIZE + 1,
@@ -118,30 +104,59 @@ async fn handle_request(
             let mut resp = Response::new(Body::from(
                 serde_json::to_vec(&shared_state.rw.lock().await.chain_state).unwrap(),
             ));
-            set_headers(resp.headers_mut(), false);
+            rpc.set_headers(resp.headers_mut(), false);
             Ok(resp)
         }
 
         // geth upstream json-rpc
         (&Method::POST, "/") => {
-            let body_bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
-            let obj: ProxyRequest =
-                serde_json::from_slice(body_bytes.as_ref()).expect("ProxyRequest");
+            let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+            let obj: ProxyRequest = match serde_json::from_slice(body_bytes.as_ref()) {
+                Ok(obj) => obj,
+                Err(err) => {
+                    let err = error_response(
+                        serde_json::Value::Null,
+                        JsonRpcError::new(JsonRpcError::PARSE_ERROR, err),
+                    );
+                    let mut resp = Response::new(Body::from(serde_json::to_vec(&err).unwrap()));
+                    rpc.set_headers(resp.headers_mut(), false);
+                    return Ok(resp);
+                }
+            };
 
             // only allow allow the following methods and nothing else
             if !PROXY_ALLOWED_METHODS.iter().any(|e| **e == obj.method) {
-                let err = JsonRpcResponseError {
-                    jsonrpc: "2.0".to_string(),
-                    id: obj.id,
-                    error: JsonRpcError {
-                        code: -32601,
-                        message: "this method is not available".to_string(),
-                    },
-                };
-                let resp = Response::new(Body::from(serde_json::to_vec(&err).unwrap()));
+                let err = error_response(
+                    obj.id,
+                    JsonRpcError::new(
+                        JsonRpcError::METHOD_NOT_FOUND,
+                        "this method is not available",
+                    ),
+                );
+                let mut resp = Response::new(Body::from(serde_json::to_vec(&err).unwrap()));
+                rpc.set_headers(resp.headers_mut(), false);
                 return Ok(resp);
             }
 
+            // users must not send transactions to the message deliverer
+            if obj.method == "eth_sendRawTransaction" {
+                let res = match serde_json::from_value::<(Bytes,)>(obj.params) {
+                    Err(err) => Err(JsonRpcError::new(JsonRpcError::INVALID_PARAMS, err)),
+                    Ok((raw,)) => shared_state
+                        .ro
+                        .tx_filter
+                        .check_raw_transaction(raw.as_ref())
+                        .map_err(JsonRpcError::from),
+                };
+
+                if let Err(err) = res {
+                    let err = error_response(obj.id, err);
+                    let mut resp = Response::new(Body::from(serde_json::to_vec(&err).unwrap()));
+                    rpc.set_headers(resp.headers_mut(), false);
+                    return Ok(resp);
+                }
+            }
+
             let mut resp;
             {
                 // choose a serving node or none
@@ -159,21 +174,30 @@ async fn handle_request(
                     // Feeding the body via a reader() which was already consumed doesn't work either :/
                     let node_req = node_req
                         .header(hyper::header::CONTENT_TYPE, "application/json")
-                        .body(Body::from(body_bytes))
-                        .unwrap();
-                    resp = client.request(node_req).await.unwrap();
+                        .body(Body::from(body_bytes));
+                    let res = match node_req {
+                        Ok(node_req) => client.request(node_req).await.map_err(|e| e.to_string()),
+                        Err(err) => Err(err.to_string()),
+                    };
+                    resp = match res {
+                        Ok(resp) => resp,
+                        Err(err) => {
+                            log::warn!("proxy request: {}", err);
+                            let mut resp = Response::default();
+                            *resp.status_mut() = StatusCode::BAD_GATEWAY;
+                            resp
+                        }
+                    };
                 }
             }
 
-            set_headers(resp.headers_mut(), false);
+            rpc.set_headers(resp.headers_mut(), false);
             Ok(resp)
         }
 
         // serve CORS headers
-        (&Method::OPTIONS, "/") => {
-            let mut resp = Response::default();
-            set_headers(resp.headers_mut(), true);
-            Ok(resp)
+        (&Method::OPTIONS, "/") | (&Method::OPTIONS, "/rpc") => {
+            Ok(rpc.handle(shared_state, req).await)
         }
 
         // returns 503 if faucet is disabled else 200 and enqueues a faucet requests
@@ -183,10 +207,17 @@ async fn handle_request(
             let receiver = req
                 .uri()
                 .query()
-                .expect("uri query")
-                .parse::<Address>()
-                .expect("receiver address");
+                .and_then(|query| query.parse::<Address>().ok());
             let mut resp = Response::default();
+            let receiver = match receiver {
+                Some(receiver) => receiver,
+                None => {
+                    *resp.body_mut() = Body::from("expected receiver address");
+                    *resp.status_mut() = StatusCode::BAD_REQUEST;
+                    rpc.set_headers(resp.headers_mut(), false);
+                    return Ok(resp);
+                }
+            };
 
             match faucet {
                 None => {
@@ -198,61 +229,12 @@ async fn handle_request(
                 }
             }
 
-            set_headers(resp.headers_mut(), false);
+            rpc.set_headers(resp.headers_mut(), false);
             Ok(resp)
         }
 
-        // coordinator rpc
-        // TODO: protect this interface from public consumption
-        (&Method::POST, "/rpc") => {
-            let body_bytes = hyper::body::aggregate(req.into_body())
-                .await
-                .unwrap()
-                .reader();
-            let json_req: Result<JsonRpcRequest<Vec<serde_json::Value>>, serde_json::Error> =
-                serde_json::from_reader(body_bytes);
-
-            if let Err(err) = json_req {
-                let payload = serde_json::to_vec(&JsonRpcResponseError {
-                    jsonrpc: "2.0".to_string(),
-                    id: 0.into(),
-                    error: JsonRpcError {
-                        // parser error
-                        code: -32700,
-                        message: err.to_string(),
-                    },
-                })
-                .unwrap();
-                let mut resp = Response::new(Body::from(payload));
-                set_headers(resp.headers_mut(), false);
-                return Ok(resp);
-            }
-
-            let json_req = json_req.unwrap();
-            let result: Result<serde_json::Value, String> =
-                handle_method(json_req.method.as_str(), &json_req.params, &shared_state).await;
-            let payload = match result {
-                Err(err) => {
-                    serde_json::to_vec(&JsonRpcResponseError {
-                        jsonrpc: "2.0".to_string(),
-                        id: json_req.id,
-                        error: JsonRpcError {
-                            // internal server error
-                            code: -32000,
-                            message: err,
-                        },
-                    })
-                }
-                Ok(val) => serde_json::to_vec(&JsonRpcResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id: json_req.id,
-                    result: Some(val),
-                }),
-            };
-            let mut resp = Response::new(Body::from(payload.unwrap()));
-            set_headers(resp.headers_mut(), false);
-            Ok(resp)
-        }
+        // coordinator rpc, requires `rpc_auth_token` if configured
+        (&Method::POST, "/rpc") => Ok(rpc.handle(shared_state, req).await),
 
         // everything else
         _ => {
@@ -266,7 +248,7 @@ async fn handle_request(
 /// Discovers healthy nodes via DNS service discovery.
 /// If nodes are discovered but are not up-to-date, then this function attempts to choose a
 /// fallback node.
-async fn check_nodes(ctx: SharedState, client: hyper::Client<HttpConnector>) {
+async fn check_nodes(ctx: SharedState, client: JsonRpcClient) -> Result<(), Error> {
     let server_nodes = ctx.config.lock().await.rpc_server_nodes.clone();
     let head_hash = ctx.rw.lock().await.chain_state.head_block_hash;
     let mut nodes = Vec::new();
@@ -274,12 +256,19 @@ async fn check_nodes(ctx: SharedState, client: hyper::Client<HttpConnector>) {
     let mut fallback_node_num = U64::zero();
     let mut addrs = server_nodes
         .to_socket_addrs()
-        .unwrap()
+        .map_err(|e| Error::RpcTransport(format!("{}: {}", server_nodes, e)))?
         .collect::<Vec<SocketAddr>>();
     addrs.sort_unstable();
     for addr in addrs {
-        let uri = Uri::try_from(format!("http://{}", addr)).unwrap();
-        let header = get_chain_head(&client, &uri).await;
+        let uri = Uri::try_from(format!("http://{}", addr))
+            .map_err(|e| Error::Other(format!("{}: {}", addr, e)))?;
+        let header = match get_chain_head(&client, &uri).await {
+            Ok(header) => header,
+            Err(err) => {
+                log::debug!("skipping unavailable node: {} {}", uri, err);
+                continue;
+            }
+        };
 
         // use the most advanced node as fallback
         if header.number >= fallback_node_num {
@@ -297,55 +286,65 @@ async fn check_nodes(ctx: SharedState, client: hyper::Client<HttpConnector>) {
 
     // update nodes
     let mut rw = ctx.rw.lock().await;
-    if nodes.is_empty() && fallback_node_uri.is_some() {
-        nodes.push(fallback_node_uri.unwrap());
-        if rw.nodes != nodes {
-            log::info!("using {} as fallback node", nodes[0]);
+    match fallback_node_uri {
+        Some(fallback_node_uri) if nodes.is_empty() => {
+            nodes.push(fallback_node_uri);
+            if rw.nodes != nodes {
+                log::info!("using {} as fallback node", nodes[0]);
+            }
+        }
+        _ => {
+            if rw.nodes.len() != nodes.len() {
+                log::info!("found {} ready rpc nodes", nodes.len());
+            }
         }
-    } else if rw.nodes.len() != nodes.len() {
-        log::info!("found {} ready rpc nodes", nodes.len());
     }
     rw.nodes = nodes;
+
+    Ok(())
 }
 
-async fn event_loop(ctx: SharedState, _client: hyper::Client<HttpConnector>) {
+async fn event_loop(ctx: SharedState, _client: JsonRpcClient) -> Result<(), Error> {
     // TODO: split sync,mine into own task
 
-    ctx.sync().await;
-    ctx.mine().await;
-    ctx.submit_blocks().await;
-    ctx.finalize_blocks().await.expect("finalize_blocks");
-    ctx.relay_to_l1().await;
-}
+    ctx.sync().await?;
+    ctx.mine().await?;
+    ctx.submit_blocks().await?;
+    ctx.finalize_blocks().await?;
+    ctx.relay_to_l1().await?;
 
-async fn handle_method(
-    method: &str,
-    params: &[serde_json::Value],
-    shared_state: &SharedState,
-) -> Result<serde_json::Value, String> {
-    match method {
-        "config" => {
-            if !shared_state.config.lock().await.unsafe_rpc {
-                return Err("this method is disabled".to_string());
-            }
-
-            let config = match params.get(0) {
-                Some(options) => {
-                    let options: Config =
-                        serde_json::from_value(options.to_owned()).map_err(|e| e.to_string())?;
+    Ok(())
+}
 
-                    shared_state.set_config(options.clone()).await;
-                    options
+/// Registers the coordinator json-rpc methods.
+fn rpc_server(config: &Config) -> JsonRpcServer<SharedState> {
+    JsonRpcServer::new()
+        .with_max_body_size(MAX_BODY_SIZE)
+        .with_auth_token(config.rpc_auth_token.clone())
+        // returns the current configuration and sets it if given
+        .method(
+            "config",
+            |shared_state: SharedState, options: Vec<Config>| async move {
+                if !shared_state.config.lock().await.unsafe_rpc {
+                    return Err("this method is disabled".to_string());
                 }
-                None => shared_state.get_config().await,
-            };
-
-            // return the current configuration
-            Ok(serde_json::to_value(config).unwrap())
-        }
 
-        _ => Err("this method is not available".to_string()),
-    }
+                match options.into_iter().next() {
+                    Some(options) => {
+                        shared_state.set_config(options.clone()).await;
+                        Ok(options)
+                    }
+                    None => Ok(shared_state.get_config().await),
+                }
+            },
+        )
+        // returns the L1 > L2 messages that could not be delivered
+        .method(
+            "failedMessages",
+            |shared_state: SharedState, _: NoParams| async move {
+                Ok::<_, String>(shared_state.rw.lock().await.l1_failed_messages.clone())
+            },
+        )
 }
 
 #[tokio::main]
@@ -353,9 +352,11 @@ async fn main() {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
     let config = Config::parse();
-    let shared_state = SharedState::new(&config).await;
+    let shared_state = SharedState::new(&config)
+        .await
+        .expect("initialize shared state");
 
-    shared_state.init().await;
+    shared_state.init().await.expect("init");
 
     let faucet: Option<Faucet> = if config.enable_faucet {
         Some(Faucet::default())
@@ -368,6 +369,7 @@ async fn main() {
     {
         let addr = config.listen;
         let client = hyper::Client::new();
+        let rpc = Arc::new(rpc_server(&config));
         let shared_state = shared_state.clone();
         let faucet = faucet.clone();
         // start the http server
@@ -376,8 +378,15 @@ async fn main() {
                 let shared_state = shared_state.clone();
                 let faucet = faucet.clone();
                 let client = client.clone();
+                let rpc = rpc.clone();
                 let service = service_fn(move |req| {
-                    handle_request(shared_state.clone(), faucet.clone(), client.to_owned(), req)
+                    handle_request(
+                        shared_state.clone(),
+                        faucet.clone(),
+                        client.to_owned(),
+                        rpc.clone(),
+                        req,
+                    )
                 });
 
                 async move { Ok::<_, hyper::Error>(service) }
@@ -392,7 +401,7 @@ async fn main() {
     {
         let ctx = shared_state.clone();
         let h1 = spawn(async move {
-            let client = hyper::Client::new();
+            let client = JsonRpcClient::new(RPC_REQUEST_TIMEOUT);
             loop {
                 log::debug!("spawning event_loop task");
 
@@ -400,8 +409,10 @@ async fn main() {
                     // the event_loop handles l1/l2 chain events and chain progression
                     let res = spawn(event_loop(ctx.clone(), client.to_owned())).await;
 
-                    if let Err(err) = res {
-                        log::error!("event_loop: {}", err);
+                    match res {
+                        Err(err) => log::error!("event_loop: {}", err),
+                        Ok(Err(err)) => log::error!("event_loop: {}", err),
+                        Ok(Ok(())) => {}
                     }
                 }
 
@@ -413,13 +424,17 @@ async fn main() {
                     let res = spawn(async move {
                         if let Some(_faucet) = &faucet {
                             // only consume up to 3 items each time
-                            _faucet.drain(ctx, 3).await;
+                            _faucet.drain(ctx, 3).await?;
                         }
+
+                        Ok::<_, Error>(())
                     })
                     .await;
 
-                    if let Err(err) = res {
-                        log::error!("faucet: {}", err);
+                    match res {
+                        Err(err) => log::error!("faucet: {}", err),
+                        Ok(Err(err)) => log::error!("faucet: {}", err),
+                        Ok(Ok(())) => {}
                     }
                 }
 
@@ -429,13 +444,15 @@ async fn main() {
 
         let ctx = shared_state.clone();
         let h2 = spawn(async move {
-            let client = hyper::Client::new();
+            let client = JsonRpcClient::new(RPC_REQUEST_TIMEOUT);
             loop {
                 log::debug!("spawning check_nodes task");
                 let res = spawn(check_nodes(ctx.clone(), client.to_owned())).await;
 
-                if let Err(err) = res {
-                    log::error!("task: {}", err);
+                match res {
+                    Err(err) => log::error!("task: {}", err),
+                    Ok(Err(err)) => log::error!("check_nodes: {}", err),
+                    Ok(Ok(())) => {}
                 }
 
                 sleep(Duration::from_millis(100)).await;
@@ -0,0 +1,259 @@
+use crate::config::Config;
+use crate::error::Error;
+use crate::structs::*;
+use crate::utils::*;
+use ethers_core::types::{Address, Block, Bytes, Transaction, H256};
+use ethers_core::utils::keccak256;
+use hmac::{Hmac, Mac};
+use hyper::Uri;
+use sha2::Sha256;
+use std::time::SystemTime;
+
+/// Produces L2 blocks on the execution node.
+#[derive(Clone, Debug)]
+pub enum BlockBuilder {
+    /// Uses the non-standard `miner_init`, `miner_sealBlock` and `miner_setHead` methods
+    /// of the L2 rpc endpoint.
+    Miner,
+    /// Uses `engine_forkchoiceUpdatedV1`, `engine_getPayloadV1` and `engine_newPayloadV1`
+    /// of the authenticated Engine API endpoint `url`.
+    /// If `tx_list` is set, blocks with explicit transactions, e.g. L1 messages or blocks limited
+    /// by the circuit capacity, are built with the `transactions` and `noTxPool` payload
+    /// attributes extension of the execution client.
+    /// Otherwise only the standard payload attributes are used, as supported by stock
+    /// execution clients. Explicit transactions are then submitted to the transaction pool
+    /// of the node, which may add other pending transactions to the block.
+    Engine {
+        url: Uri,
+        jwt_secret: H256,
+        tx_list: bool,
+    },
+}
+
+impl BlockBuilder {
+    pub fn from_config(config: &Config) -> Result<Self, Error> {
+        match &config.l2_engine_url {
+            None => Ok(Self::Miner),
+            Some(url) => {
+                let jwt_secret = config.l2_engine_jwt_secret.ok_or_else(|| {
+                    Error::Other("l2_engine_url requires l2_engine_jwt_secret".to_string())
+                })?;
+
+                Ok(Self::Engine {
+                    url: url.clone(),
+                    jwt_secret,
+                    tx_list: config.l2_engine_tx_list,
+                })
+            }
+        }
+    }
+
+    /// Prepares the node for block production, e.g. enabling the transaction pool.
+    /// Should be called regularly to account for node restarts.
+    pub async fn init(&self, client: &JsonRpcClient, l2_rpc_url: &Uri) -> Result<(), Error> {
+        match self {
+            Self::Miner => {
+                let _: Option<Address> = rpc_request(client, l2_rpc_url, "miner_init", ())
+                    .await
+                    .unwrap_or_default();
+                Ok(())
+            }
+            Self::Engine { .. } => Ok(()),
+        }
+    }
+
+    /// Creates a new block on top of `chain_state.head_block_hash` without making it canonical.
+    /// If `transactions` is `Some` then any transaction errors
+    /// are returned as `Err`. Otherwise it draws from the transaction pool and reverted
+    /// transactions are not considered to be errors.
+    pub async fn build_block(
+        &self,
+        client: &JsonRpcClient,
+        l2_rpc_url: &Uri,
+        chain_state: &ForkchoiceStateV1,
+        timestamp: u64,
+        transactions: Option<&Vec<Bytes>>,
+    ) -> Result<Block<Transaction>, Error> {
+        match self {
+            Self::Miner => {
+                rpc_request(
+                    client,
+                    l2_rpc_url,
+                    "miner_sealBlock",
+                    [SealBlockRequest {
+                        parent: &chain_state.head_block_hash,
+                        random: &H256::zero(),
+                        timestamp: &timestamp.into(),
+                        transactions,
+                    }],
+                )
+                .await
+            }
+            Self::Engine {
+                url,
+                jwt_secret,
+                tx_list,
+            } => {
+                let engine_client = client
+                    .clone()
+                    .with_auth_token(Some(engine_jwt(jwt_secret, unix_timestamp())));
+                if let (false, Some(transactions)) = (tx_list, transactions) {
+                    // the node draws them from its transaction pool
+                    for raw_tx in transactions.iter() {
+                        submit_transaction(client, l2_rpc_url, raw_tx).await?;
+                    }
+                }
+                let attributes = PayloadAttributesV1 {
+                    timestamp: timestamp.into(),
+                    prev_randao: H256::zero(),
+                    suggested_fee_recipient: Address::zero(),
+                    transactions: transactions.filter(|_| *tx_list),
+                    no_tx_pool: *tx_list && transactions.is_some(),
+                };
+                let resp: ForkchoiceUpdatedResponse = rpc_request(
+                    &engine_client,
+                    url,
+                    "engine_forkchoiceUpdatedV1",
+                    (chain_state, attributes),
+                )
+                .await?;
+                check_payload_status("engine_forkchoiceUpdatedV1", &resp.payload_status)?;
+                let payload_id = resp.payload_id.ok_or_else(|| {
+                    Error::Decode("engine_forkchoiceUpdatedV1: missing payloadId".to_string())
+                })?;
+
+                let payload: serde_json::Value =
+                    rpc_request(&engine_client, url, "engine_getPayloadV1", [payload_id]).await?;
+                let block_hash: H256 = payload
+                    .get("blockHash")
+                    .cloned()
+                    .and_then(|v| serde_json::from_value(v).ok())
+                    .ok_or_else(|| {
+                        Error::Decode("engine_getPayloadV1: missing blockHash".to_string())
+                    })?;
+
+                let status: PayloadStatusV1 =
+                    rpc_request(&engine_client, url, "engine_newPayloadV1", [&payload]).await?;
+                check_payload_status("engine_newPayloadV1", &status)?;
+
+                let block: Block<Transaction> =
+                    rpc_request(client, l2_rpc_url, "eth_getBlockByHash", (block_hash, true))
+                        .await?;
+                if let Some(transactions) = transactions {
+                    let included = match tx_list {
+                        true => block.transactions.len(),
+                        // the block may contain other transactions of the pool
+                        false => transactions
+                            .iter()
+                            .map(|raw_tx| H256::from(keccak256(raw_tx)))
+                            .filter(|hash| block.transactions.iter().any(|tx| tx.hash == *hash))
+                            .count(),
+                    };
+                    if included != transactions.len() {
+                        return Err(Error::Other(format!(
+                            "engine: {} of {} transactions included",
+                            included,
+                            transactions.len()
+                        )));
+                    }
+                }
+
+                Ok(block)
+            }
+        }
+    }
+
+    /// Makes `chain_state.head_block_hash` the canonical chain head.
+    pub async fn set_head(
+        &self,
+        client: &JsonRpcClient,
+        l2_rpc_url: &Uri,
+        chain_state: &ForkchoiceStateV1,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Miner => {
+                let res: bool = rpc_request(
+                    client,
+                    l2_rpc_url,
+                    "miner_setHead",
+                    [chain_state.head_block_hash],
+                )
+                .await?;
+
+                if !res {
+                    return Err(Error::Other(format!(
+                        "unable to set chain head to {:?}",
+                        chain_state.head_block_hash
+                    )));
+                }
+
+                Ok(())
+            }
+            Self::Engine {
+                url, jwt_secret, ..
+            } => {
+                let engine_client = client
+                    .clone()
+                    .with_auth_token(Some(engine_jwt(jwt_secret, unix_timestamp())));
+                let resp: ForkchoiceUpdatedResponse = rpc_request(
+                    &engine_client,
+                    url,
+                    "engine_forkchoiceUpdatedV1",
+                    (chain_state, Option::<()>::None),
+                )
+                .await?;
+
+                check_payload_status("engine_forkchoiceUpdatedV1", &resp.payload_status)
+            }
+        }
+    }
+}
+
+/// Adds `raw_tx` to the transaction pool of the node, it is fine if it is already known.
+async fn submit_transaction(
+    client: &JsonRpcClient,
+    l2_rpc_url: &Uri,
+    raw_tx: &Bytes,
+) -> Result<(), Error> {
+    let res: Result<H256, Error> =
+        rpc_request(client, l2_rpc_url, "eth_sendRawTransaction", [raw_tx]).await;
+    match res {
+        Err(Error::Rpc { message, .. }) if message.contains("already known") => Ok(()),
+        res => res.map(|_| ()),
+    }
+}
+
+fn check_payload_status(method: &str, status: &PayloadStatusV1) -> Result<(), Error> {
+    match status.status.as_str() {
+        "VALID" => Ok(()),
+        // the node is not ready yet
+        "SYNCING" | "ACCEPTED" => Err(Error::NotReady(format!("{}: {}", method, status.status))),
+        _ => Err(Error::Other(format!(
+            "{}: {} {}",
+            method,
+            status.status,
+            status.validation_error.as_deref().unwrap_or_default()
+        ))),
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("time")
+        .as_secs()
+}
+
+/// Returns a HS256 JWT with the `iat` claim as required by the Engine API authentication.
+pub fn engine_jwt(secret: &H256, iat: u64) -> String {
+    let header = base64::encode_config(r#"{"alg":"HS256","typ":"JWT"}"#, base64::URL_SAFE_NO_PAD);
+    let claims = base64::encode_config(format!(r#"{{"iat":{}}}"#, iat), base64::URL_SAFE_NO_PAD);
+    let message = format!("{}.{}", header, claims);
+
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key size");
+    mac.update(message.as_bytes());
+    let signature = base64::encode_config(mac.finalize().into_bytes(), base64::URL_SAFE_NO_PAD);
+
+    format!("{}.{}", message, signature)
+}
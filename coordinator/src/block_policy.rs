@@ -0,0 +1,82 @@
+use crate::config::Config;
+
+/// Decides when the sequencer seals a new L2 block.
+/// All durations and timestamps are in seconds.
+#[derive(Clone, Debug, Default)]
+pub struct BlockPolicy {
+    /// Minimum time between two blocks.
+    pub block_time: u64,
+    /// Seal a block, even if empty, if no block was produced for this long.
+    pub heartbeat: Option<u64>,
+    /// Minimum number of pending transactions.
+    pub min_txs: u64,
+    /// Minimum gas of the pending transactions.
+    pub min_gas: u64,
+    /// Seal a block if transactions are pending for this long, regardless of `min_txs` and
+    /// `min_gas`.
+    pub max_wait: Option<u64>,
+    /// Maximum number of seconds a block timestamp may be ahead of the local clock.
+    pub max_timestamp_drift: u64,
+}
+
+/// The state of the transaction pool and the chain as seen by the sequencer.
+#[derive(Clone, Debug, Default)]
+pub struct PendingBlock {
+    /// Local time of the last produced block.
+    pub last_block_at: u64,
+    /// Local time since when transactions are pending, if any.
+    pub pending_since: Option<u64>,
+    pub pending_txs: u64,
+    /// Only available if `BlockPolicy::min_gas` is used.
+    pub pending_gas: u64,
+}
+
+impl BlockPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            block_time: config.block_time,
+            heartbeat: config.block_heartbeat,
+            min_txs: config.block_min_txs,
+            min_gas: config.block_min_gas,
+            max_wait: config.block_max_wait,
+            max_timestamp_drift: config.block_max_timestamp_drift,
+        }
+    }
+
+    /// Returns `true` if a block should be sealed at local time `now`.
+    pub fn should_seal(&self, now: u64, pending: &PendingBlock) -> bool {
+        let since_last_block = now.saturating_sub(pending.last_block_at);
+        if since_last_block < self.block_time {
+            return false;
+        }
+
+        if pending.pending_txs > 0 {
+            if pending.pending_txs >= self.min_txs && pending.pending_gas >= self.min_gas {
+                return true;
+            }
+
+            if let (Some(max_wait), Some(pending_since)) = (self.max_wait, pending.pending_since) {
+                if now.saturating_sub(pending_since) >= max_wait {
+                    return true;
+                }
+            }
+        }
+
+        matches!(self.heartbeat, Some(heartbeat) if since_last_block >= heartbeat)
+    }
+
+    /// Returns the timestamp for the next block given the local time `now` and the timestamp
+    /// of the previous block, or `None` if it would drift too far into the future.
+    pub fn next_timestamp(&self, now: u64, prev_timestamp: u64) -> Option<u64> {
+        let ts = if now <= prev_timestamp {
+            prev_timestamp + 1
+        } else {
+            now
+        };
+
+        match ts.saturating_sub(now) > self.max_timestamp_drift {
+            true => None,
+            false => Some(ts),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use ethers_core::types::{Transaction, H256};
+use std::collections::HashSet;
+use zkevm_common::prover::CircuitConfig;
+
+/// Resources of a block that are limited by the circuit parameters.
+/// `gas` is the sum of the transaction gas limits and therefore an upper bound,
+/// the gas used is only known after execution and has to be checked on the sealed block.
+/// `CircuitConfig::max_rws` can not be checked before execution either, it is covered by
+/// the gas used because the prover chooses the circuit parameters with `CircuitConfig::for_gas`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CircuitUsage {
+    pub txs: usize,
+    pub calldata: usize,
+    pub bytecode: usize,
+    pub gas: u64,
+}
+
+impl CircuitUsage {
+    /// The usage of a single transaction.
+    pub fn of(tx: &Transaction) -> Self {
+        let input_len = tx.input.as_ref().len();
+        Self {
+            txs: 1,
+            calldata: input_len,
+            // the initcode of contract creations ends up in the bytecode table
+            bytecode: if tx.to.is_none() { input_len } else { 0 },
+            gas: tx.gas.try_into().unwrap_or(u64::MAX),
+        }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        Self {
+            txs: self.txs + other.txs,
+            calldata: self.calldata + other.calldata,
+            bytecode: self.bytecode + other.bytecode,
+            gas: self.gas.saturating_add(other.gas),
+        }
+    }
+
+    /// Returns `true` if a block with this usage can be proven with `config`.
+    pub fn fits(&self, config: &CircuitConfig) -> bool {
+        self.fits_without_gas(config) && self.gas <= config.block_gas_limit as u64
+    }
+
+    /// Like `fits` but ignores `gas`, these limits do not depend on the execution.
+    pub fn fits_without_gas(&self, config: &CircuitConfig) -> bool {
+        self.txs <= config.max_txs
+            && self.calldata <= config.max_calldata
+            && self.bytecode <= config.max_bytecode
+    }
+}
+
+/// The transactions for the next block.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionSelection {
+    /// Transactions that fit into the circuit, in the order they should be included.
+    pub included: Vec<Transaction>,
+    /// Transactions that fit on their own but not into this block anymore.
+    pub deferred: Vec<H256>,
+    /// Transactions that can not be proven with any circuit parameters.
+    pub unprovable: Vec<H256>,
+    /// Transactions that were excluded by the caller.
+    pub excluded: Vec<H256>,
+    pub usage: CircuitUsage,
+    /// The gas limit of `included` exceeds `block_gas_limit`,
+    /// the gas used has to be checked after sealing the block.
+    pub unchecked_gas: bool,
+}
+
+impl TransactionSelection {
+    /// Returns `true` if all transactions were included.
+    pub fn is_complete(&self) -> bool {
+        self.deferred.is_empty() && self.unprovable.is_empty() && self.excluded.is_empty()
+    }
+}
+
+/// Selects transactions from `pending` for a block that can be proven with `config`.
+/// `pending` are expected to be grouped by sender and ordered by nonce.
+/// Transactions in `excluded` are skipped. If a transaction of a sender is not included,
+/// then all following transactions of the same sender are not included either.
+/// A transaction with a gas limit above `block_gas_limit` may still use less gas,
+/// it is only included on its own, see `TransactionSelection::unchecked_gas`.
+pub fn select_transactions(
+    config: &CircuitConfig,
+    pending: &[Transaction],
+    excluded: &HashSet<H256>,
+) -> TransactionSelection {
+    let mut selection = TransactionSelection::default();
+    let mut skipped_senders = HashSet::new();
+
+    for tx in pending {
+        if excluded.contains(&tx.hash) {
+            selection.excluded.push(tx.hash);
+            skipped_senders.insert(tx.from);
+            continue;
+        }
+
+        let tx_usage = CircuitUsage::of(tx);
+        if !tx_usage.fits_without_gas(config) {
+            selection.unprovable.push(tx.hash);
+            skipped_senders.insert(tx.from);
+            continue;
+        }
+
+        let usage = selection.usage.add(&tx_usage);
+        if !skipped_senders.contains(&tx.from)
+            && selection.included.is_empty()
+            && !tx_usage.fits(config)
+        {
+            selection.usage = usage;
+            selection.included.push(tx.clone());
+            selection.unchecked_gas = true;
+            continue;
+        }
+        if skipped_senders.contains(&tx.from) || !usage.fits(config) {
+            selection.deferred.push(tx.hash);
+            skipped_senders.insert(tx.from);
+            continue;
+        }
+
+        selection.usage = usage;
+        selection.included.push(tx.clone());
+    }
+
+    selection
+}
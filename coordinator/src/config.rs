@@ -1,5 +1,5 @@
 use clap::Parser;
-use ethers_core::types::Address;
+use ethers_core::types::{Address, H256};
 use hyper::Uri;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
@@ -52,14 +52,37 @@ pub struct Config {
     /// L2 RPC node in http URL format.
     pub l2_rpc_url: Uri,
 
+    #[clap(long, env = "COORDINATOR_L2_ENGINE_URL")]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    /// Authenticated Engine API endpoint of the L2 node.
+    /// If set, blocks are built with `engine_*` methods instead of `miner_*`.
+    pub l2_engine_url: Option<Uri>,
+
+    #[clap(long, env = "COORDINATOR_L2_ENGINE_JWT_SECRET")]
+    /// Hex encoded 32 byte secret for the Engine API JWT authentication.
+    pub l2_engine_jwt_secret: Option<H256>,
+
+    #[clap(long, env = "COORDINATOR_L2_ENGINE_TX_LIST", default_value_t = false)]
+    /// Use the non-standard `transactions` and `noTxPool` payload attributes of the execution
+    /// client behind `l2_engine_url` for blocks with explicit transactions, e.g. L1 messages or
+    /// blocks limited by the circuit capacity.
+    /// Otherwise these transactions are submitted to the transaction pool of a stock client,
+    /// which may include other pending transactions as well. The block gas limit of the L2 chain
+    /// then has to fit the circuit capacity.
+    pub l2_engine_tx_list: bool,
+
     #[clap(long, env = "COORDINATOR_PROVER_RPCD_URL")]
     #[serde_as(as = "DisplayFromStr")]
     /// Prover RPC node URL.
     pub prover_rpcd_url: Uri,
 
+    #[clap(long, env = "COORDINATOR_PROVER_RPCD_AUTH_TOKEN")]
+    /// Bearer token for the prover RPC node, if it requires one.
+    pub prover_rpcd_auth_token: Option<String>,
+
     #[clap(long, env = "COORDINATOR_PARAMS_PATH")]
     /// Parameters file or directory to use for the prover requests.
-    /// Otherwise generates them on the fly.
+    /// Otherwise the prover generates them on the fly, if enabled with `PROVERD_ALLOW_SETUP`.
     pub params_path: Option<String>,
 
     #[clap(long, env = "COORDINATOR_CIRCUIT_NAME")]
@@ -71,9 +94,58 @@ pub struct Config {
     /// Signals the prover to aggregate the circuit proof
     pub aggregate_proof: bool,
 
+    #[clap(long, env = "COORDINATOR_EXPOSE_INSTANCES", default_value_t = false)]
+    /// Signals the prover to expose the public inputs of the block in the aggregation proof.
+    /// Requires `COORDINATOR_AGGREGATE_PROOF`.
+    pub expose_instances: bool,
+
+    #[clap(long, env = "COORDINATOR_BLOCK_TIME", default_value_t = 0)]
+    /// Minimum time in seconds between two L2 blocks.
+    pub block_time: u64,
+
+    #[clap(long, env = "COORDINATOR_BLOCK_HEARTBEAT")]
+    /// Seals a (possibly empty) L2 block if no block was produced for this many seconds.
+    pub block_heartbeat: Option<u64>,
+
+    #[clap(long, env = "COORDINATOR_BLOCK_MIN_TXS", default_value_t = 1)]
+    /// Minimum number of pending transactions before sealing a L2 block.
+    pub block_min_txs: u64,
+
+    #[clap(long, env = "COORDINATOR_BLOCK_MIN_GAS", default_value_t = 0)]
+    /// Minimum gas of the pending transactions before sealing a L2 block.
+    pub block_min_gas: u64,
+
+    #[clap(long, env = "COORDINATOR_BLOCK_MAX_WAIT")]
+    /// Seals a L2 block if transactions are pending for this many seconds,
+    /// even if `block_min_txs` or `block_min_gas` is not reached.
+    pub block_max_wait: Option<u64>,
+
+    #[clap(
+        long,
+        env = "COORDINATOR_BLOCK_MAX_TIMESTAMP_DRIFT",
+        default_value_t = 60
+    )]
+    /// Maximum number of seconds a L2 block timestamp may be ahead of the local clock.
+    pub block_max_timestamp_drift: u64,
+
+    #[clap(long, env = "COORDINATOR_L1_MESSAGE_MAX_AGE", default_value_t = 60)]
+    /// L1 > L2 messages that are queued for this many seconds are delivered
+    /// before any other message in the next L2 block.
+    pub l1_message_max_age: u64,
+
+    #[clap(long, env = "COORDINATOR_L1_MESSAGE_MAX_ATTEMPTS", default_value_t = 3)]
+    /// Number of failed delivery attempts before a L1 > L2 message is dropped
+    /// and reported by the `failedMessages` rpc method.
+    pub l1_message_max_attempts: u32,
+
     #[clap(long, env = "COORDINATOR_UNSAFE_RPC", default_value_t = false)]
     /// Allow unsafe rpc methods of the coordinator if true
     pub unsafe_rpc: bool,
+
+    #[clap(long, env = "COORDINATOR_RPC_AUTH_TOKEN")]
+    /// If set, requests to the coordinator rpc (`/rpc`) must contain a
+    /// `authorization: Bearer <token>` header.
+    pub rpc_auth_token: Option<String>,
 }
 
 impl Config {
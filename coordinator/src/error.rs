@@ -0,0 +1,87 @@
+use ethers_core::types::H256;
+use zkevm_common::bridge::BridgeError;
+use zkevm_common::json_rpc::JsonRpcClientError;
+
+/// Errors that can occur while the coordinator talks to the L1/L2 nodes or the prover.
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// The node could not be reached or the connection broke.
+    RpcTransport(String),
+    /// The node responded with a json-rpc error.
+    Rpc { code: i32, message: String },
+    /// The transaction was mined but reverted.
+    Revert(H256),
+    /// The operation did not complete in time.
+    Timeout(String),
+    /// The node is not ready yet, e.g. still syncing.
+    NotReady(String),
+    /// A response, log or transaction could not be decoded.
+    Decode(String),
+    /// Anything else, e.g. signing or abi encoding errors.
+    Other(String),
+}
+
+impl Error {
+    /// Returns `true` if the operation may succeed if it is tried again.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::RpcTransport(_) | Self::Timeout(_) | Self::NotReady(_)
+        )
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RpcTransport(err) => write!(f, "rpc transport: {}", err),
+            Self::Rpc { code, message } => write!(f, "rpc error({}): {}", code, message),
+            Self::Revert(tx_hash) => write!(f, "transaction reverted: {:?}", tx_hash),
+            Self::Timeout(err) => write!(f, "timeout: {}", err),
+            Self::NotReady(err) => write!(f, "not ready: {}", err),
+            Self::Decode(err) => write!(f, "decode: {}", err),
+            Self::Other(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<JsonRpcClientError> for Error {
+    fn from(err: JsonRpcClientError) -> Self {
+        match err {
+            JsonRpcClientError::Transport(err) => Self::RpcTransport(err),
+            JsonRpcClientError::Timeout => Self::Timeout("json-rpc request".to_string()),
+            JsonRpcClientError::Decode(err) => Self::Decode(err),
+            JsonRpcClientError::Rpc(err) => Self::Rpc {
+                code: err.code,
+                message: err.message,
+            },
+            JsonRpcClientError::NoResult => Self::Decode("no result in response".to_string()),
+        }
+    }
+}
+
+impl From<BridgeError> for Error {
+    fn from(err: BridgeError) -> Self {
+        match err {
+            BridgeError::Rpc(err) => err.into(),
+            BridgeError::Revert(tx_hash) => Self::Revert(tx_hash),
+            BridgeError::Timeout(err) => Self::Timeout(err),
+            BridgeError::Decode(err) => Self::Decode(err),
+            BridgeError::Other(err) => Self::Other(err),
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(err: String) -> Self {
+        Self::Other(err)
+    }
+}
+
+impl From<Error> for String {
+    fn from(err: Error) -> Self {
+        err.to_string()
+    }
+}
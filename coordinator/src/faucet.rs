@@ -5,9 +5,9 @@ use ethers_core::types::Address;
 use ethers_core::types::U256;
 use ethers_signers::Signer;
 
-use tokio::spawn;
 use tokio::sync::Mutex;
 
+use crate::error::Error;
 use crate::shared_state::SharedState;
 
 #[derive(Clone)]
@@ -26,15 +26,14 @@ impl Faucet {
     /// To avoid replacing transactions or invoking other race conditions,
     /// this function should not be run in parallel with any other `SharedState` tasks.
     /// Only consumes up to `max_items` items from the queue each time.
-    pub async fn drain(&self, shared_state: SharedState, max_items: usize) {
+    pub async fn drain(&self, shared_state: SharedState, max_items: usize) -> Result<(), Error> {
         let mut queue = self.queue.lock().await;
         let mut remaining_balance: U256 = shared_state
             .request_l1(
                 "eth_getBalance",
                 (shared_state.ro.l1_wallet.address(), "latest"),
             )
-            .await
-            .expect("l1 balance");
+            .await?;
 
         // can be made configurable if needed
         let faucet_amount = U256::from(1000000000000000000u64);
@@ -57,18 +56,10 @@ impl Faucet {
                 break;
             }
 
-            // spawn task to catch panics
             {
-                #[allow(clippy::clone_on_copy)]
-                let receiver = receiver.clone();
-                let shared_state = shared_state.clone();
-                let res = spawn(async move {
-                    shared_state
-                        .transaction_to_l1(Some(receiver), faucet_amount, vec![])
-                        .await
-                        .expect("receipt");
-                })
-                .await;
+                let res = shared_state
+                    .transaction_to_l1(Some(*receiver), faucet_amount, vec![])
+                    .await;
 
                 if let Err(err) = res {
                     log::error!("drain: {}", err);
@@ -82,5 +73,7 @@ impl Faucet {
 
         // drain all successful transfers
         queue.drain(0..i);
+
+        Ok(())
     }
 }
@@ -1,7 +1,15 @@
+pub mod block_builder;
+pub mod block_policy;
+pub mod circuit_capacity;
 pub mod config;
-mod debug;
+pub mod error;
 pub mod faucet;
 pub mod macros;
+pub mod message_policy;
+pub mod mpt;
+pub mod public_input;
 pub mod shared_state;
 pub mod structs;
+pub mod tx_filter;
 pub mod utils;
+pub mod witness;
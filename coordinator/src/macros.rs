@@ -1,4 +1,5 @@
-/// Wraps a expression inside an async block that timeouts after `a` ms
+/// Wraps a expression inside an async block that timeouts after `a` ms.
+/// Returns `Err(Error::Timeout)` on timeout.
 #[macro_export]
 macro_rules! timeout {
     ($a:literal, $b:expr) => {
@@ -6,13 +7,39 @@ macro_rules! timeout {
             let res =
                 tokio::time::timeout(std::time::Duration::from_millis($a), async { $b }).await;
 
-            if let Err(err) = &res {
+            res.map_err(|err| {
                 log::error!("timeout: {}", err);
+                $crate::error::Error::Timeout(err.to_string())
+            })
+        }
+        .await
+    };
+}
+
+/// Evaluates the expression `b` and evaluates it again according to the `RetryPolicy` `a`
+/// as long as it returns a retryable `Error`.
+#[macro_export]
+macro_rules! retry {
+    ($a:expr, $b:expr) => {
+        async {
+            let policy: &$crate::utils::RetryPolicy = &$a;
+            let mut attempt = 0;
+            loop {
+                match $b {
+                    Ok(val) => break Ok(val),
+                    Err(err) => {
+                        let err: $crate::error::Error = err;
+                        if attempt >= policy.retries || !err.is_retryable() {
+                            break Err(err);
+                        }
+                        log::warn!("retry({}/{}): {}", attempt + 1, policy.retries, err);
+                        tokio::time::sleep(policy.delay(attempt)).await;
+                        attempt += 1;
+                    }
+                }
             }
-            res
         }
         .await
-        .unwrap()
     };
 }
 
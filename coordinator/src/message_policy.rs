@@ -0,0 +1,74 @@
+use crate::config::Config;
+use crate::structs::MessageBeacon;
+use ethers_core::types::H256;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+/// Decides in which order L1 > L2 messages are delivered and when a message is given up.
+/// All durations and timestamps are in seconds.
+#[derive(Clone, Debug, Default)]
+pub struct MessagePolicy {
+    /// Messages that are queued for this long are attempted before any other message.
+    pub max_age: u64,
+    /// Number of failed delivery attempts before a message is considered permanently failing.
+    pub max_attempts: u32,
+}
+
+/// The delivery attempts of a L1 > L2 message.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct MessageDelivery {
+    pub id: H256,
+    /// Local time when the message was queued.
+    pub queued_at: u64,
+    pub attempts: u32,
+    /// The error of each failed attempt, e.g. the revert reason.
+    pub errors: Vec<String>,
+}
+
+impl MessageDelivery {
+    pub fn new(id: H256, queued_at: u64) -> Self {
+        Self {
+            id,
+            queued_at,
+            ..Default::default()
+        }
+    }
+}
+
+impl MessagePolicy {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_age: config.l1_message_max_age,
+            max_attempts: config.l1_message_max_attempts,
+        }
+    }
+
+    /// Returns `true` if `delivery` must be attempted in the next block.
+    pub fn is_forced(&self, now: u64, delivery: &MessageDelivery) -> bool {
+        now.saturating_sub(delivery.queued_at) >= self.max_age
+    }
+
+    /// Orders `queue` so that forced messages come first, oldest first.
+    /// The order of all other messages stays the same.
+    pub fn order(
+        &self,
+        now: u64,
+        queue: &mut VecDeque<MessageBeacon>,
+        deliveries: &HashMap<H256, MessageDelivery>,
+    ) {
+        queue
+            .make_contiguous()
+            .sort_by_key(|msg| match deliveries.get(&msg.id) {
+                Some(delivery) if self.is_forced(now, delivery) => (0, delivery.queued_at),
+                _ => (1, 0),
+            });
+    }
+
+    /// Records a failed attempt and returns `true` if the message should not be retried.
+    pub fn record_failure(&self, delivery: &mut MessageDelivery, error: String) -> bool {
+        delivery.attempts += 1;
+        delivery.errors.push(error);
+        delivery.attempts >= self.max_attempts
+    }
+}
@@ -0,0 +1,234 @@
+use crate::error::Error;
+use crate::structs::ProofRequest;
+use ethers_core::types::{Address, Bytes, H256, U256};
+use ethers_core::utils::keccak256;
+use ethers_core::utils::rlp::Rlp;
+
+/// keccak256(rlp(""))
+pub const EMPTY_ROOT: H256 = H256([
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+]);
+
+/// An account leaf of the state trie.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountState {
+    pub nonce: U256,
+    pub balance: U256,
+    pub storage_root: H256,
+    pub code_hash: H256,
+}
+
+/// A reference to a child node, either by hash or embedded if its encoding is shorter than 32 bytes.
+enum NodeRef {
+    Hash(H256),
+    Embedded(Vec<u8>),
+}
+
+impl NodeRef {
+    fn from_rlp(rlp: &Rlp) -> Result<Option<Self>, Error> {
+        if rlp.is_list() {
+            return Ok(Some(Self::Embedded(rlp.as_raw().to_vec())));
+        }
+
+        let data = rlp.data().map_err(decode_err)?;
+        match data.len() {
+            0 => Ok(None),
+            32 => Ok(Some(Self::Hash(H256::from_slice(data)))),
+            len => Err(Error::Decode(format!(
+                "mpt: invalid node reference length {}",
+                len
+            ))),
+        }
+    }
+}
+
+fn decode_err(err: impl std::fmt::Display) -> Error {
+    Error::Decode(format!("mpt: {}", err))
+}
+
+fn to_nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect()
+}
+
+/// Decodes the hex-prefix encoded `path` of a leaf or extension node.
+/// Returns the nibbles and `true` if it is a leaf.
+fn decode_path(path: &[u8]) -> Result<(Vec<u8>, bool), Error> {
+    let first = *path
+        .first()
+        .ok_or_else(|| Error::Decode("mpt: empty node path".to_string()))?;
+    let flag = first >> 4;
+    if flag > 3 {
+        return Err(Error::Decode(format!(
+            "mpt: invalid node path flag {}",
+            flag
+        )));
+    }
+
+    let mut nibbles = Vec::with_capacity(path.len() * 2);
+    if flag & 1 == 1 {
+        nibbles.push(first & 0x0f);
+    }
+    nibbles.extend(to_nibbles(&path[1..]));
+
+    Ok((nibbles, flag & 2 == 2))
+}
+
+/// Verifies the merkle patricia `proof` for `key` of the secure trie with `root`,
+/// as returned by `eth_getProof`.
+/// Returns the value or `None` if the proof shows that `key` is not part of the trie.
+pub fn verify_proof(root: &H256, key: &[u8], proof: &[Bytes]) -> Result<Option<Vec<u8>>, Error> {
+    if proof.is_empty() && *root == EMPTY_ROOT {
+        return Ok(None);
+    }
+
+    let path = to_nibbles(&keccak256(key));
+    let mut pos = 0;
+    let mut proof = proof.iter();
+    let mut next = NodeRef::Hash(*root);
+
+    loop {
+        let node = match next {
+            NodeRef::Hash(hash) => {
+                let node = proof.next().ok_or_else(|| {
+                    Error::Other(format!("mpt: proof is missing node {:?}", hash))
+                })?;
+                if H256::from(keccak256(node)) != hash {
+                    return Err(Error::Other(format!("mpt: node hash mismatch {:?}", hash)));
+                }
+                node.to_vec()
+            }
+            NodeRef::Embedded(node) => node,
+        };
+
+        let rlp = Rlp::new(&node);
+        let child = match rlp.item_count().map_err(decode_err)? {
+            // branch node
+            17 => {
+                if pos == path.len() {
+                    let value = rlp.at(16).and_then(|v| v.data()).map_err(decode_err)?;
+                    return Ok(Some(value.to_vec()).filter(|v| !v.is_empty()));
+                }
+
+                let child = rlp.at(path[pos] as usize).map_err(decode_err)?;
+                pos += 1;
+                child
+            }
+            // leaf or extension node
+            2 => {
+                let node_path = rlp.at(0).and_then(|v| v.data()).map_err(decode_err)?;
+                let (node_path, is_leaf) = decode_path(node_path)?;
+                if !path[pos..].starts_with(&node_path) {
+                    return Ok(None);
+                }
+                pos += node_path.len();
+
+                if is_leaf {
+                    if pos != path.len() {
+                        return Ok(None);
+                    }
+                    let value = rlp.at(1).and_then(|v| v.data()).map_err(decode_err)?;
+                    return Ok(Some(value.to_vec()));
+                }
+
+                rlp.at(1).map_err(decode_err)?
+            }
+            count => {
+                return Err(Error::Decode(format!(
+                    "mpt: invalid node with {} items",
+                    count
+                )))
+            }
+        };
+
+        next = match NodeRef::from_rlp(&child)? {
+            Some(next) => next,
+            // empty branch slot
+            None => return Ok(None),
+        };
+    }
+}
+
+/// Verifies the account `proof` of `address` against the state root `state_root`.
+/// Returns `None` if the account does not exist.
+pub fn verify_account_proof(
+    state_root: &H256,
+    address: &Address,
+    proof: &[Bytes],
+) -> Result<Option<AccountState>, Error> {
+    let value = match verify_proof(state_root, address.as_bytes(), proof)? {
+        None => return Ok(None),
+        Some(value) => value,
+    };
+
+    let rlp = Rlp::new(&value);
+    let field =
+        |i: usize| -> Result<&[u8], Error> { rlp.at(i).and_then(|v| v.data()).map_err(decode_err) };
+    let hash = |i: usize| -> Result<H256, Error> {
+        let data = field(i)?;
+        if data.len() != 32 {
+            return Err(Error::Decode("mpt: invalid account".to_string()));
+        }
+        Ok(H256::from_slice(data))
+    };
+
+    Ok(Some(AccountState {
+        nonce: U256::from_big_endian(field(0)?),
+        balance: U256::from_big_endian(field(1)?),
+        storage_root: hash(2)?,
+        code_hash: hash(3)?,
+    }))
+}
+
+/// Verifies the storage `proof` of `slot` against the storage root `storage_root`.
+/// Returns zero if the slot is not set.
+pub fn verify_storage_proof(
+    storage_root: &H256,
+    slot: &H256,
+    proof: &[Bytes],
+) -> Result<U256, Error> {
+    match verify_proof(storage_root, slot.as_bytes(), proof)? {
+        None => Ok(U256::zero()),
+        Some(value) => {
+            let data = Rlp::new(&value).data().map_err(decode_err)?.to_vec();
+            if data.len() > 32 {
+                return Err(Error::Decode("mpt: invalid storage value".to_string()));
+            }
+            Ok(U256::from_big_endian(&data))
+        }
+    }
+}
+
+/// Verifies all proofs of an `eth_getProof` response against `state_root`
+/// and that they match the values of the response.
+pub fn verify_eth_proof(state_root: &H256, proof: &ProofRequest) -> Result<(), Error> {
+    let storage_root = match verify_account_proof(state_root, &proof.address, &proof.account_proof)?
+    {
+        None => EMPTY_ROOT,
+        Some(account) => {
+            if account.nonce != proof.nonce
+                || account.balance != proof.balance
+                || account.storage_root != proof.storage_hash
+                || account.code_hash != proof.code_hash
+            {
+                return Err(Error::Other(format!(
+                    "mpt: account {:?} does not match the proof",
+                    proof.address
+                )));
+            }
+            account.storage_root
+        }
+    };
+
+    for storage_proof in proof.storage_proof.iter() {
+        let value = verify_storage_proof(&storage_root, &storage_proof.key, &storage_proof.proof)?;
+        if value != storage_proof.value {
+            return Err(Error::Other(format!(
+                "mpt: storage slot {:?} of {:?} does not match the proof",
+                storage_proof.key, proof.address
+            )));
+        }
+    }
+
+    Ok(())
+}
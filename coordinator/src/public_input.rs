@@ -0,0 +1,432 @@
+use crate::error::Error;
+use crate::witness::{effective_gas_price, WitnessVersion, TX_TYPE_EIP1559, TX_TYPE_EIP2930};
+use ethers_core::types::{H256, U256, U512};
+use ethers_core::utils::keccak256;
+
+/// The scalar field modulus of bn254.
+const FR_MODULUS: U256 = U256([
+    0x43e1f593f0000001,
+    0x2833e84879b97091,
+    0xb85045b68181585d,
+    0x30644e72e131a029,
+]);
+/// The order of the secp256k1 curve.
+const SECP256K1_Q: U256 = U256([
+    0xbfd25e8cd0364141,
+    0xbaaedce6af48a03b,
+    0xfffffffffffffffe,
+    0xffffffffffffffff,
+]);
+/// The randomness used for the rlc of block and transaction values.
+const RANDOMNESS: U256 = U256([0; 4]);
+/// hashes(256) + block(8) + extra(3)
+const BLOCK_FIELDS: usize = 267;
+const TX_FIELDS: usize = 10;
+
+const TX_TAG_NONCE: u64 = 1;
+const TX_TAG_GAS: u64 = 2;
+const TX_TAG_GAS_PRICE: u64 = 3;
+const TX_TAG_CALLER_ADDRESS: u64 = 4;
+const TX_TAG_CALLEE_ADDRESS: u64 = 5;
+const TX_TAG_IS_CREATE: u64 = 6;
+const TX_TAG_VALUE: u64 = 7;
+const TX_TAG_CALL_DATA_LENGTH: u64 = 8;
+const TX_TAG_CALL_DATA_GAS: u64 = 9;
+const TX_TAG_SIGN_HASH: u64 = 10;
+
+fn err(msg: &str) -> Error {
+    Error::Other(format!("public input: {}", msg))
+}
+
+fn mulmod(a: U256, b: U256) -> U256 {
+    let res = a.full_mul(b) % U512::from(FR_MODULUS);
+    U256::try_from(res).expect("reduced")
+}
+
+fn addmod(a: U256, b: U256) -> U256 {
+    let res = (U512::from(a) + U512::from(b)) % U512::from(FR_MODULUS);
+    U256::try_from(res).expect("reduced")
+}
+
+fn rlc(v: U256) -> U256 {
+    (0..256).step_by(8).fold(U256::zero(), |acc, i| {
+        addmod(mulmod(acc, RANDOMNESS), (v >> i) & U256::from(0xff))
+    })
+}
+
+fn rlc_le(v: U256) -> U256 {
+    let mask = U256::from(0xff) << 248;
+    (0..256).step_by(8).fold(U256::zero(), |acc, i| {
+        addmod(mulmod(acc, RANDOMNESS), v & (mask >> i))
+    })
+}
+
+/// Returns 32 bytes at `offset` like `calldataload`.
+fn load_word(data: &[u8], offset: usize) -> U256 {
+    let mut word = [0u8; 32];
+    if offset < data.len() {
+        let len = std::cmp::min(32, data.len() - offset);
+        word[..len].copy_from_slice(&data[offset..offset + len]);
+    }
+    U256::from_big_endian(&word)
+}
+
+fn load_byte(data: &[u8], offset: usize) -> u8 {
+    data.get(offset).copied().unwrap_or_default()
+}
+
+/// Loads a big endian length of `len_of` bytes at `offset`.
+fn load_len(data: &[u8], offset: usize, len_of: usize) -> Result<usize, Error> {
+    if len_of > 4 {
+        return Err(err("RLP"));
+    }
+    Ok((0..len_of).fold(0, |acc, i| {
+        (acc << 8) | load_byte(data, offset + i) as usize
+    }))
+}
+
+/// A rlp data item as `(offset, len)`.
+type Item = (usize, usize);
+
+/// Returns `value` of the data item like `loadValue`.
+/// Values longer than 32 bytes are truncated to the last 32 bytes.
+fn load_value(data: &[u8], (offset, len): Item) -> U256 {
+    match len {
+        0 => U256::zero(),
+        1..=31 => load_word(data, offset) >> ((32 - len) * 8),
+        _ => load_word(data, offset + len - 32),
+    }
+}
+
+/// Decodes the rlp list at `ptr` and returns all data items, including the ones
+/// of nested lists, the offset after the list and the hash of the list.
+/// Follows `decodeFlat` of `rlp.yul`.
+fn decode_flat(data: &[u8], ptr: usize) -> Result<(usize, Vec<Item>, H256), Error> {
+    let first = load_byte(data, ptr);
+    let (len, mut offset) = match first {
+        0..=0xbf => return Err(err("RLP")),
+        0xc0..=0xf7 => (first as usize - 0xbf, ptr + 1),
+        _ => {
+            let len_of = first as usize - 0xf7;
+            let len = load_len(data, ptr + 1, len_of)? + len_of + 1;
+            (len, ptr + 1 + len_of)
+        }
+    };
+    let end = ptr + len;
+    if end > data.len() {
+        return Err(err("RLP"));
+    }
+    let hash = H256::from(keccak256(&data[ptr..end]));
+
+    let mut items = Vec::new();
+    while offset < end {
+        let first = load_byte(data, offset);
+        match first {
+            0..=0x7f => {
+                items.push((offset, 1));
+                offset += 1;
+            }
+            0x80..=0xb7 => {
+                let len = first as usize - 0x80;
+                items.push((offset + 1, len));
+                offset += 1 + len;
+            }
+            0xb8..=0xbf => {
+                let len_of = first as usize - 0xb7;
+                let len = load_len(data, offset + 1, len_of)?;
+                items.push((offset + 1 + len_of, len));
+                offset += 1 + len_of + len;
+            }
+            // the items of nested lists are decoded as well
+            0xc0..=0xf7 => offset += 1,
+            _ => offset += 1 + first as usize - 0xf7,
+        }
+    }
+    if offset != end {
+        return Err(err("RLP"));
+    }
+
+    Ok((end, items, hash))
+}
+
+/// The data items of a transaction, the gas price is the effective one.
+struct TxItems {
+    /// offset after the transaction, excluding the extra fields
+    end: usize,
+    /// the signing hash
+    hash: H256,
+    chain_id: Item,
+    nonce: Item,
+    gas_price: U256,
+    gas: Item,
+    to: Item,
+    value: Item,
+    input: Item,
+}
+
+/// The `raw_public_inputs` of the PublicInput circuit.
+struct RawPublicInputs {
+    values: Vec<U256>,
+    block_ptr: usize,
+    tx_ptr: usize,
+    call_data_ptr: usize,
+    /// distance between the id, index and value columns
+    spread: usize,
+}
+
+impl RawPublicInputs {
+    fn set(&mut self, idx: usize, value: U256) -> Result<(), Error> {
+        let slot = self
+            .values
+            .get_mut(idx)
+            .ok_or_else(|| err("OUT_OF_BOUNDS"))?;
+        *slot = value;
+        Ok(())
+    }
+
+    fn append(&mut self, value: U256) -> Result<(), Error> {
+        self.set(self.block_ptr, value)?;
+        self.block_ptr += 1;
+        Ok(())
+    }
+
+    /// The tag is implied by the row and not part of the raw public inputs.
+    fn append_tx_row(&mut self, tx_id: usize, _tag: u64, value: U256) -> Result<(), Error> {
+        let ptr = self.tx_ptr;
+        self.set(ptr, tx_id.into())?;
+        self.set(ptr + 2 * self.spread, value)?;
+        self.tx_ptr += 1;
+        Ok(())
+    }
+
+    fn append_call_data_row(&mut self, tx_id: usize, index: usize, value: u8) -> Result<(), Error> {
+        let ptr = self.call_data_ptr;
+        self.set(ptr, tx_id.into())?;
+        self.set(ptr + self.spread, index.into())?;
+        self.set(ptr + 2 * self.spread, value.into())?;
+        self.call_data_ptr += 1;
+        Ok(())
+    }
+}
+
+/// Computes the public input commitment of the block `witness`, as produced by
+/// `encode_verifier_witness`, for a circuit with `max_txs` and `max_calldata`.
+/// Returns `[rand_rpi, rpi_rlc, chain_id, state_root, parent_state_root]`,
+/// same as `PublicInput._buildCommitment` for `WitnessVersion::V0`.
+pub fn public_input_commitment(
+    max_txs: usize,
+    max_calldata: usize,
+    chain_id: u64,
+    parent_state_root: &H256,
+    witness: &[u8],
+) -> Result<Vec<U256>, Error> {
+    let chain_id = U256::from(chain_id);
+    let max_tx_fields = TX_FIELDS * max_txs;
+    // initial zero row
+    let n_fields = 1 + max_tx_fields + max_calldata;
+    let mut raw = RawPublicInputs {
+        values: vec![U256::zero(); BLOCK_FIELDS + 3 * n_fields],
+        block_ptr: 0,
+        tx_ptr: BLOCK_FIELDS,
+        call_data_ptr: BLOCK_FIELDS + max_tx_fields,
+        spread: n_fields,
+    };
+    let mut table = vec![U256::zero(); 5];
+    table[2] = chain_id;
+
+    // `WitnessVersion::V1` is prefixed with the version and
+    // includes the base fee and typed transactions
+    let (version, header_ptr) = match load_byte(witness, 0) {
+        0xc0..=0xff => (WitnessVersion::V0, 0),
+        1 => (WitnessVersion::V1, 1),
+        _ => return Err(err("VERSION")),
+    };
+    let extra_words = match version {
+        // from, r, s
+        WitnessVersion::V0 => 3,
+        // from, r, s, v
+        WitnessVersion::V1 => 4,
+    };
+
+    // block+extra table
+    let (mut offset, values, hash) = decode_flat(witness, header_ptr)?;
+    let base_fee = match (version, values.len()) {
+        (_, 15) => U256::zero(),
+        (WitnessVersion::V1, 16) => load_value(witness, values[15]),
+        _ => return Err(err("BLOCK_ITEMS")),
+    };
+
+    // initial zero
+    raw.append(U256::zero())?;
+    // coinbase
+    raw.append(load_value(witness, values[2]))?;
+    // gas_limit
+    raw.append(load_value(witness, values[9]))?;
+    // number
+    raw.append(load_value(witness, values[8]))?;
+    // time
+    raw.append(load_value(witness, values[11]))?;
+    // difficulty
+    raw.append(rlc_le(load_value(witness, values[7])))?;
+    // base fee
+    raw.append(rlc_le(base_fee))?;
+    // chain id
+    raw.append(chain_id)?;
+    // history hashes
+    for _ in 0..256 {
+        raw.append(rlc(load_word(witness, offset)))?;
+        offset += 32;
+    }
+    // block hash
+    raw.append(rlc(U256::from(hash.as_bytes())))?;
+    // state root
+    table[3] = rlc(load_value(witness, values[3]));
+    raw.append(table[3])?;
+    // parent state root
+    table[4] = rlc(U256::from(parent_state_root.as_bytes()));
+    raw.append(table[4])?;
+
+    if offset > witness.len() {
+        return Err(err("DATA"));
+    }
+
+    // tx table
+    // initial zero row
+    raw.append_tx_row(0, 0, U256::zero())?;
+    raw.append_call_data_row(0, 0, 0)?;
+
+    let mut tx_id = 0;
+    let mut call_data_bytes = 0;
+    while offset < witness.len() {
+        tx_id += 1;
+        if tx_id > max_txs {
+            return Err(err("MAX_TXS"));
+        }
+
+        let tx_type = match load_byte(witness, offset) {
+            0xc0..=0xff => 0,
+            tx_type if version == WitnessVersion::V1 => tx_type,
+            _ => return Err(err("TX_TYPE")),
+        };
+        let tx = match tx_type {
+            0 => {
+                let (ptr, values, hash) = decode_flat(witness, offset)?;
+                if values.len() != 9 {
+                    return Err(err("TX_ITEMS"));
+                }
+                TxItems {
+                    end: ptr,
+                    hash,
+                    chain_id: values[6],
+                    nonce: values[0],
+                    gas_price: load_value(witness, values[1]),
+                    gas: values[2],
+                    to: values[3],
+                    value: values[4],
+                    input: values[5],
+                }
+            }
+            TX_TYPE_EIP2930 => {
+                // the items of the access list follow
+                let (ptr, values, _) = decode_flat(witness, offset + 1)?;
+                if values.len() < 7 {
+                    return Err(err("TX_ITEMS"));
+                }
+                TxItems {
+                    end: ptr,
+                    hash: H256::from(keccak256(&witness[offset..ptr])),
+                    chain_id: values[0],
+                    nonce: values[1],
+                    gas_price: load_value(witness, values[2]),
+                    gas: values[3],
+                    to: values[4],
+                    value: values[5],
+                    input: values[6],
+                }
+            }
+            TX_TYPE_EIP1559 => {
+                let (ptr, values, _) = decode_flat(witness, offset + 1)?;
+                if values.len() < 8 {
+                    return Err(err("TX_ITEMS"));
+                }
+                TxItems {
+                    end: ptr,
+                    hash: H256::from(keccak256(&witness[offset..ptr])),
+                    chain_id: values[0],
+                    nonce: values[1],
+                    gas_price: effective_gas_price(
+                        base_fee,
+                        load_value(witness, values[2]),
+                        load_value(witness, values[3]),
+                    ),
+                    gas: values[4],
+                    to: values[5],
+                    value: values[6],
+                    input: values[7],
+                }
+            }
+            _ => return Err(err("TX_TYPE")),
+        };
+        offset = tx.end;
+        let tx_from = load_word(witness, offset);
+        offset += extra_words * 32;
+
+        if load_value(witness, tx.chain_id) != chain_id {
+            return Err(err("TX_CHAINID"));
+        }
+
+        raw.append_tx_row(tx_id, TX_TAG_NONCE, rlc_le(load_value(witness, tx.nonce)))?;
+        raw.append_tx_row(tx_id, TX_TAG_GAS, rlc_le(load_value(witness, tx.gas)))?;
+        raw.append_tx_row(tx_id, TX_TAG_GAS_PRICE, rlc_le(tx.gas_price))?;
+        raw.append_tx_row(tx_id, TX_TAG_CALLER_ADDRESS, tx_from)?;
+        raw.append_tx_row(tx_id, TX_TAG_CALLEE_ADDRESS, load_value(witness, tx.to))?;
+        let is_create = tx.to.1 == 0;
+        raw.append_tx_row(tx_id, TX_TAG_IS_CREATE, U256::from(is_create as u64))?;
+        raw.append_tx_row(tx_id, TX_TAG_VALUE, rlc_le(load_value(witness, tx.value)))?;
+
+        let (input_offset, input_len) = tx.input;
+        call_data_bytes += input_len;
+        if call_data_bytes > max_calldata {
+            return Err(err("MAX_DATA"));
+        }
+        raw.append_tx_row(tx_id, TX_TAG_CALL_DATA_LENGTH, input_len.into())?;
+
+        let mut zero_bytes = 0;
+        for i in 0..input_len {
+            let val = load_byte(witness, input_offset + i);
+            raw.append_call_data_row(tx_id, i, val)?;
+            zero_bytes += (val == 0) as usize;
+        }
+        let gas_cost = zero_bytes * 4 + (input_len - zero_bytes) * 16;
+        raw.append_tx_row(tx_id, TX_TAG_CALL_DATA_GAS, gas_cost.into())?;
+
+        let msg_hash = U256::from(tx.hash.as_bytes()) % SECP256K1_Q;
+        raw.append_tx_row(tx_id, TX_TAG_SIGN_HASH, rlc_le(msg_hash))?;
+    }
+
+    if offset != witness.len() {
+        return Err(err("DATA"));
+    }
+
+    // MAX_TXS padding
+    while tx_id < max_txs {
+        tx_id += 1;
+        for tag in TX_TAG_NONCE..=TX_TAG_SIGN_HASH {
+            raw.append_tx_row(tx_id, tag, U256::zero())?;
+        }
+    }
+
+    // hash(raw_public_inputs)
+    let mut buf = vec![0u8; raw.values.len() * 32];
+    for (i, value) in raw.values.iter().enumerate() {
+        value.to_big_endian(&mut buf[i * 32..(i + 1) * 32]);
+    }
+    let rand_rpi = U256::from(keccak256(&buf)) % FR_MODULUS;
+    let rpi_rlc = raw.values.iter().rev().fold(U256::zero(), |acc, value| {
+        addmod(mulmod(acc, rand_rpi), *value)
+    });
+
+    table[0] = rand_rpi;
+    table[1] = rpi_rlc;
+    Ok(table)
+}
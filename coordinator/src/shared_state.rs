@@ -1,10 +1,20 @@
+use crate::block_builder::BlockBuilder;
+use crate::block_policy::BlockPolicy;
+use crate::block_policy::PendingBlock;
+use crate::circuit_capacity::select_transactions;
 use crate::config::Config;
-use crate::debug::test_public_commitment;
+use crate::error::Error;
+use crate::message_policy::MessageDelivery;
+use crate::message_policy::MessagePolicy;
+use crate::mpt::verify_eth_proof;
+use crate::public_input::public_input_commitment;
+use crate::retry;
 use crate::structs::*;
+use crate::tx_filter::TransactionFilter;
 use crate::utils::*;
+use crate::witness::witness_header;
 use ethers_core::abi::Abi;
 use ethers_core::abi::AbiParser;
-use ethers_core::abi::RawLog;
 use ethers_core::abi::Token;
 use ethers_core::abi::Tokenizable;
 use ethers_core::types::TransactionReceipt;
@@ -13,23 +23,26 @@ use ethers_core::types::{
     ValueOrArray, H256, U256, U64,
 };
 use ethers_core::utils::keccak256;
-use ethers_core::utils::rlp;
 use ethers_signers::LocalWallet;
 use ethers_signers::Signer;
-use hyper::client::HttpConnector;
 use hyper::Uri;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::cmp;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::sync::Arc;
 use std::time::SystemTime;
 use tokio::sync::Mutex;
-use zkevm_common::json_rpc::jsonrpc_request;
-use zkevm_common::json_rpc::jsonrpc_request_client;
+use zkevm_common::bridge::{
+    BridgeClient, Layer, Message, L2_MESSAGE_DELIVERER, L2_MESSAGE_DISPATCHER,
+};
+use zkevm_common::json_rpc::JsonRpcClientError;
+use zkevm_common::prover::CircuitConfig;
 use zkevm_common::prover::ProofRequestOptions;
 use zkevm_common::prover::Proofs;
+use zkevm_common::prover::AGGREGATION_ACCUMULATOR_LEN;
 
 pub struct RoState {
     pub l2_message_deliverer_addr: Address,
@@ -40,7 +53,9 @@ pub struct RoState {
     pub message_dispatched_topic: H256,
     pub message_delivered_topic: H256,
 
-    pub http_client: hyper::Client<HttpConnector>,
+    pub http_client: JsonRpcClient,
+    pub block_builder: BlockBuilder,
+    pub tx_filter: TransactionFilter,
     pub l1_wallet: LocalWallet,
     pub l2_wallet: LocalWallet,
 
@@ -48,10 +63,11 @@ pub struct RoState {
 }
 
 impl RoState {
-    pub async fn new(config: &Config) -> Self {
-        let l1_wallet = get_wallet(&config.l1_rpc_url, &config.l1_priv).await;
+    pub async fn new(config: &Config) -> Result<Self, Error> {
+        let http_client = JsonRpcClient::new(RPC_REQUEST_TIMEOUT);
+        let l1_wallet = get_wallet(&http_client, &config.l1_rpc_url, &config.l1_priv).await?;
         // TODO: support different keys for L1 and L2
-        let l2_wallet = get_wallet(&config.l2_rpc_url, &config.l1_priv).await;
+        let l2_wallet = get_wallet(&http_client, &config.l2_rpc_url, &config.l1_priv).await?;
 
         let abi = get_abi();
 
@@ -60,24 +76,27 @@ impl RoState {
         let message_dispatched_topic = abi.event("MessageDispatched").unwrap().signature();
         let message_delivered_topic = abi.event("MessageDelivered").unwrap().signature();
 
-        RoState {
-            l2_message_deliverer_addr: "0x0000000000000000000000000000000000010000"
-                .parse()
-                .unwrap(),
-            l2_message_dispatcher_addr: "0x0000000000000000000000000000000000020000"
-                .parse()
-                .unwrap(),
+        let l2_message_deliverer_addr = L2_MESSAGE_DELIVERER;
+
+        Ok(RoState {
+            l2_message_deliverer_addr,
+            l2_message_dispatcher_addr: L2_MESSAGE_DISPATCHER,
 
             block_beacon_topic: beacon_topic,
             block_finalized_topic,
             message_dispatched_topic,
             message_delivered_topic,
 
-            http_client: hyper::Client::new(),
+            http_client,
+            block_builder: BlockBuilder::from_config(config)?,
+            tx_filter: TransactionFilter {
+                restricted_addr: l2_message_deliverer_addr,
+                coordinator_addr: l2_wallet.address(),
+            },
             l1_wallet,
             l2_wallet,
             bridge_abi: abi,
-        }
+        })
     }
 }
 
@@ -89,10 +108,25 @@ pub struct RwState {
     pub l1_last_sync_block: U64,
     pub l2_last_sync_block: U64,
     pub l1_message_queue: VecDeque<MessageBeacon>,
+    /// delivery attempts of the messages in `l1_message_queue`
+    pub l1_message_deliveries: HashMap<H256, MessageDelivery>,
+    /// messages that were given up, see `MessagePolicy`
+    pub l1_failed_messages: Vec<MessageDelivery>,
     pub l2_delivered_messages: Vec<H256>,
     pub l2_message_queue: Vec<MessageBeacon>,
     pub l1_delivered_messages: Vec<H256>,
 
+    /// local time of the last block that was set as chain head
+    pub last_block_at: u64,
+    /// local time since when transactions are pending in the l2 transaction pool
+    pub pending_since: Option<u64>,
+    /// pending l2 transactions that are not included in blocks because they are
+    /// not provable or failed to execute
+    pub excluded_txs: HashSet<H256>,
+    /// pending l2 transactions that used more gas than the circuit allows,
+    /// they are tried again once the chain head changed
+    pub deferred_txs: HashSet<H256>,
+
     /// keeps track of the timestamp used for preparing the last block
     _prev_timestamp: u64,
 }
@@ -111,9 +145,15 @@ impl Default for RwState {
             l1_last_sync_block: U64::zero(),
             l2_last_sync_block: U64::zero(),
             l1_message_queue: VecDeque::new(),
+            l1_message_deliveries: HashMap::new(),
+            l1_failed_messages: Vec::new(),
             l2_delivered_messages: Vec::new(),
             l2_message_queue: Vec::new(),
             l1_delivered_messages: Vec::new(),
+            last_block_at: 0,
+            pending_since: None,
+            excluded_txs: HashSet::new(),
+            deferred_txs: HashSet::new(),
 
             _prev_timestamp: 0,
         }
@@ -128,31 +168,34 @@ pub struct SharedState {
 }
 
 impl SharedState {
-    pub async fn new(config: &Config) -> Self {
-        Self {
+    pub async fn new(config: &Config) -> Result<Self, Error> {
+        Ok(Self {
             config: Arc::new(Mutex::new(config.clone())),
-            ro: Arc::new(RoState::new(config).await),
+            ro: Arc::new(RoState::new(config).await?),
             rw: Arc::new(Mutex::new(RwState::default())),
-        }
+        })
     }
 
     /// Initiates configuration from environment variables only.
-    pub async fn from_env() -> Self {
+    pub async fn from_env() -> Result<Self, Error> {
         let config = Config::from_env();
 
         Self::new(&config).await
     }
 
-    pub async fn init(&self) {
+    pub async fn init(&self) -> Result<(), Error> {
         if !self.rw.lock().await.chain_state.head_block_hash.is_zero() {
-            panic!("init");
+            return Err(Error::Other("already initialized".to_string()));
         }
 
-        let genesis: Block<H256> = self
-            .request_l2("eth_getBlockByNumber", ("0x0", false))
-            .await
-            .expect("genesis block");
-        let h = genesis.hash.unwrap();
+        let genesis: Block<H256> = retry!(
+            RETRY_READ,
+            self.request_l2("eth_getBlockByNumber", ("0x0", false))
+                .await
+        )?;
+        let h = genesis
+            .hash
+            .ok_or_else(|| Error::Decode("genesis block without hash".to_string()))?;
         log::info!("init with genesis: {:?}", h);
 
         let chain_state = &mut self.rw.lock().await.chain_state;
@@ -161,29 +204,23 @@ impl SharedState {
         chain_state.finalized_block_hash = h;
 
         // initialize l1 bridge if necessary
-        let bridge_state_root = self.state_root_l1().await.expect("l1.stateRoot");
+        let bridge_state_root = retry!(RETRY_READ, self.state_root_l1().await)?;
         if bridge_state_root == H256::zero() {
             log::info!("init l1 bridge");
-            let block_hash = genesis.hash.unwrap();
             let state_root = genesis.state_root;
-            let calldata = get_abi()
-                .function("initGenesis")
-                .unwrap()
-                .encode_input(&[block_hash.into_token(), state_root.into_token()])
-                .expect("calldata");
+            let calldata =
+                self.bridge_calldata("initGenesis", &[h.into_token(), state_root.into_token()])?;
             let l1_bridge_addr = self.config.lock().await.l1_bridge;
             self.transaction_to_l1(Some(l1_bridge_addr), U256::zero(), calldata)
-                .await
-                .expect("init genesis");
+                .await?;
         }
+
+        Ok(())
     }
 
-    pub async fn sync(&self) {
+    pub async fn sync(&self) -> Result<(), Error> {
         // sync events
-        let latest_block: U64 = self
-            .request_l1("eth_blockNumber", ())
-            .await
-            .expect("eth_blockNumber");
+        let latest_block: U64 = retry!(RETRY_READ, self.request_l1("eth_blockNumber", ()).await)?;
         let mut last_to_block: U64 = U64::zero();
         let mut from: U64 = self.rw.lock().await.l1_last_sync_block + 1;
         let mut filter = Filter::new()
@@ -201,10 +238,8 @@ impl SharedState {
             log::info!("fetching l1 logs from={} to={}", from, to);
             filter = filter.from_block(from).to_block(to);
 
-            let logs: Vec<Log> = self
-                .request_l1("eth_getLogs", [&filter])
-                .await
-                .expect("eth_getLogs");
+            let logs: Vec<Log> =
+                retry!(RETRY_READ, self.request_l1("eth_getLogs", [&filter]).await)?;
             // TODO: ugly hack to fix geth inconstency issues
             if !logs.is_empty() {
                 last_to_block = to;
@@ -214,36 +249,40 @@ impl SharedState {
                 let topic = log.topics[0];
 
                 if topic == self.ro.block_beacon_topic {
-                    let tx_hash = log.transaction_hash.expect("log txhash");
-                    let tx: Transaction = self
-                        .request_l1("eth_getTransactionByHash", [tx_hash])
-                        .await
-                        .expect("tx");
+                    let tx_hash = log
+                        .transaction_hash
+                        .ok_or_else(|| Error::Decode("log without transaction hash".to_string()))?;
+                    let tx: Transaction = retry!(
+                        RETRY_READ,
+                        self.request_l1("eth_getTransactionByHash", [tx_hash]).await
+                    )?;
 
                     let tx_data = tx.input.as_ref();
 
-                    // TODO: handle the case if len < 68
+                    if tx_data.len() < 68 {
+                        return Err(Error::Decode(format!(
+                            "BlockSubmitted: calldata too short in {:?}",
+                            tx_hash
+                        )));
+                    }
                     let len = U256::from(&tx_data[36..68]).as_usize();
                     let start = 68;
                     let end = start + len;
                     if end > tx_data.len() {
                         log::warn!("TODO: zeropad block data");
                     }
-                    let rlp = rlp::Rlp::new(&tx_data[start..end]);
-                    let info = rlp.payload_info().expect("payload_info");
-                    let block_header = &rlp.as_raw()[0..info.header_len + info.value_len];
+                    let block_header = witness_header(
+                        &tx_data[start..cmp::min(end, tx_data.len())],
+                    )
+                    .map_err(|e| Error::Decode(format!("BlockSubmitted: {} {:?}", e, tx_hash)))?;
                     let block_hash = H256::from(keccak256(block_header));
                     log::info!("BlockSubmitted: {:?} via {:?}", block_hash, tx_hash);
 
-                    let resp: Result<serde_json::Value, String> =
+                    let resp: Result<serde_json::Value, Error> =
                         self.request_l2("eth_getHeaderByHash", [block_hash]).await;
 
-                    if resp.is_err() {
-                        log::error!(
-                            "TODO: block not found {} {}",
-                            block_hash,
-                            resp.err().unwrap()
-                        );
+                    if let Err(err) = resp {
+                        log::error!("TODO: block not found {} {}", block_hash, err);
                     }
 
                     self.rw.lock().await.chain_state.safe_block_hash = block_hash;
@@ -259,12 +298,12 @@ impl SharedState {
                     );
 
                     self.rw.lock().await.chain_state.finalized_block_hash = block_hash;
-                    self.record_l2_messages(block_hash).await;
+                    self.record_l2_messages(block_hash).await?;
                     continue;
                 }
 
                 if topic == self.ro.message_dispatched_topic {
-                    let beacon = self._parse_message_beacon(log);
+                    let beacon = self._parse_message_beacon(log)?;
                     log::info!("L1:MessageDispatched:{:?}", beacon.id);
                     log::debug!("{:?}", beacon);
                     self.rw.lock().await.l1_message_queue.push_back(beacon);
@@ -285,59 +324,72 @@ impl SharedState {
         if last_to_block != U64::zero() {
             self.rw.lock().await.l1_last_sync_block = last_to_block;
         }
-        self.sync_l2().await;
+        self.sync_l2().await
     }
 
-    pub async fn mine(&self) {
+    pub async fn mine(&self) -> Result<(), Error> {
         // TODO: verify that head_hash is correct
-        let head_hash = get_chain_head(&self.ro.http_client, &self.config.lock().await.l2_rpc_url)
-            .await
+        let l2_rpc_url = self.config.lock().await.l2_rpc_url.clone();
+        let head_hash = get_chain_head(&self.ro.http_client, &l2_rpc_url)
+            .await?
             .hash;
         self.rw.lock().await.chain_state.head_block_hash = head_hash;
 
-        {
-            // always send a miner_init request to enable transaction pool etc.
-            // just to account for the case that the node was restarted
-            let _: Option<Address> = self.request_l2("miner_init", ()).await.unwrap_or_default();
-        }
+        // always initialize the block builder to enable transaction pool etc.
+        // just to account for the case that the node was restarted
+        self.ro
+            .block_builder
+            .init(&self.ro.http_client, &l2_rpc_url)
+            .await?;
 
         {
             // check l1 > l2 message queue
             let len = self.rw.lock().await.l1_message_queue.len();
             if len > 0 {
-                let mut nonce: U256 = self
-                    .request_l2(
+                // messages that are queued for too long go first
+                let policy = MessagePolicy::from_config(&*self.config.lock().await);
+                {
+                    let now = timestamp();
+                    let mut rw = self.rw.lock().await;
+                    let rw = &mut *rw;
+                    for msg in rw.l1_message_queue.iter() {
+                        rw.l1_message_deliveries
+                            .entry(msg.id)
+                            .or_insert_with(|| MessageDelivery::new(msg.id, now));
+                    }
+                    policy.order(now, &mut rw.l1_message_queue, &rw.l1_message_deliveries);
+                }
+
+                let mut nonce: U256 = retry!(
+                    RETRY_READ,
+                    self.request_l2(
                         "eth_getTransactionCount",
                         (self.ro.l2_wallet.address(), "latest"),
                     )
                     .await
-                    .expect("nonce");
+                )?;
 
                 const LOG_TAG: &str = "L2:deliverMessage:";
 
                 // anchors a L1 block into L2
-                let l1_block_header: BlockHeader = self
-                    .request_l1("eth_getHeaderByNumber", ["latest"])
-                    .await
-                    .expect("l1 block header");
-                // TODO: figure out how to get by hash - gonna be safer
-                // Or just hash it and compare against l1_block_header.hash.
-                let block_data: Bytes = self
-                    .request_l1("debug_getHeaderRlp", [l1_block_header.number.as_u64()])
-                    .await
-                    .expect("block_data");
+                let l1_block_header: BlockHeader = retry!(
+                    RETRY_READ,
+                    self.request_l1("eth_getHeaderByNumber", ["latest"]).await
+                )?;
+                // the header is fetched by number, a mismatch means the chain reorged
+                // in the meantime. Try again with the next iteration.
+                let l1_rpc_url = self.config.lock().await.l1_rpc_url.clone();
+                let block_data =
+                    get_header_rlp(&self.ro.http_client, &l1_rpc_url, &l1_block_header).await?;
                 // import l1 block
-                let calldata = self
-                    .ro
-                    .bridge_abi
-                    .function("importBlockHeader")
-                    .unwrap()
-                    .encode_input(&[
+                let calldata = self.bridge_calldata(
+                    "importBlockHeader",
+                    &[
                         U256::from(l1_block_header.number.as_u64()).into_token(),
                         l1_block_header.hash.into_token(),
                         block_data.into_token(),
-                    ])
-                    .expect("calldata");
+                    ],
+                )?;
                 let block_import_tx = self
                     .sign_l2(
                         self.ro.l2_message_deliverer_addr,
@@ -345,34 +397,35 @@ impl SharedState {
                         nonce,
                         calldata,
                     )
-                    .await;
+                    .await?;
                 nonce = nonce + 1;
 
                 // Use this block to run the messages against.
                 // This is required for proper gas calculation.
                 let mut messages = vec![block_import_tx];
-                let block_timestamp = self.next_timestamp().await;
-                let mut temporary_block = self
-                    .prepare_block(block_timestamp, Some(&messages))
-                    .await
-                    .expect("prepare block with import tx");
+                let block_timestamp = self.next_timestamp().await?;
+                let mut temporary_block =
+                    self.prepare_block(block_timestamp, Some(&messages)).await?;
 
                 let ts = U256::from(block_timestamp);
                 let mut drop_idxs = Vec::new();
+                let mut failed_msgs = Vec::new();
                 let mut i = 0;
-                let l1_bridge_addr = self.config.lock().await.l1_bridge;
+                let bridge = self.bridge_client().await;
                 loop {
                     let rw = self.rw.lock().await;
-                    let msg = rw.l1_message_queue.get(i);
-                    if msg.is_none() {
-                        break;
-                    }
-                    let msg = msg.unwrap().clone();
+                    let msg = match rw.l1_message_queue.get(i) {
+                        None => break,
+                        Some(msg) => msg.clone(),
+                    };
                     drop(rw);
 
                     if msg.deadline < ts {
                         log::info!("{} {:?} deadline exceeded", LOG_TAG, msg.id);
                         log::debug!("{:?}", msg);
+                        self.record_message_failure(&policy, msg.id, "deadline exceeded".into())
+                            .await;
+                        failed_msgs.push(msg.id);
                         drop_idxs.push(i);
                         i += 1;
                         continue;
@@ -397,37 +450,35 @@ impl SharedState {
                         }
                     }
 
-                    // calculate the storage slot for this message
-                    let storage_slot = msg.storage_slot();
-                    // request proof
-                    let proof_obj: ProofRequest = self
-                        .request_l1(
-                            "eth_getProof",
-                            (l1_bridge_addr, [storage_slot], l1_block_header.hash),
-                        )
-                        .await
-                        .expect("eth_getProof");
-                    // encode proof
-                    let proof: Bytes = Bytes::from(marshal_proof(
-                        &proof_obj.account_proof,
-                        &proof_obj.storage_proof[0].proof,
-                    ));
-                    let calldata = self
-                        .ro
-                        .bridge_abi
-                        .function("deliverMessageWithProof")
-                        .unwrap()
-                        .encode_input(&[
-                            msg.from.into_token(),
-                            msg.to.into_token(),
-                            msg.value.into_token(),
-                            msg.fee.into_token(),
-                            msg.deadline.into_token(),
-                            msg.nonce.into_token(),
-                            Token::Bytes(msg.calldata),
-                            proof.into_token(),
-                        ])
-                        .expect("calldata");
+                    // request, verify and encode proof
+                    let message = msg.message();
+                    let proof = retry!(
+                        RETRY_READ,
+                        bridge
+                            .build_delivery_proof(Layer::L1, &message, l1_block_header.hash)
+                            .await
+                            .map_err(Error::from)
+                    )
+                    .and_then(|proof| {
+                        verify_message_proof(&l1_block_header.state_root, &proof.proof)?;
+                        Ok(proof.data)
+                    });
+                    let proof = match proof {
+                        Err(err) if err.is_retryable() => return Err(err),
+                        Err(err) => {
+                            if self
+                                .record_message_failure(&policy, msg.id, err.to_string())
+                                .await
+                            {
+                                failed_msgs.push(msg.id);
+                                drop_idxs.push(i);
+                            }
+                            i += 1;
+                            continue;
+                        }
+                        Ok(proof) => proof,
+                    };
+                    let calldata = message.delivery_calldata(proof);
 
                     // simulate against temporary block
                     let tx = self
@@ -436,40 +487,69 @@ impl SharedState {
                             U256::zero(),
                             nonce,
                             calldata,
-                            Some(format!("{:#066x}", temporary_block.hash.unwrap())),
+                            Some(format!(
+                                "{:#066x}",
+                                temporary_block.hash.unwrap_or_default()
+                            )),
                         )
                         .await;
-                    if let Err(err) = tx {
-                        log::debug!("{} simulate tx {}", LOG_TAG, err);
-                        drop_idxs.push(i);
-                        i += 1;
-                        continue;
-                    }
-
-                    // try to build that block
-                    messages.push(tx.unwrap());
-                    let tmp = self.prepare_block(block_timestamp, Some(&messages)).await;
-                    if let Err(err) = tmp {
-                        log::debug!("{} {}", LOG_TAG, err);
-                        // bad tx
-                        messages.pop();
-
-                        match err.as_str() {
-                            "gas limit reached" => {
-                                // block is full
+                    let tx = match tx {
+                        Err(err) => {
+                            log::debug!("{} simulate tx {}", LOG_TAG, err);
+                            if err.is_retryable() {
+                                // node is unavailable, try again later
                                 break;
                             }
-                            _ => {
-                                // another error, probably a revert
+                            if self
+                                .record_message_failure(&policy, msg.id, err.to_string())
+                                .await
+                            {
+                                failed_msgs.push(msg.id);
                                 drop_idxs.push(i);
-                                i += 1;
-                                continue;
                             }
+                            i += 1;
+                            continue;
                         }
-                    }
+                        Ok(tx) => tx,
+                    };
+
+                    // try to build that block
+                    messages.push(tx);
+                    let tmp = self.prepare_block(block_timestamp, Some(&messages)).await;
+                    let tmp = match tmp {
+                        Err(err) => {
+                            log::debug!("{} {}", LOG_TAG, err);
+                            // bad tx
+                            messages.pop();
+
+                            match err {
+                                Error::Rpc { message, .. } if message == "gas limit reached" => {
+                                    // block is full
+                                    break;
+                                }
+                                err if err.is_retryable() => {
+                                    // node is unavailable, try again later
+                                    break;
+                                }
+                                err => {
+                                    // another error, probably a revert
+                                    if self
+                                        .record_message_failure(&policy, msg.id, err.to_string())
+                                        .await
+                                    {
+                                        failed_msgs.push(msg.id);
+                                        drop_idxs.push(i);
+                                    }
+                                    i += 1;
+                                    continue;
+                                }
+                            }
+                        }
+                        Ok(tmp) => tmp,
+                    };
 
                     // block looks good
-                    temporary_block = tmp.unwrap();
+                    temporary_block = tmp;
                     log::debug!(
                         "{} used={} limit={}",
                         LOG_TAG,
@@ -483,80 +563,144 @@ impl SharedState {
 
                 // final step
                 if temporary_block.transactions.len() > 1 {
-                    self.set_chain_head(temporary_block.hash.unwrap())
-                        .await
-                        .expect("set_chain_head relay");
+                    let block_hash = temporary_block
+                        .hash
+                        .ok_or_else(|| Error::Decode("block without hash".to_string()))?;
+                    self.set_chain_head(block_hash).await?;
                 }
 
                 // everything went well
                 let mut rw = self.rw.lock().await;
                 for (i, original_pos) in drop_idxs.into_iter().enumerate() {
-                    rw.l1_message_queue.remove(original_pos - i);
+                    let msg = match rw.l1_message_queue.remove(original_pos - i) {
+                        None => continue,
+                        Some(msg) => msg,
+                    };
+                    let delivery = rw.l1_message_deliveries.remove(&msg.id);
+                    if let (true, Some(delivery)) = (failed_msgs.contains(&msg.id), delivery) {
+                        log::warn!(
+                            "{} {:?} giving up after {} attempts: {:?}",
+                            LOG_TAG,
+                            msg.id,
+                            delivery.attempts,
+                            delivery.errors.last()
+                        );
+                        rw.l1_failed_messages.push(delivery);
+                    }
                 }
             }
         }
 
         // check if we can mine a block
-        let resp: TxpoolStatus = self.request_l2("txpool_status", ()).await.unwrap();
-        let pending_txs = resp.pending.as_u64();
+        let policy = BlockPolicy::from_config(&*self.config.lock().await);
+        let resp: TxpoolStatus = retry!(RETRY_READ, self.request_l2("txpool_status", ()).await)?;
+        let pending_txs = match resp.pending.is_zero() {
+            true => Vec::new(),
+            false => self.pending_transactions().await?,
+        };
+        let now = timestamp();
+        let (selection, pending) = {
+            let mut rw = self.rw.lock().await;
+            if rw.last_block_at == 0 {
+                rw.last_block_at = now;
+            }
+
+            // only keep track of transactions that are still pending
+            rw.excluded_txs
+                .retain(|hash| pending_txs.iter().any(|tx| tx.hash == *hash));
+            for tx in pending_txs.iter() {
+                if !self.ro.tx_filter.is_allowed(tx) && rw.excluded_txs.insert(tx.hash) {
+                    log::warn!(
+                        "mine: excluding transaction {:?} from {:?} to {:?}",
+                        tx.hash,
+                        tx.from,
+                        tx.to
+                    );
+                }
+            }
+            let skipped = rw.excluded_txs.union(&rw.deferred_txs).copied().collect();
+            let selection = select_transactions(CircuitConfig::max(), &pending_txs, &skipped);
+            for tx_hash in selection.unprovable.iter() {
+                log::warn!("mine: excluding unprovable transaction {:?}", tx_hash);
+                rw.excluded_txs.insert(*tx_hash);
+            }
+
+            rw.pending_since = match selection.included.len() {
+                0 => None,
+                _ => Some(rw.pending_since.unwrap_or(now)),
+            };
+
+            let pending = PendingBlock {
+                last_block_at: rw.last_block_at,
+                pending_since: rw.pending_since,
+                pending_txs: selection.included.len() as u64,
+                pending_gas: selection.usage.gas,
+            };
+            (selection, pending)
+        };
 
-        if pending_txs != 0 {
-            self.mine_block(None).await.expect("mine_block regular");
+        if policy.should_seal(now, &pending) {
+            match selection.is_complete() && !selection.unchecked_gas {
+                // the whole transaction pool fits into the circuit
+                true => {
+                    self.mine_block(None).await?;
+                }
+                false => {
+                    log::info!(
+                        "mine: deferring {} transactions, circuit usage: {:?}",
+                        selection.deferred.len(),
+                        selection.usage
+                    );
+                    self.mine_transactions(&selection.included).await?;
+                }
+            }
         }
+
+        Ok(())
     }
 
-    pub async fn submit_blocks(&self) {
+    pub async fn submit_blocks(&self) -> Result<(), Error> {
         // block submission
         let safe_hash = self.rw.lock().await.chain_state.safe_block_hash;
         let head_hash = self.rw.lock().await.chain_state.head_block_hash;
         if safe_hash != head_hash {
             // find all the blocks since `safe_hash`
-            let blocks = get_blocks_between(
-                &self.ro.http_client,
-                &self.config.lock().await.l2_rpc_url,
-                &safe_hash,
-                &head_hash,
-            )
-            .await;
+            let l2_rpc_url = self.config.lock().await.l2_rpc_url.clone();
+            let blocks =
+                get_blocks_between(&self.ro.http_client, &l2_rpc_url, &safe_hash, &head_hash)
+                    .await?;
             let l1_bridge_addr = Some(self.config.lock().await.l1_bridge);
 
             log::info!("blocks to be submitted: {:?}", blocks.len());
             for block in blocks.iter().rev() {
                 log::info!("submit_block: {}", format_block(block));
                 {
-                    let witness = self
-                        .request_witness(&block.number.unwrap())
-                        .await
-                        .expect("witness");
+                    let block_num = block
+                        .number
+                        .ok_or_else(|| Error::Decode("block without number".to_string()))?;
+                    let witness = self.request_witness(&block_num).await?;
                     let block_data = witness.input;
-                    let calldata = self
-                        .ro
-                        .bridge_abi
-                        .function("submitBlock")
-                        .unwrap()
-                        .encode_input(&[block_data.into_token()])
-                        .expect("calldata");
+                    let calldata =
+                        self.bridge_calldata("submitBlock", &[block_data.into_token()])?;
 
                     self.transaction_to_l1(l1_bridge_addr, U256::zero(), calldata)
-                        .await
-                        .expect("receipt");
+                        .await?;
                 }
             }
         }
+
+        Ok(())
     }
 
-    pub async fn finalize_blocks(&self) -> Result<(), String> {
+    pub async fn finalize_blocks(&self) -> Result<(), Error> {
         // block finalization
         let safe_hash = self.rw.lock().await.chain_state.safe_block_hash;
         let final_hash = self.rw.lock().await.chain_state.finalized_block_hash;
         if final_hash != safe_hash {
-            let blocks = get_blocks_between(
-                &self.ro.http_client,
-                &self.config.lock().await.l2_rpc_url,
-                &final_hash,
-                &safe_hash,
-            )
-            .await;
+            let l2_rpc_url = self.config.lock().await.l2_rpc_url.clone();
+            let blocks =
+                get_blocks_between(&self.ro.http_client, &l2_rpc_url, &final_hash, &safe_hash)
+                    .await?;
 
             log::info!("blocks for finalization: {:?}", blocks.len());
             for block in blocks.iter().rev() {
@@ -567,12 +711,17 @@ impl SharedState {
         Ok(())
     }
 
-    pub async fn finalize_block(&self, block: &Block<H256>) -> Result<(), String> {
+    pub async fn finalize_block(&self, block: &Block<H256>) -> Result<(), Error> {
         const LOG_TAG: &str = "L1:finalize_block:";
         log::debug!("{} {}", LOG_TAG, format_block(block));
 
-        let block_num = block.number.unwrap();
-        let proofs: Result<Option<Proofs>, String> = self.request_proof(&block_num).await;
+        let block_num = block
+            .number
+            .ok_or_else(|| Error::Decode("block without number".to_string()))?;
+        let block_hash = block
+            .hash
+            .ok_or_else(|| Error::Decode("block without hash".to_string()))?;
+        let proofs: Result<Option<Proofs>, Error> = self.request_proof(&block_num).await;
 
         if let Err(err) = proofs {
             log::error!("{}:{} {:?}", LOG_TAG, block_num, err);
@@ -580,56 +729,47 @@ impl SharedState {
             return Err(err);
         }
 
-        match proofs.unwrap() {
+        match proofs? {
             None => log::info!("{} proof not yet computed for: {}", LOG_TAG, block_num),
             Some(proof) => {
                 log::info!("{} found proof: {:#?} for {}", LOG_TAG, proof, block_num);
 
-                // choose the aggregation proof if not empty
-                let proof_result = {
+                // choose the aggregation proof if not empty,
+                // the public inputs of the block follow its accumulator if exposed
+                let (proof_result, block_instance) = {
                     if proof.aggregation.proof.len() != 0 {
-                        proof.aggregation
+                        let block_instance = proof
+                            .aggregation
+                            .instance
+                            .get(AGGREGATION_ACCUMULATOR_LEN..)
+                            .unwrap_or_default()
+                            .to_vec();
+                        (proof.aggregation, block_instance)
                     } else {
-                        proof.circuit
+                        let block_instance = proof.circuit.instance.clone();
+                        (proof.circuit, block_instance)
                     }
                 };
 
-                if !proof_result.instance.is_empty() && log::log_enabled!(log::Level::Debug) {
-                    let table = test_public_commitment(self, &block_num, &proof.config).await?;
-                    assert_eq!(proof_result.instance, table, "public inputs");
+                // only the instance of these circuits is the public input table
+                let circuit_name = self.config.lock().await.circuit_name.clone();
+                if !block_instance.is_empty() && matches!(circuit_name.as_str(), "pi" | "super") {
+                    let table = self.public_inputs(&block_num, &proof.config).await?;
+                    if block_instance != table {
+                        return Err(Error::Other(format!(
+                            "{} public inputs mismatch for {}",
+                            LOG_TAG, block_num
+                        )));
+                    }
                 }
 
-                let mut verifier_calldata = vec![];
-                let mut tmp_buf = vec![0u8; 32];
-
-                proof_result.instance.iter().for_each(|v| {
-                    v.to_big_endian(&mut tmp_buf);
-                    verifier_calldata.extend_from_slice(&tmp_buf);
-                });
-                verifier_calldata.extend_from_slice(proof_result.proof.as_ref());
-
-                let mut proof_data = vec![];
-                proof_data.extend_from_slice(block.hash.unwrap().as_ref());
-                // this is temporary until proper contract setup
-                let verifier_addr = U256::from(proof_result.label.as_bytes());
-                verifier_addr.to_big_endian(&mut tmp_buf);
-                proof_data.extend_from_slice(&tmp_buf);
-                proof_data.extend_from_slice(&verifier_calldata);
-
-                let proof_data = Bytes::from(proof_data);
+                let proof_data = encode_finalize_proof(&block_hash, &proof_result);
                 log::debug!("proof_data: {}", proof_data);
-                let calldata = self
-                    .ro
-                    .bridge_abi
-                    .function("finalizeBlock")
-                    .unwrap()
-                    .encode_input(&[proof_data.into_token()])
-                    .expect("calldata");
+                let calldata = self.bridge_calldata("finalizeBlock", &[proof_data.into_token()])?;
 
                 let l1_bridge_addr = Some(self.config.lock().await.l1_bridge);
                 self.transaction_to_l1(l1_bridge_addr, U256::zero(), calldata)
-                    .await
-                    .expect("receipt");
+                    .await?;
             }
         }
 
@@ -641,10 +781,11 @@ impl SharedState {
         to: Option<Address>,
         value: U256,
         calldata: Vec<u8>,
-    ) -> Result<TransactionReceipt, String> {
+    ) -> Result<TransactionReceipt, Error> {
+        let l1_rpc_url = self.config.lock().await.l1_rpc_url.clone();
         send_transaction_to_l1(
             &self.ro.http_client,
-            &self.config.lock().await.l1_rpc_url,
+            &l1_rpc_url,
             &self.ro.l1_wallet,
             to,
             value,
@@ -658,10 +799,11 @@ impl SharedState {
         to: Option<Address>,
         value: U256,
         calldata: Vec<u8>,
-    ) -> Result<H256, String> {
+    ) -> Result<H256, Error> {
+        let l2_rpc_url = self.config.lock().await.l2_rpc_url.clone();
         send_transaction_to_l2(
             &self.ro.http_client,
-            &self.config.lock().await.l2_rpc_url,
+            &l2_rpc_url,
             &self.ro.l2_wallet,
             to,
             value,
@@ -671,11 +813,15 @@ impl SharedState {
     }
 
     /// Estimates gas against "latest" block and returns a raw signed transaction.
-    /// Throws on error.
-    pub async fn sign_l2(&self, to: Address, value: U256, nonce: U256, calldata: Vec<u8>) -> Bytes {
+    pub async fn sign_l2(
+        &self,
+        to: Address,
+        value: U256,
+        nonce: U256,
+        calldata: Vec<u8>,
+    ) -> Result<Bytes, Error> {
         self.sign_l2_given_block_tag(to, value, nonce, calldata, None)
             .await
-            .expect("sign_l2")
     }
 
     /// Estimates gas against `option_block` or "latest" block and returns a raw signed
@@ -687,10 +833,10 @@ impl SharedState {
         nonce: U256,
         calldata: Vec<u8>,
         option_block: Option<String>,
-    ) -> Result<Bytes, String> {
+    ) -> Result<Bytes, Error> {
         let wallet = &self.ro.l2_wallet;
         let wallet_addr: Address = wallet.address();
-        let gas_price: U256 = self.request_l2("eth_gasPrice", ()).await?;
+        let gas_price: U256 = retry!(RETRY_READ, self.request_l2("eth_gasPrice", ()).await)?;
         let tx = TransactionRequest::new()
             .chain_id(wallet.chain_id())
             .from(wallet_addr)
@@ -705,54 +851,173 @@ impl SharedState {
         let sig = wallet
             .sign_transaction(&tx)
             .await
-            .map_err(|e| e.to_string())?;
+            .map_err(|e| Error::Other(format!("sign_transaction: {}", e)))?;
 
         Ok(tx.rlp_signed(&sig))
     }
 
+    /// Returns the abi encoded calldata for the bridge function `name`.
+    fn bridge_calldata(&self, name: &str, tokens: &[Token]) -> Result<Vec<u8>, Error> {
+        self.ro
+            .bridge_abi
+            .function(name)
+            .and_then(|f| f.encode_input(tokens))
+            .map_err(|e| Error::Other(format!("{}: {}", name, e)))
+    }
+
+    /// A `BridgeClient` for the configured nodes and bridge.
+    pub async fn bridge_client(&self) -> BridgeClient {
+        let config = self.config.lock().await;
+        BridgeClient::new(
+            self.ro.http_client.clone(),
+            config.l1_rpc_url.clone(),
+            config.l2_rpc_url.clone(),
+            config.l1_bridge,
+        )
+    }
+
     pub async fn request_l1<T: Serialize + Send + Sync, R: DeserializeOwned>(
         &self,
         method: &str,
         args: T,
-    ) -> Result<R, String> {
-        jsonrpc_request_client(
-            RPC_REQUEST_TIMEOUT,
-            &self.ro.http_client,
-            &self.config.lock().await.l1_rpc_url,
-            method,
-            args,
-        )
-        .await
+    ) -> Result<R, Error> {
+        let l1_rpc_url = self.config.lock().await.l1_rpc_url.clone();
+        rpc_request(&self.ro.http_client, &l1_rpc_url, method, args).await
     }
 
     pub async fn request_l2<T: Serialize + Send + Sync, R: DeserializeOwned>(
         &self,
         method: &str,
         args: T,
-    ) -> Result<R, String> {
-        jsonrpc_request_client(
-            RPC_REQUEST_TIMEOUT,
-            &self.ro.http_client,
-            &self.config.lock().await.l2_rpc_url,
-            method,
-            args,
-        )
-        .await
+    ) -> Result<R, Error> {
+        let l2_rpc_url = self.config.lock().await.l2_rpc_url.clone();
+        rpc_request(&self.ro.http_client, &l2_rpc_url, method, args).await
     }
 
     /// Returns a timestamp that takes care of being greater than the previous one.
-    /// This can potentially lead to a timestamp too far into the future
-    /// if used too fast.
-    async fn next_timestamp(&self) -> u64 {
-        let mut ts = timestamp();
+    /// Fails if the timestamp would drift too far into the future,
+    /// see `BlockPolicy::max_timestamp_drift`.
+    async fn next_timestamp(&self) -> Result<u64, Error> {
+        let policy = BlockPolicy::from_config(&*self.config.lock().await);
+        let now = timestamp();
+        let mut rw = self.rw.lock().await;
+        let ts = policy
+            .next_timestamp(now, rw._prev_timestamp)
+            .ok_or_else(|| {
+                Error::Other(format!(
+                    "block timestamp {} is too far ahead of the local time {}",
+                    rw._prev_timestamp + 1,
+                    now
+                ))
+            })?;
+        rw._prev_timestamp = ts;
+
+        Ok(ts)
+    }
+
+    /// Records a failed delivery attempt of the L1 > L2 message `id`.
+    /// Returns `true` if the message should not be retried.
+    async fn record_message_failure(
+        &self,
+        policy: &MessagePolicy,
+        id: H256,
+        error: String,
+    ) -> bool {
+        log::warn!("L2:deliverMessage: {:?} failed: {}", id, error);
+        let now = timestamp();
         let mut rw = self.rw.lock().await;
+        let delivery = rw
+            .l1_message_deliveries
+            .entry(id)
+            .or_insert_with(|| MessageDelivery::new(id, now));
 
-        if ts <= rw._prev_timestamp {
-            ts = rw._prev_timestamp + 1;
+        policy.record_failure(delivery, error)
+    }
+
+    /// Returns the pending transactions in the l2 transaction pool,
+    /// grouped by sender and ordered by nonce.
+    async fn pending_transactions(&self) -> Result<Vec<Transaction>, Error> {
+        #[derive(serde::Deserialize)]
+        struct TxpoolContent {
+            pending: HashMap<Address, HashMap<String, Transaction>>,
+        }
+
+        let content: TxpoolContent =
+            retry!(RETRY_READ, self.request_l2("txpool_content", ()).await)?;
+        let mut senders: Vec<_> = content.pending.into_iter().collect();
+        senders.sort_by_key(|(sender, _)| *sender);
+
+        let mut pending = Vec::new();
+        for (_, txs) in senders {
+            let mut txs: Vec<Transaction> = txs.into_values().collect();
+            txs.sort_by_key(|tx| tx.nonce);
+            pending.extend(txs);
         }
-        rw._prev_timestamp = ts;
 
-        ts
+        Ok(pending)
+    }
+
+    /// Mines a new block with `transactions`.
+    /// If the block can not be built then the transactions are tried one by one
+    /// and the failing ones are excluded from the following blocks.
+    async fn mine_transactions(&self, transactions: &[Transaction]) -> Result<(), Error> {
+        let timestamp = self.next_timestamp().await?;
+        let raw_txs: Vec<Bytes> = transactions.iter().map(|tx| tx.rlp()).collect();
+        let err = match self.prepare_block(timestamp, Some(&raw_txs)).await {
+            Ok(block) => return self.set_chain_head_within_capacity(&block).await,
+            Err(err) if err.is_retryable() => return Err(err),
+            Err(err) => err,
+        };
+
+        log::warn!(
+            "mine: unable to build block with selected transactions: {}",
+            err
+        );
+        let mut raw_txs = Vec::new();
+        let mut block = None;
+        for tx in transactions {
+            raw_txs.push(tx.rlp());
+            match self.prepare_block(timestamp, Some(&raw_txs)).await {
+                Ok(prepared_block) => block = Some(prepared_block),
+                Err(err) if err.is_retryable() => return Err(err),
+                Err(err) => {
+                    log::warn!("mine: excluding transaction {:?}: {}", tx.hash, err);
+                    raw_txs.pop();
+                    self.rw.lock().await.excluded_txs.insert(tx.hash);
+                }
+            }
+        }
+
+        match block {
+            Some(block) => self.set_chain_head_within_capacity(&block).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Sets `block` as the chain head if its gas used fits into the circuit parameters.
+    /// Otherwise the transactions of `block` are deferred until the chain head changed.
+    async fn set_chain_head_within_capacity(
+        &self,
+        block: &Block<Transaction>,
+    ) -> Result<(), Error> {
+        let block_gas_limit = CircuitConfig::max().block_gas_limit;
+        if block.gas_used > block_gas_limit.into() {
+            log::warn!(
+                "mine: deferring {} transactions, gas used {} exceeds {}",
+                block.transactions.len(),
+                block.gas_used,
+                block_gas_limit
+            );
+            let mut rw = self.rw.lock().await;
+            rw.deferred_txs
+                .extend(block.transactions.iter().map(|tx| tx.hash));
+            return Ok(());
+        }
+
+        let block_hash = block
+            .hash
+            .ok_or_else(|| Error::Decode("block without hash".to_string()))?;
+        self.set_chain_head(block_hash).await
     }
 
     /// Creates a new block with `transactions` on `l2_node`.
@@ -763,17 +1028,19 @@ impl SharedState {
         &self,
         timestamp: u64,
         transactions: Option<&Vec<Bytes>>,
-    ) -> Result<Block<Transaction>, String> {
+    ) -> Result<Block<Transaction>, Error> {
         // request new block
-        let prepared_block: Block<Transaction> = self
-            .request_l2(
-                "miner_sealBlock",
-                [SealBlockRequest {
-                    parent: &self.rw.lock().await.chain_state.head_block_hash,
-                    random: &H256::zero(),
-                    timestamp: &timestamp.into(),
-                    transactions,
-                }],
+        let l2_rpc_url = self.config.lock().await.l2_rpc_url.clone();
+        let chain_state = self.rw.lock().await.chain_state;
+        let prepared_block = self
+            .ro
+            .block_builder
+            .build_block(
+                &self.ro.http_client,
+                &l2_rpc_url,
+                &chain_state,
+                timestamp,
+                transactions,
             )
             .await?;
         log::info!(
@@ -785,14 +1052,23 @@ impl SharedState {
     }
 
     /// Set canonical chain head on `l2_node` and update `chain_state`.
-    pub async fn set_chain_head(&self, block_hash: H256) -> Result<(), String> {
-        let res: bool = self.request_l2("miner_setHead", [block_hash]).await?;
-
-        if !res {
-            return Err(format!("unable to set chain head to {:?}", block_hash));
-        }
+    pub async fn set_chain_head(&self, block_hash: H256) -> Result<(), Error> {
+        let l2_rpc_url = self.config.lock().await.l2_rpc_url.clone();
+        let mut chain_state = self.rw.lock().await.chain_state;
+        chain_state.head_block_hash = block_hash;
+        retry!(
+            RETRY_SEND,
+            self.ro
+                .block_builder
+                .set_head(&self.ro.http_client, &l2_rpc_url, &chain_state)
+                .await
+        )?;
 
-        self.rw.lock().await.chain_state.head_block_hash = block_hash;
+        let mut rw = self.rw.lock().await;
+        rw.chain_state.head_block_hash = block_hash;
+        rw.last_block_at = timestamp();
+        // the gas used depends on the state
+        rw.deferred_txs.clear();
         Ok(())
     }
 
@@ -801,22 +1077,22 @@ impl SharedState {
     pub async fn mine_block(
         &self,
         transactions: Option<&Vec<Bytes>>,
-    ) -> Result<Block<Transaction>, String> {
+    ) -> Result<Block<Transaction>, Error> {
         let block = self
-            .prepare_block(self.next_timestamp().await, transactions)
+            .prepare_block(self.next_timestamp().await?, transactions)
             .await?;
 
-        self.set_chain_head(block.hash.unwrap()).await?;
+        let block_hash = block
+            .hash
+            .ok_or_else(|| Error::Decode("block without hash".to_string()))?;
+        self.set_chain_head(block_hash).await?;
         Ok(block)
     }
 
     /// keeps track of l2 bridge message events
-    async fn sync_l2(&self) {
+    async fn sync_l2(&self) -> Result<(), Error> {
         // TODO: DRY syncing mechanics w/ l1
-        let latest_block: U64 = self
-            .request_l2("eth_blockNumber", ())
-            .await
-            .expect("eth_blockNumber");
+        let latest_block: U64 = retry!(RETRY_READ, self.request_l2("eth_blockNumber", ()).await)?;
         let mut last_to_block: U64 = U64::zero();
         let mut from: U64 = self.rw.lock().await.l2_last_sync_block + 1;
         let mut filter = Filter::new()
@@ -830,10 +1106,8 @@ impl SharedState {
             log::info!("fetching logs from={} to={}", from, to);
             filter = filter.from_block(from).to_block(to);
 
-            let logs: Vec<Log> = self
-                .request_l2("eth_getLogs", [&filter])
-                .await
-                .expect("eth_getLogs");
+            let logs: Vec<Log> =
+                retry!(RETRY_READ, self.request_l2("eth_getLogs", [&filter]).await)?;
             // TODO: ugly hack to fix geth inconstency issues
             if !logs.is_empty() {
                 last_to_block = to;
@@ -852,23 +1126,22 @@ impl SharedState {
             rw.l2_last_sync_block = last_to_block;
             rw.l2_delivered_messages.extend_from_slice(&executed_msgs);
         }
+
+        Ok(())
     }
 
     /// keeps track of L2 > L1 message events
-    async fn record_l2_messages(&self, block_hash: H256) {
+    async fn record_l2_messages(&self, block_hash: H256) -> Result<(), Error> {
         let filter = Filter::new()
             .address(ValueOrArray::Value(self.ro.l2_message_dispatcher_addr))
             .topic0(ValueOrArray::Value(self.ro.message_dispatched_topic))
             .at_block_hash(block_hash);
-        let logs: Vec<Log> = self
-            .request_l2("eth_getLogs", [&filter])
-            .await
-            .expect("eth_getLogs");
+        let logs: Vec<Log> = retry!(RETRY_READ, self.request_l2("eth_getLogs", [&filter]).await)?;
 
         log::info!("L2: {} relay events for {}", logs.len(), block_hash);
         let mut pending = vec![];
         for log in logs {
-            let beacon = self._parse_message_beacon(log);
+            let beacon = self._parse_message_beacon(log)?;
             log::info!("L1Relay: {:?}", beacon.id);
             log::debug!("{:?}", beacon);
             pending.push(beacon);
@@ -876,135 +1149,115 @@ impl SharedState {
 
         let mut rw = self.rw.lock().await;
         rw.l2_message_queue.extend(pending);
+
+        Ok(())
     }
 
-    pub async fn relay_to_l1(&self) {
-        let mut rw = self.rw.lock().await;
-        let len = rw.l2_message_queue.len();
+    pub async fn relay_to_l1(&self) -> Result<(), Error> {
+        let len = self.rw.lock().await.l2_message_queue.len();
 
         if len == 0 {
-            return;
+            return Ok(());
         }
 
-        // TODO: we are going to lose messages if we panic below
-        let todo: Vec<MessageBeacon> = rw.l2_message_queue.drain(0..cmp::min(32, len)).collect();
-        drop(rw);
+        let todo: Vec<MessageBeacon> =
+            self.rw.lock().await.l2_message_queue[0..cmp::min(32, len)].to_vec();
 
-        const LOG_TAG: &str = "L1:deliverMessageWithProof:";
         let l1_bridge_addr = Some(self.config.lock().await.l1_bridge);
         for msg in todo {
-            {
-                // check deadline
-                let ts_with_padding = U256::from(timestamp() + 900);
-                if msg.deadline < ts_with_padding {
-                    log::info!("{} {:?} deadline exceeded", LOG_TAG, msg.id);
-                    log::debug!("{:?}", msg);
-                    continue;
+            // messages are only removed from the queue once they are processed
+            // or failed permanently, nothing is lost if the node is unavailable.
+            if let Err(err) = self.relay_message_to_l1(&msg, l1_bridge_addr).await {
+                if err.is_retryable() {
+                    return Err(err);
                 }
+                log::error!("L1Relay: dropping {:?}: {}", msg.id, err);
             }
 
-            let found = self
-                .rw
-                .lock()
-                .await
-                .l1_delivered_messages
-                .iter()
-                .any(|&e| e == msg.id);
-
-            log::info!("{} skip={} {:?}", LOG_TAG, found, msg.id);
-            log::debug!("{:?}", msg);
-            if found {
-                continue;
+            let mut rw = self.rw.lock().await;
+            if let Some(pos) = rw.l2_message_queue.iter().position(|e| e.id == msg.id) {
+                rw.l2_message_queue.remove(pos);
             }
+        }
 
-            // latest state root known on L1
-            let state_root = self.state_root_l1().await.expect("l1.stateRoot");
-            log::info!("L1:stateRoot: {:?}", state_root);
-
-            // latest finalized block hash, should include `state_root`
-            let block_hash = self.rw.lock().await.chain_state.finalized_block_hash;
-
-            // calculate the storage slot for this message
-            let storage_slot = msg.storage_slot();
-            // request proof
-            let proof_obj: ProofRequest = self
-                .request_l2(
-                    "eth_getProof",
-                    (
-                        self.ro.l2_message_dispatcher_addr,
-                        [storage_slot],
-                        block_hash,
-                    ),
-                )
-                .await
-                .expect("eth_getProof");
-
-            // encode proof and send it
-            let proof: Bytes = Bytes::from(marshal_proof(
-                &proof_obj.account_proof,
-                &proof_obj.storage_proof[0].proof,
-            ));
-            let calldata = self
-                .ro
-                .bridge_abi
-                .function("deliverMessageWithProof")
-                .unwrap()
-                .encode_input(&[
-                    msg.from.into_token(),
-                    msg.to.into_token(),
-                    msg.value.into_token(),
-                    msg.fee.into_token(),
-                    msg.deadline.into_token(),
-                    msg.nonce.into_token(),
-                    Token::Bytes(msg.calldata),
-                    proof.into_token(),
-                ])
-                .expect("calldata");
-            self.transaction_to_l1(l1_bridge_addr, U256::zero(), calldata)
-                .await
-                .expect("receipt");
+        Ok(())
+    }
+
+    async fn relay_message_to_l1(
+        &self,
+        msg: &MessageBeacon,
+        l1_bridge_addr: Option<Address>,
+    ) -> Result<(), Error> {
+        const LOG_TAG: &str = "L1:deliverMessageWithProof:";
+        {
+            // check deadline
+            let ts_with_padding = U256::from(timestamp() + 900);
+            if msg.deadline < ts_with_padding {
+                log::info!("{} {:?} deadline exceeded", LOG_TAG, msg.id);
+                log::debug!("{:?}", msg);
+                return Ok(());
+            }
+        }
+
+        let found = self
+            .rw
+            .lock()
+            .await
+            .l1_delivered_messages
+            .iter()
+            .any(|&e| e == msg.id);
+
+        log::info!("{} skip={} {:?}", LOG_TAG, found, msg.id);
+        log::debug!("{:?}", msg);
+        if found {
+            return Ok(());
         }
+
+        // latest state root known on L1
+        let state_root = retry!(RETRY_READ, self.state_root_l1().await)?;
+        log::info!("L1:stateRoot: {:?}", state_root);
+
+        // latest finalized block hash, should include `state_root`
+        let block_hash = self.rw.lock().await.chain_state.finalized_block_hash;
+
+        // request, verify and encode proof and send it
+        let message = msg.message();
+        let bridge = self.bridge_client().await;
+        let proof = retry!(
+            RETRY_READ,
+            bridge
+                .build_delivery_proof(Layer::L2, &message, block_hash)
+                .await
+                .map_err(Error::from)
+        )?;
+        verify_message_proof(&state_root, &proof.proof)?;
+        let calldata = message.delivery_calldata(proof.data);
+        self.transaction_to_l1(l1_bridge_addr, U256::zero(), calldata)
+            .await?;
+
+        Ok(())
     }
 
-    fn _parse_message_beacon(&self, log: Log) -> MessageBeacon {
-        // TODO: this is really ugly. consider finding a alternative
-        let evt = self.ro.bridge_abi.event("MessageDispatched").unwrap();
-        let evt = evt
-            .parse_log(RawLog::from((log.topics, log.data.to_vec())))
-            .unwrap();
-
-        let id: H256 = keccak256(log.data).into();
-        let from = evt.params[0].value.to_owned().into_address().unwrap();
-        let to = evt.params[1].value.to_owned().into_address().unwrap();
-        let value = evt.params[2].value.to_owned().into_uint().unwrap();
-        let fee = evt.params[3].value.to_owned().into_uint().unwrap();
-        let deadline = evt.params[4].value.to_owned().into_uint().unwrap();
-        let nonce = evt.params[5].value.to_owned().into_uint().unwrap();
-        let calldata = evt.params[6].value.to_owned().into_bytes().unwrap();
-
-        MessageBeacon {
+    fn _parse_message_beacon(&self, log: Log) -> Result<MessageBeacon, Error> {
+        let id: H256 = keccak256(&log.data).into();
+        let msg = Message::from_log(&log)?;
+
+        Ok(MessageBeacon {
             id,
-            from,
-            to,
-            value,
-            fee,
-            deadline,
-            nonce,
-            calldata,
-        }
+            from: msg.from,
+            to: msg.to,
+            value: msg.value,
+            fee: msg.fee,
+            deadline: msg.deadline,
+            nonce: msg.nonce,
+            calldata: msg.data.to_vec(),
+        })
     }
 
-    async fn state_root_l1(&self) -> Result<H256, String> {
-        let calldata = Bytes::from(
-            self.ro
-                .bridge_abi
-                .function("stateRoot")
-                .unwrap()
-                .encode_input(&[])
-                .expect("calldata"),
-        );
+    async fn state_root_l1(&self) -> Result<H256, Error> {
+        let calldata = Bytes::from(self.bridge_calldata("stateRoot", &[])?);
         let l1_bridge_addr = self.config.lock().await.l1_bridge;
-        let resp: Result<H256, String> = self
+        let resp: Result<H256, Error> = self
             .request_l1(
                 "eth_call",
                 serde_json::json!([{ "to": l1_bridge_addr, "data": calldata }, "latest"]),
@@ -1016,21 +1269,40 @@ impl SharedState {
 
     /// TODO: WIP - moved from prover/inputs
     /// Generates a witness suitable for the L1 Verifier contract(s) for block `block_num`.
-    pub async fn request_witness(&self, block_num: &U64) -> Result<Witness, String> {
-        let block: Block<Transaction> = self
-            .request_l2("eth_getBlockByNumber", (block_num, true))
-            .await
-            .expect("block");
+    pub async fn request_witness(&self, block_num: &U64) -> Result<Witness, Error> {
+        let block: Block<Transaction> = retry!(
+            RETRY_READ,
+            self.request_l2("eth_getBlockByNumber", (block_num, true))
+                .await
+        )?;
+        // the hashes of the previous 256 blocks, history_hashes[255] is the parent
         let mut history_hashes = vec![H256::zero(); 256];
+        let block_num = block.number.unwrap_or_default().as_u64();
+        let params: Vec<[U64; 1]> = (block_num.saturating_sub(256)..block_num)
+            .rev()
+            .map(|num| [U64::from(num)])
+            .collect();
+        let l2_rpc_url = self.config.lock().await.l2_rpc_url.clone();
+        let headers: Vec<BlockHeader> = retry!(
+            RETRY_READ,
+            rpc_batch_request(
+                &self.ro.http_client,
+                &l2_rpc_url,
+                "eth_getHeaderByNumber",
+                &params
+            )
+            .await
+        )?;
         let mut block_hash = block.parent_hash;
-        history_hashes[255] = block_hash;
-        for i in 0..255 {
-            if block_hash != H256::zero() {
-                let header: BlockHeader =
-                    self.request_l2("eth_getHeaderByHash", [block_hash]).await?;
-                block_hash = header.parent_hash;
+        for (i, header) in headers.iter().enumerate() {
+            if header.hash != block_hash {
+                return Err(Error::Other(format!(
+                    "request_witness: chain changed while fetching history of block {}",
+                    block_num
+                )));
             }
-            history_hashes[254 - i] = block_hash;
+            history_hashes[255 - i] = block_hash;
+            block_hash = header.parent_hash;
         }
         let chain_id = self.ro.l2_wallet.chain_id();
         let witness: Vec<u8> = encode_verifier_witness(&block, &history_hashes, &chain_id)?;
@@ -1042,7 +1314,30 @@ impl SharedState {
         Ok(witness)
     }
 
-    pub async fn request_proof(&self, block_num: &U64) -> Result<Option<Proofs>, String> {
+    /// Computes the public input commitment of block `block_num` for `circuit_config`,
+    /// see `public_input_commitment`.
+    pub async fn public_inputs(
+        &self,
+        block_num: &U64,
+        circuit_config: &CircuitConfig,
+    ) -> Result<Vec<U256>, Error> {
+        let prev_block: BlockHeader = retry!(
+            RETRY_READ,
+            self.request_l2("eth_getHeaderByNumber", [block_num - 1])
+                .await
+        )?;
+        let witness = self.request_witness(block_num).await?;
+
+        public_input_commitment(
+            circuit_config.max_txs,
+            circuit_config.max_calldata,
+            self.ro.l2_wallet.chain_id(),
+            &prev_block.state_root,
+            witness.input.as_ref(),
+        )
+    }
+
+    pub async fn request_proof(&self, block_num: &U64) -> Result<Option<Proofs>, Error> {
         if self.config.lock().await.dummy_prover {
             log::warn!("COORDINATOR_DUMMY_PROVER");
             return Ok(Some(Proofs::default()));
@@ -1050,6 +1345,11 @@ impl SharedState {
 
         let config = self.config.lock().await;
         let prover_rpcd_url = config.prover_rpcd_url.clone();
+        let prover_client = self
+            .ro
+            .http_client
+            .clone()
+            .with_auth_token(config.prover_rpcd_auth_token.clone());
         let proof_options = ProofRequestOptions {
             circuit: config.circuit_name.clone(),
             block: block_num.as_u64(),
@@ -1059,30 +1359,24 @@ impl SharedState {
             mock: config.mock_prover,
             aggregate: config.aggregate_proof,
             mock_feedback: config.mock_prover_if_error,
+            last_block: None,
+            expose_instances: config.expose_instances,
         };
         drop(config);
 
-        let resp = jsonrpc_request_client(
-            RPC_REQUEST_TIMEOUT,
-            &self.ro.http_client,
-            &prover_rpcd_url,
-            "proof",
-            [proof_options],
-        )
-        .await;
-
-        match resp {
-            Err(err) => {
-                match err.as_ref() {
-                    "no result in response" => {
-                        // ...not an error
-                        Ok(None)
-                    }
-                    _ => Err(err),
-                }
-            }
-            Ok(val) => Ok(Some(val)),
-        }
+        let resp = retry!(
+            RETRY_READ,
+            prover_client
+                .request(&prover_rpcd_url, "proof", [&proof_options])
+                .await
+                .or_else(|err| match err {
+                    // ...not an error
+                    JsonRpcClientError::NoResult => Ok(None),
+                    err => Err(Error::from(err)),
+                })
+        )?;
+
+        Ok(resp)
     }
 
     /// Returns the current coordinator configuration.
@@ -1115,6 +1409,18 @@ fn get_abi() -> Abi {
         .expect("parse abi")
 }
 
+/// Verifies `proof` against `state_root` and that the message slot is set.
+fn verify_message_proof(state_root: &H256, proof: &ProofRequest) -> Result<(), Error> {
+    verify_eth_proof(state_root, proof)?;
+    match proof.storage_proof.iter().all(|p| !p.value.is_zero()) {
+        true => Ok(()),
+        false => Err(Error::Other(format!(
+            "message not found in storage of {:?} at state root {:?}",
+            proof.address, state_root
+        ))),
+    }
+}
+
 fn timestamp() -> u64 {
     SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
@@ -1122,13 +1428,20 @@ fn timestamp() -> u64 {
         .as_secs()
 }
 
-async fn get_wallet(rcp_url: &Uri, sign_key: &str) -> LocalWallet {
-    let chain_id: U64 = jsonrpc_request(rcp_url, "eth_chainId", ())
-        .await
-        .expect("chain id L1");
-
-    sign_key
+async fn get_wallet(
+    client: &JsonRpcClient,
+    rcp_url: &Uri,
+    sign_key: &str,
+) -> Result<LocalWallet, Error> {
+    let chain_id: U64 = retry!(
+        RETRY_READ,
+        rpc_request(client, rcp_url, "eth_chainId", ()).await
+    )?;
+
+    let wallet = sign_key
         .parse::<LocalWallet>()
-        .expect("cannot create LocalWallet from private key")
-        .with_chain_id(chain_id.as_u64())
+        .map_err(|e| Error::Other(format!("cannot create LocalWallet from private key: {}", e)))?
+        .with_chain_id(chain_id.as_u64());
+
+    Ok(wallet)
 }
@@ -1,5 +1,5 @@
 use ethers_core::types::{Address, Bytes, H256, U256, U64};
-use ethers_core::utils::keccak256;
+use zkevm_common::bridge::{message_storage_slot, Message};
 
 #[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub struct ForkchoiceStateV1 {
@@ -26,14 +26,20 @@ pub struct MessageBeacon {
 impl MessageBeacon {
     /// calculates the storage address for `self`
     pub fn storage_slot(&self) -> H256 {
-        let mut buf: Vec<u8> = Vec::with_capacity(64);
-        let sig = 0x31df76a4_u32.to_be_bytes();
-
-        buf.resize(28, 0);
-        buf.extend(sig);
-        buf.extend(self.id.as_ref());
+        message_storage_slot(&self.id)
+    }
 
-        keccak256(buf).into()
+    /// The message as understood by the bridge contracts.
+    pub fn message(&self) -> Message {
+        Message {
+            from: self.from,
+            to: self.to,
+            value: self.value,
+            fee: self.fee,
+            deadline: self.deadline,
+            nonce: self.nonce,
+            data: self.calldata.clone().into(),
+        }
     }
 }
 
@@ -56,31 +62,43 @@ pub struct BlockHeader {
     // add missing fields if required
 }
 
-// https://eips.ethereum.org/EIPS/eip-1186
-#[derive(Debug, serde::Deserialize)]
-pub struct ProofRequest {
-    pub address: Address,
-    #[serde(rename = "accountProof")]
-    pub account_proof: Vec<Bytes>,
-    pub balance: U256,
-    #[serde(rename = "codeHash")]
-    pub code_hash: H256,
-    pub nonce: U256,
-    #[serde(rename = "storageHash")]
-    pub storage_hash: H256,
-    #[serde(rename = "storageProof")]
-    pub storage_proof: Vec<StorageProof>,
-}
-
-#[derive(Debug, serde::Deserialize)]
-pub struct StorageProof {
-    pub key: H256,
-    pub value: U256,
-    pub proof: Vec<Bytes>,
-}
+pub use zkevm_common::bridge::{ProofRequest, StorageProof};
 
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct Witness {
     pub randomness: U256,
     pub input: Bytes,
 }
+
+// https://github.com/ethereum/execution-apis/blob/main/src/engine/paris.md
+#[derive(Debug, serde::Serialize)]
+pub struct PayloadAttributesV1<'a> {
+    pub timestamp: U64,
+    #[serde(rename = "prevRandao")]
+    pub prev_randao: H256,
+    #[serde(rename = "suggestedFeeRecipient")]
+    pub suggested_fee_recipient: Address,
+    /// Non-standard extension: builds the payload from exactly these transactions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transactions: Option<&'a Vec<Bytes>>,
+    /// Non-standard extension: do not draw transactions from the pool.
+    #[serde(rename = "noTxPool", skip_serializing_if = "std::ops::Not::not")]
+    pub no_tx_pool: bool,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct PayloadStatusV1 {
+    pub status: String,
+    #[serde(rename = "latestValidHash")]
+    pub latest_valid_hash: Option<H256>,
+    #[serde(rename = "validationError")]
+    pub validation_error: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct ForkchoiceUpdatedResponse {
+    #[serde(rename = "payloadStatus")]
+    pub payload_status: PayloadStatusV1,
+    #[serde(rename = "payloadId")]
+    pub payload_id: Option<serde_json::Value>,
+}
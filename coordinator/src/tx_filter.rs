@@ -0,0 +1,37 @@
+use ethers_core::types::{Address, Transaction};
+use ethers_core::utils::rlp;
+
+/// Rejects L2 transactions that only the coordinator is allowed to send,
+/// i.e. transactions to `ZkEvmL2MessageDeliverer`.
+#[derive(Clone, Debug)]
+pub struct TransactionFilter {
+    pub restricted_addr: Address,
+    pub coordinator_addr: Address,
+}
+
+impl TransactionFilter {
+    /// Returns `true` if `tx` may be included in a block.
+    pub fn is_allowed(&self, tx: &Transaction) -> bool {
+        tx.to != Some(self.restricted_addr) || tx.from == self.coordinator_addr
+    }
+
+    /// Decodes the signed transaction `raw` and checks if it may be included in a block.
+    pub fn check_raw_transaction(&self, raw: &[u8]) -> Result<(), String> {
+        let mut tx: Transaction =
+            rlp::decode(raw).map_err(|err| format!("invalid transaction: {}", err))?;
+        if tx.to != Some(self.restricted_addr) {
+            return Ok(());
+        }
+
+        tx.from = tx
+            .recover_from()
+            .map_err(|err| format!("invalid transaction signature: {}", err))?;
+        match self.is_allowed(&tx) {
+            true => Ok(()),
+            false => Err(format!(
+                "transactions to {:?} are not allowed",
+                self.restricted_addr
+            )),
+        }
+    }
+}
@@ -1,60 +1,115 @@
+use crate::error::Error;
+use crate::retry;
 use crate::structs::*;
 use crate::timeout;
+use crate::witness::WitnessVersion;
 use ethers_core::types::transaction::eip2930::AccessListWithGasUsed;
 use ethers_core::types::Transaction;
 use ethers_core::types::{
     Address, Block, Bytes, Eip1559TransactionRequest, TransactionReceipt, TransactionRequest, H256,
-    U256,
+    U256, U64,
 };
 use ethers_core::utils::keccak256;
-use ethers_core::utils::rlp::RlpStream;
 use ethers_signers::{LocalWallet, Signer};
-use hyper::client::HttpConnector;
 use hyper::Uri;
-use zkevm_common::json_rpc::jsonrpc_request_client;
+pub use zkevm_common::json_rpc::JsonRpcClient;
+pub use zkevm_common::json_rpc::RetryPolicy;
+use zkevm_common::prover::ProofResult;
 
 pub const RPC_REQUEST_TIMEOUT: u64 = 15000;
+/// Maximum number of calls in a single json-rpc batch request.
+pub const RPC_BATCH_LIMIT: usize = 128;
+
+/// Policy for idempotent requests, e.g. `eth_getLogs` or `eth_getBlockByHash`.
+pub const RETRY_READ: RetryPolicy = RetryPolicy {
+    retries: 5,
+    backoff: 250,
+    max_backoff: 4000,
+};
+
+/// Policy for requests with side effects, e.g. `eth_sendRawTransaction`.
+pub const RETRY_SEND: RetryPolicy = RetryPolicy {
+    retries: 2,
+    backoff: 1000,
+    max_backoff: 4000,
+};
+
+/// Invokes a json-rpc request on `uri` and converts the error into `Error`.
+pub async fn rpc_request<T: serde::Serialize + Send + Sync, R: serde::de::DeserializeOwned>(
+    client: &JsonRpcClient,
+    uri: &Uri,
+    method: &str,
+    params: T,
+) -> Result<R, Error> {
+    client
+        .request(uri, method, params)
+        .await
+        .map_err(Error::from)
+}
+
+/// Invokes `method` for each entry of `params` with json-rpc batch requests of up to
+/// `RPC_BATCH_LIMIT` calls. Fails if any of the calls fail.
+pub async fn rpc_batch_request<
+    T: serde::Serialize + Send + Sync,
+    R: serde::de::DeserializeOwned,
+>(
+    client: &JsonRpcClient,
+    uri: &Uri,
+    method: &str,
+    params: &[T],
+) -> Result<Vec<R>, Error> {
+    let mut ret = Vec::with_capacity(params.len());
+    for chunk in params.chunks(RPC_BATCH_LIMIT) {
+        let results = client.batch_request(uri, method, chunk).await?;
+        for res in results {
+            ret.push(res?);
+        }
+    }
+
+    Ok(ret)
+}
 
 /// may override any pending transactions
 pub async fn send_transaction_to_l1(
-    client: &hyper::Client<HttpConnector>,
+    client: &JsonRpcClient,
     node_uri: &Uri,
     wallet: &LocalWallet,
     to: Option<Address>,
     value: U256,
     calldata: Vec<u8>,
-) -> Result<TransactionReceipt, String> {
-    let nonce: U256 = jsonrpc_request_client(
-        RPC_REQUEST_TIMEOUT,
-        client,
-        node_uri,
-        "eth_getTransactionCount",
-        (wallet.address(), "latest"),
-    )
-    .await
-    .expect("nonce");
+) -> Result<TransactionReceipt, Error> {
+    let nonce: U256 = retry!(
+        RETRY_READ,
+        rpc_request(
+            client,
+            node_uri,
+            "eth_getTransactionCount",
+            (wallet.address(), "latest"),
+        )
+        .await
+    )?;
 
-    let raw_tx = sign_transaction_l1(client, node_uri, wallet, to, value, calldata, nonce).await;
+    let raw_tx = sign_transaction_l1(client, node_uri, wallet, to, value, calldata, nonce).await?;
     // wait up to 120 seconds
-    timeout!(120_000, wait_for_tx(client, node_uri, &raw_tx).await)
+    timeout!(120_000, wait_for_tx(client, node_uri, &raw_tx).await)?
 }
 
 /// may override any pending transactions
 pub async fn sign_transaction_l1(
-    client: &hyper::Client<HttpConnector>,
+    client: &JsonRpcClient,
     node_uri: &Uri,
     wallet: &LocalWallet,
     to: Option<Address>,
     value: U256,
     calldata: Vec<u8>,
     nonce: U256,
-) -> Bytes {
+) -> Result<Bytes, Error> {
     let wallet_addr: Address = wallet.address();
 
-    let gas_price: U256 =
-        jsonrpc_request_client(RPC_REQUEST_TIMEOUT, client, node_uri, "eth_gasPrice", ())
-            .await
-            .expect("gasPrice");
+    let gas_price: U256 = retry!(
+        RETRY_READ,
+        rpc_request(client, node_uri, "eth_gasPrice", ()).await
+    )?;
 
     let mut tx: Eip1559TransactionRequest = Eip1559TransactionRequest::new()
         .chain_id(wallet.chain_id())
@@ -65,29 +120,19 @@ pub async fn sign_transaction_l1(
         .max_fee_per_gas(gas_price * 2u64)
         .data(calldata);
 
-    if to.is_some() {
-        tx = tx.to(to.unwrap());
+    if let Some(to) = to {
+        tx = tx.to(to);
     }
 
-    let access_list: AccessListWithGasUsed = jsonrpc_request_client(
-        RPC_REQUEST_TIMEOUT,
-        client,
-        node_uri,
-        "eth_createAccessList",
-        [&tx],
-    )
-    .await
-    .expect("eth_createAccessList");
+    let access_list: AccessListWithGasUsed = retry!(
+        RETRY_READ,
+        rpc_request(client, node_uri, "eth_createAccessList", [&tx]).await
+    )?;
     let tx = tx.access_list(access_list.access_list);
-    let estimate: U256 = jsonrpc_request_client(
-        RPC_REQUEST_TIMEOUT,
-        client,
-        node_uri,
-        "eth_estimateGas",
-        [&tx],
-    )
-    .await
-    .expect("eth_estimateGas");
+    let estimate: U256 = retry!(
+        RETRY_READ,
+        rpc_request(client, node_uri, "eth_estimateGas", [&tx]).await
+    )?;
     let tx = tx.gas(estimate).into();
 
     log::debug!("sending l1 tx: {:?}", tx);
@@ -95,35 +140,36 @@ pub async fn sign_transaction_l1(
     let sig = wallet
         .sign_transaction(&tx)
         .await
-        .expect("sign_transaction");
+        .map_err(|e| Error::Other(format!("sign_transaction: {}", e)))?;
 
-    tx.rlp_signed(&sig)
+    Ok(tx.rlp_signed(&sig))
 }
 
 /// may override any pending transactions
 pub async fn send_transaction_to_l2(
-    client: &hyper::Client<HttpConnector>,
+    client: &JsonRpcClient,
     node_uri: &Uri,
     wallet: &LocalWallet,
     to: Option<Address>,
     value: U256,
     calldata: Vec<u8>,
-) -> Result<H256, String> {
+) -> Result<H256, Error> {
     let wallet_addr: Address = wallet.address();
-    let nonce: U256 = jsonrpc_request_client(
-        RPC_REQUEST_TIMEOUT,
-        client,
-        node_uri,
-        "eth_getTransactionCount",
-        (wallet_addr, "latest"),
-    )
-    .await
-    .expect("nonce");
+    let nonce: U256 = retry!(
+        RETRY_READ,
+        rpc_request(
+            client,
+            node_uri,
+            "eth_getTransactionCount",
+            (wallet_addr, "latest"),
+        )
+        .await
+    )?;
 
-    let gas_price: U256 =
-        jsonrpc_request_client(RPC_REQUEST_TIMEOUT, client, node_uri, "eth_gasPrice", ())
-            .await
-            .expect("gasPrice");
+    let gas_price: U256 = retry!(
+        RETRY_READ,
+        rpc_request(client, node_uri, "eth_gasPrice", ()).await
+    )?;
 
     let mut tx = TransactionRequest::new()
         .chain_id(wallet.chain_id())
@@ -133,77 +179,61 @@ pub async fn send_transaction_to_l2(
         .gas_price(gas_price * 2u64)
         .data(calldata);
 
-    if to.is_some() {
-        tx = tx.to(to.unwrap())
+    if let Some(to) = to {
+        tx = tx.to(to)
     }
 
-    let estimate: U256 = jsonrpc_request_client(
-        RPC_REQUEST_TIMEOUT,
-        client,
-        node_uri,
-        "eth_estimateGas",
-        [&tx],
-    )
-    .await
-    .expect("estimateGas");
+    let estimate: U256 = retry!(
+        RETRY_READ,
+        rpc_request(client, node_uri, "eth_estimateGas", [&tx]).await
+    )?;
     let tx = tx.gas(estimate).into();
 
-    let sig = wallet.sign_transaction(&tx).await.unwrap();
+    let sig = wallet
+        .sign_transaction(&tx)
+        .await
+        .map_err(|e| Error::Other(format!("sign_transaction: {}", e)))?;
     let raw_tx = tx.rlp_signed(&sig);
 
     // TODO: will be obsolete once execution api is used
-    jsonrpc_request_client(
-        RPC_REQUEST_TIMEOUT,
-        client,
-        node_uri,
-        "eth_sendRawTransaction",
-        [raw_tx],
+    retry!(
+        RETRY_SEND,
+        rpc_request(client, node_uri, "eth_sendRawTransaction", [&raw_tx]).await
     )
-    .await
 }
 
 /// Can loop forever, thus should be wrapped inside timeout handler
 pub async fn wait_for_tx(
-    client: &hyper::Client<HttpConnector>,
+    client: &JsonRpcClient,
     node_uri: &Uri,
     raw_tx: &Bytes,
-) -> Result<TransactionReceipt, String> {
+) -> Result<TransactionReceipt, Error> {
     let tx_hash = H256::from_slice(&keccak256(raw_tx));
 
     // ignore
-    let resp: Result<H256, String> = jsonrpc_request_client(
-        RPC_REQUEST_TIMEOUT,
-        client,
-        node_uri,
-        "eth_sendRawTransaction",
-        [raw_tx],
-    )
-    .await;
+    let resp: Result<H256, Error> = retry!(
+        RETRY_SEND,
+        rpc_request(client, node_uri, "eth_sendRawTransaction", [raw_tx]).await
+    );
 
     log::debug!("{:?}", resp);
 
     loop {
         tokio::time::sleep(std::time::Duration::from_millis(1000)).await;
 
-        let receipt: Result<TransactionReceipt, String> = jsonrpc_request_client(
-            RPC_REQUEST_TIMEOUT,
-            client,
-            node_uri,
-            "eth_getTransactionReceipt",
-            [&tx_hash],
-        )
-        .await;
+        let receipt: Result<TransactionReceipt, Error> =
+            rpc_request(client, node_uri, "eth_getTransactionReceipt", [&tx_hash]).await;
 
         log::debug!("{:?}", receipt);
 
-        if receipt.is_err() {
-            continue;
-        }
-
-        let receipt = receipt.expect("TransactionReceipt");
+        let receipt = match receipt {
+            // not yet mined or a transient error
+            Err(_) => continue,
+            Ok(val) => val,
+        };
 
-        if receipt.status.expect("tx.status").as_u64() != 1 {
-            return Err("transaction reverted".to_string());
+        if receipt.status.map(|v| v.as_u64()) != Some(1) {
+            return Err(Error::Revert(tx_hash));
         }
 
         return Ok(receipt);
@@ -213,151 +243,123 @@ pub async fn wait_for_tx(
 pub fn format_block<T>(block: &Block<T>) -> String {
     format!(
         "Block {}({}) {} txs",
-        block.number.unwrap(),
-        block.hash.unwrap(),
+        block.number.unwrap_or_default(),
+        block.hash.unwrap_or_default(),
         block.transactions.len()
     )
 }
 
-pub async fn get_chain_head(client: &hyper::Client<HttpConnector>, uri: &Uri) -> BlockHeader {
-    let header: BlockHeader = jsonrpc_request_client(
-        RPC_REQUEST_TIMEOUT,
-        client,
-        uri,
-        "eth_getHeaderByNumber",
-        ["latest"],
+pub async fn get_chain_head(client: &JsonRpcClient, uri: &Uri) -> Result<BlockHeader, Error> {
+    retry!(
+        RETRY_READ,
+        rpc_request(client, uri, "eth_getHeaderByNumber", ["latest"]).await
     )
-    .await
-    .unwrap();
+}
 
-    header
+/// Returns the rlp encoded block header of `header` and
+/// verifies that it hashes to `header.hash`, e.g. to detect reorgs.
+pub async fn get_header_rlp(
+    client: &JsonRpcClient,
+    uri: &Uri,
+    header: &BlockHeader,
+) -> Result<Bytes, Error> {
+    let header_rlp: Bytes = retry!(
+        RETRY_READ,
+        rpc_request(client, uri, "debug_getHeaderRlp", [header.number.as_u64()]).await
+    )?;
+
+    let hash = H256::from(keccak256(&header_rlp));
+    if hash != header.hash {
+        return Err(Error::Other(format!(
+            "debug_getHeaderRlp: header {} hashes to {:?} instead of {:?}",
+            header.number, hash, header.hash
+        )));
+    }
+
+    Ok(header_rlp)
 }
 
 pub async fn get_blocks_between(
-    client: &hyper::Client<HttpConnector>,
+    client: &JsonRpcClient,
     uri: &Uri,
     from: &H256,
     to: &H256,
-) -> Vec<Block<H256>> {
-    let mut ret: Vec<Block<H256>> = Vec::new();
-    let mut hash = *to;
-    loop {
-        let block: Block<H256> = jsonrpc_request_client(
-            RPC_REQUEST_TIMEOUT,
+) -> Result<Vec<Block<H256>>, Error> {
+    let [from_block, to_block]: [Block<H256>; 2] = retry!(
+        RETRY_READ,
+        rpc_batch_request(
             client,
             uri,
             "eth_getBlockByHash",
-            (hash, false),
+            &[(from, false), (to, false)]
         )
         .await
-        .expect("eth_getBlockByHash");
-        hash = block.parent_hash;
-
-        if block.hash.unwrap() != *from {
-            ret.push(block);
-        }
-        if hash == *from {
-            break;
-        }
+    )?
+    .try_into()
+    .map_err(|_| Error::Decode("eth_getBlockByHash: invalid batch response".to_string()))?;
+    let from_num = from_block.number.unwrap_or_default();
+    let to_num = to_block.number.unwrap_or_default();
+    if from == to || to_num <= from_num {
+        return Ok(Vec::new());
     }
 
-    ret
-}
-
-/// encodes the proof from `eth_getCode` suitable for the PatriciaValidator contract.
-pub fn marshal_proof(account_proof: &[Bytes], storage_proof: &[Bytes]) -> Vec<u8> {
-    let mut ret: Vec<u8> = Vec::new();
-    ret.extend([account_proof.len() as u8]);
-
-    for b in account_proof.iter() {
-        let data = b.as_ref();
-        ret.extend(data);
+    // fetch the blocks in between by number and check that they link `to` with `from`
+    let params: Vec<(U64, bool)> = (from_num.as_u64() + 1..to_num.as_u64())
+        .rev()
+        .map(|num| (U64::from(num), false))
+        .collect();
+    let blocks: Vec<Block<H256>> = retry!(
+        RETRY_READ,
+        rpc_batch_request(client, uri, "eth_getBlockByNumber", &params).await
+    )?;
+
+    let mut ret: Vec<Block<H256>> = Vec::with_capacity(blocks.len() + 1);
+    ret.push(to_block);
+    for block in blocks {
+        if Some(ret[ret.len() - 1].parent_hash) != block.hash {
+            return Err(Error::Other(format!(
+                "get_blocks_between: chain changed while fetching {:?}..{:?}",
+                from, to
+            )));
+        }
+        ret.push(block);
     }
-
-    ret.extend([storage_proof.len() as u8]);
-
-    for b in storage_proof.iter() {
-        let data = b.as_ref();
-        ret.extend(data);
+    if ret[ret.len() - 1].parent_hash != *from {
+        return Err(Error::Other(format!(
+            "get_blocks_between: {:?} is not an ancestor of {:?}",
+            from, to
+        )));
     }
 
-    ret
+    Ok(ret)
 }
 
 /// Generates a witness suitable for the L1 Verifier contract(s) for block `block_num`.
+/// Uses the lowest `WitnessVersion` that is able to encode `block`.
 pub fn encode_verifier_witness(
     block: &Block<Transaction>,
     history_hashes: &[H256],
     chain_id: &u64,
-) -> Result<Vec<u8>, String> {
-    fn store_word_bytes(buf: &mut Vec<u8>, val: &[u8]) {
-        let mut tmp: Vec<u8> = Vec::with_capacity(32);
-        tmp.resize(32 - val.len(), 0);
-        tmp.extend(val);
-
-        buf.extend(tmp);
-    }
-
-    macro_rules! store_word {
-        ($a:expr, $b:expr) => {
-            let mut tmp: Vec<u8> = vec![0; 32];
-            $b.to_big_endian(&mut tmp);
-            $a.extend(tmp);
-        };
-    }
-
-    let mut witness: Vec<u8> = Vec::new();
-    // block header + extra fields
-    {
-        let mut rlp = RlpStream::new_list(15);
-        rlp.append(&block.parent_hash);
-        rlp.append(&block.uncles_hash);
-        rlp.append(&block.author.expect("block.author"));
-        rlp.append(&block.state_root);
-        rlp.append(&block.transactions_root);
-        rlp.append(&block.receipts_root);
-        rlp.append(&block.logs_bloom.expect("block.logs_bloom"));
-        rlp.append(&block.difficulty);
-        rlp.append(&block.number.expect("block.number"));
-        rlp.append(&block.gas_limit);
-        rlp.append(&block.gas_used);
-        rlp.append(&block.timestamp);
-        rlp.append(&block.extra_data.as_ref());
-        rlp.append(&block.mix_hash.expect("block.mix_hash"));
-        rlp.append(&block.nonce.expect("block.nonce"));
-        witness.extend(rlp.out());
-
-        for block_hash in history_hashes {
-            store_word_bytes(&mut witness, block_hash.as_ref());
-        }
-    }
+) -> Result<Vec<u8>, Error> {
+    WitnessVersion::for_block(block).encode(block, history_hashes, chain_id)
+}
 
-    // transactions + extra fields
-    for tx in block.transactions.iter() {
-        // https://eips.ethereum.org/EIPS/eip-155
-        let mut rlp = RlpStream::new_list(9);
-        rlp.append(&tx.nonce);
-        rlp.append(&tx.gas_price.expect("gas_price"));
-        rlp.append(&tx.gas);
-        if tx.to.is_some() {
-            rlp.append(&tx.to.unwrap().as_ref());
-        } else {
-            rlp.append_empty_data();
-        }
-        rlp.append(&tx.value);
-        rlp.append(&tx.input.as_ref());
-        rlp.append(chain_id);
-        rlp.append_empty_data();
-        rlp.append_empty_data();
-
-        witness.extend(rlp.out());
-
-        // extra fields
-        // FIXME: can we safely encode the recovery bit of the signature into `s`?
-        store_word_bytes(&mut witness, tx.from.as_ref());
-        store_word!(&mut witness, &tx.r);
-        store_word!(&mut witness, &tx.s);
+/// Encodes the `proof` argument of `ZkEvmL1Bridge.finalizeBlock` for block `block_hash`:
+/// `blockHash, verifier address, instance, proof`.
+/// The verifier address is derived from `ProofResult::label`.
+pub fn encode_finalize_proof(block_hash: &H256, proof: &ProofResult) -> Bytes {
+    let mut tmp_buf = vec![0u8; 32];
+    let mut proof_data = vec![];
+    proof_data.extend_from_slice(block_hash.as_ref());
+    // this is temporary until proper contract setup
+    let verifier_addr = U256::from(proof.label.as_bytes());
+    verifier_addr.to_big_endian(&mut tmp_buf);
+    proof_data.extend_from_slice(&tmp_buf);
+    for v in proof.instance.iter() {
+        v.to_big_endian(&mut tmp_buf);
+        proof_data.extend_from_slice(&tmp_buf);
     }
+    proof_data.extend_from_slice(proof.proof.as_ref());
 
-    Ok(witness)
+    Bytes::from(proof_data)
 }
@@ -0,0 +1,648 @@
+use crate::error::Error;
+use ethers_core::types::transaction::eip2930::AccessList;
+use ethers_core::types::{
+    Address, Block, Bloom, Bytes, RecoveryMessage, Signature, Transaction, H256, H64, U256, U64,
+};
+use ethers_core::utils::keccak256;
+use ethers_core::utils::rlp::{Decodable, Rlp, RlpStream};
+
+/// The number of previous block hashes in the witness.
+pub const HISTORY_HASHES: usize = 256;
+const WORD: usize = 32;
+
+/// https://eips.ethereum.org/EIPS/eip-2930
+pub const TX_TYPE_EIP2930: u8 = 1;
+/// https://eips.ethereum.org/EIPS/eip-1559
+pub const TX_TYPE_EIP1559: u8 = 2;
+
+/// The encoding of the verifier witness.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WitnessVersion {
+    /// `header(15) | history hashes | (eip155 tx | from | r | s)*`
+    /// Pre-London headers and legacy transactions only.
+    /// This is the format understood by the L1 contracts.
+    V0,
+    /// `0x01 | header(15 or 16) | history hashes | (tx envelope | from | r | s | v)*`
+    /// The header includes `base_fee_per_gas` if present and every transaction is
+    /// the unsigned EIP-2718 envelope, e.g. `0x02 | rlp([chain_id, nonce, ...])`,
+    /// or the EIP-155 list for legacy transactions.
+    V1,
+}
+
+/// The block header of a verifier witness.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WitnessHeader {
+    pub parent_hash: H256,
+    pub uncles_hash: H256,
+    pub author: Address,
+    pub state_root: H256,
+    pub transactions_root: H256,
+    pub receipts_root: H256,
+    pub logs_bloom: Bloom,
+    pub difficulty: U256,
+    pub number: U64,
+    pub gas_limit: U256,
+    pub gas_used: U256,
+    pub timestamp: U256,
+    pub extra_data: Bytes,
+    pub mix_hash: H256,
+    pub nonce: H64,
+    pub base_fee_per_gas: Option<U256>,
+    /// keccak256 of the header rlp.
+    pub hash: H256,
+}
+
+/// A transaction of a verifier witness.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WitnessTransaction {
+    /// `0` for legacy transactions.
+    pub tx_type: u8,
+    pub nonce: U256,
+    /// The effective gas price for EIP-1559 transactions.
+    pub gas_price: U256,
+    pub max_priority_fee_per_gas: Option<U256>,
+    pub max_fee_per_gas: Option<U256>,
+    pub gas: U256,
+    pub to: Option<Address>,
+    pub value: U256,
+    pub input: Bytes,
+    pub access_list: AccessList,
+    pub chain_id: u64,
+    pub from: Address,
+    pub r: U256,
+    pub s: U256,
+    /// Not part of `WitnessVersion::V0`.
+    pub v: Option<u64>,
+}
+
+/// The decoded form of `utils::encode_verifier_witness`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifierWitness {
+    pub version: WitnessVersion,
+    pub header: WitnessHeader,
+    /// The hashes of the previous 256 blocks, the last one is the parent.
+    pub history_hashes: Vec<H256>,
+    pub transactions: Vec<WitnessTransaction>,
+}
+
+fn decode_err(err: impl std::fmt::Display) -> Error {
+    Error::Decode(format!("witness: {}", err))
+}
+
+fn missing_field(name: &str) -> Error {
+    Error::Decode(format!("missing field: {}", name))
+}
+
+fn val_at<T: Decodable>(rlp: &Rlp, index: usize) -> Result<T, Error> {
+    rlp.val_at(index).map_err(decode_err)
+}
+
+fn append_to(rlp: &mut RlpStream, to: &Option<Address>) {
+    match to {
+        Some(to) => rlp.append(&to.as_ref()),
+        None => rlp.append_empty_data(),
+    };
+}
+
+fn decode_to(rlp: &Rlp, index: usize) -> Result<Option<Address>, Error> {
+    match rlp.at(index).and_then(|v| v.data()).map_err(decode_err)? {
+        [] => Ok(None),
+        to if to.len() == 20 => Ok(Some(Address::from_slice(to))),
+        _ => Err(decode_err("invalid tx.to")),
+    }
+}
+
+fn store_word(buf: &mut Vec<u8>, val: U256) {
+    let mut tmp = [0u8; WORD];
+    val.to_big_endian(&mut tmp);
+    buf.extend(tmp);
+}
+
+/// The effective gas price of an EIP-1559 transaction.
+pub fn effective_gas_price(base_fee: U256, max_priority_fee: U256, max_fee: U256) -> U256 {
+    std::cmp::min(max_fee, base_fee.saturating_add(max_priority_fee))
+}
+
+/// Decodes the rlp list at `offset` and returns it together with the offset of the next item.
+fn decode_list<'a>(
+    witness: &'a [u8],
+    offset: usize,
+    items: &[usize],
+) -> Result<(Rlp<'a>, usize), Error> {
+    let rlp = Rlp::new(witness.get(offset..).unwrap_or_default());
+    let info = rlp.payload_info().map_err(decode_err)?;
+    let len = info.header_len + info.value_len;
+    if !rlp.is_list() || offset + len > witness.len() {
+        return Err(decode_err("invalid rlp list"));
+    }
+
+    let rlp = Rlp::new(&witness[offset..offset + len]);
+    let count = rlp.item_count().map_err(decode_err)?;
+    if !items.contains(&count) {
+        return Err(decode_err(format!(
+            "expected {:?} items, got {}",
+            items, count
+        )));
+    }
+
+    Ok((rlp, offset + len))
+}
+
+fn load_word(witness: &[u8], offset: usize) -> Result<&[u8], Error> {
+    witness
+        .get(offset..offset + WORD)
+        .ok_or_else(|| decode_err("unexpected end of data"))
+}
+
+impl WitnessVersion {
+    /// Returns the version of `witness` and the offset of the block header.
+    pub fn of(witness: &[u8]) -> Result<(Self, usize), Error> {
+        match witness.first() {
+            // the header of V0 starts with a rlp list
+            Some(0xc0..=0xff) => Ok((Self::V0, 0)),
+            Some(1) => Ok((Self::V1, 1)),
+            Some(version) => Err(decode_err(format!("unknown version {}", version))),
+            None => Err(decode_err("empty")),
+        }
+    }
+
+    /// Returns the lowest version that is able to encode `block`.
+    pub fn for_block(block: &Block<Transaction>) -> Self {
+        let typed = block
+            .transactions
+            .iter()
+            .any(|tx| tx.transaction_type.unwrap_or_default() != U64::zero());
+        if typed || block.base_fee_per_gas.is_some() {
+            return Self::V1;
+        }
+
+        Self::V0
+    }
+
+    /// Encodes `block` and the hashes of the previous blocks for the L1 verifier.
+    pub fn encode(
+        &self,
+        block: &Block<Transaction>,
+        history_hashes: &[H256],
+        chain_id: &u64,
+    ) -> Result<Vec<u8>, Error> {
+        let mut witness: Vec<u8> = Vec::new();
+        if *self == Self::V1 {
+            witness.push(1);
+        }
+
+        // block header + extra fields
+        {
+            let base_fee = match self {
+                Self::V0 if block.base_fee_per_gas.is_some() => {
+                    return Err(Error::Other(
+                        "witness: V0 does not support base_fee_per_gas".to_string(),
+                    ))
+                }
+                Self::V0 => None,
+                Self::V1 => block.base_fee_per_gas,
+            };
+            let mut rlp = RlpStream::new_list(15 + base_fee.is_some() as usize);
+            rlp.append(&block.parent_hash);
+            rlp.append(&block.uncles_hash);
+            rlp.append(&block.author.ok_or_else(|| missing_field("block.author"))?);
+            rlp.append(&block.state_root);
+            rlp.append(&block.transactions_root);
+            rlp.append(&block.receipts_root);
+            rlp.append(
+                &block
+                    .logs_bloom
+                    .ok_or_else(|| missing_field("block.logs_bloom"))?,
+            );
+            rlp.append(&block.difficulty);
+            rlp.append(&block.number.ok_or_else(|| missing_field("block.number"))?);
+            rlp.append(&block.gas_limit);
+            rlp.append(&block.gas_used);
+            rlp.append(&block.timestamp);
+            rlp.append(&block.extra_data.as_ref());
+            rlp.append(
+                &block
+                    .mix_hash
+                    .ok_or_else(|| missing_field("block.mix_hash"))?,
+            );
+            rlp.append(&block.nonce.ok_or_else(|| missing_field("block.nonce"))?);
+            if let Some(base_fee) = base_fee {
+                rlp.append(&base_fee);
+            }
+            witness.extend(rlp.out());
+
+            for block_hash in history_hashes {
+                witness.extend(block_hash.as_bytes());
+            }
+        }
+
+        // transactions + extra fields
+        for tx in block.transactions.iter() {
+            let tx_type = tx.transaction_type.unwrap_or_default().as_u64();
+            match (self, tx_type) {
+                // https://eips.ethereum.org/EIPS/eip-155
+                (_, 0) => {
+                    let mut rlp = RlpStream::new_list(9);
+                    rlp.append(&tx.nonce);
+                    rlp.append(&tx.gas_price.ok_or_else(|| missing_field("tx.gas_price"))?);
+                    rlp.append(&tx.gas);
+                    append_to(&mut rlp, &tx.to);
+                    rlp.append(&tx.value);
+                    rlp.append(&tx.input.as_ref());
+                    rlp.append(chain_id);
+                    rlp.append_empty_data();
+                    rlp.append_empty_data();
+                    witness.extend(rlp.out());
+                }
+                (Self::V1, 1) => {
+                    let mut rlp = RlpStream::new_list(8);
+                    rlp.append(chain_id);
+                    rlp.append(&tx.nonce);
+                    rlp.append(&tx.gas_price.ok_or_else(|| missing_field("tx.gas_price"))?);
+                    rlp.append(&tx.gas);
+                    append_to(&mut rlp, &tx.to);
+                    rlp.append(&tx.value);
+                    rlp.append(&tx.input.as_ref());
+                    rlp.append(&tx.access_list.clone().unwrap_or_default());
+                    witness.push(TX_TYPE_EIP2930);
+                    witness.extend(rlp.out());
+                }
+                (Self::V1, 2) => {
+                    let mut rlp = RlpStream::new_list(9);
+                    rlp.append(chain_id);
+                    rlp.append(&tx.nonce);
+                    rlp.append(
+                        &tx.max_priority_fee_per_gas
+                            .ok_or_else(|| missing_field("tx.max_priority_fee_per_gas"))?,
+                    );
+                    rlp.append(
+                        &tx.max_fee_per_gas
+                            .ok_or_else(|| missing_field("tx.max_fee_per_gas"))?,
+                    );
+                    rlp.append(&tx.gas);
+                    append_to(&mut rlp, &tx.to);
+                    rlp.append(&tx.value);
+                    rlp.append(&tx.input.as_ref());
+                    rlp.append(&tx.access_list.clone().unwrap_or_default());
+                    witness.push(TX_TYPE_EIP1559);
+                    witness.extend(rlp.out());
+                }
+                (_, tx_type) => {
+                    return Err(Error::Other(format!(
+                        "witness: {:?} does not support tx type {} of {:?}",
+                        self, tx_type, tx.hash
+                    )))
+                }
+            }
+
+            // extra fields
+            // FIXME: V0 does not encode the recovery bit of the signature,
+            // decoders have to try both, see `WitnessTransaction::recover_v`.
+            witness.extend(H256::from(tx.from).as_bytes());
+            store_word(&mut witness, tx.r);
+            store_word(&mut witness, tx.s);
+            if *self == Self::V1 {
+                store_word(&mut witness, tx.v.as_u64().into());
+            }
+        }
+
+        Ok(witness)
+    }
+}
+
+/// Returns the rlp encoded block header of `witness`.
+pub fn witness_header(witness: &[u8]) -> Result<&[u8], Error> {
+    let (_, offset) = WitnessVersion::of(witness)?;
+    let (rlp, _) = decode_list(witness, offset, &[15, 16])?;
+
+    Ok(rlp.as_raw())
+}
+
+fn decode_header(
+    witness: &[u8],
+    version: WitnessVersion,
+    offset: usize,
+) -> Result<(WitnessHeader, usize), Error> {
+    let items: &[usize] = match version {
+        WitnessVersion::V0 => &[15],
+        WitnessVersion::V1 => &[15, 16],
+    };
+    let (rlp, offset) = decode_list(witness, offset, items)?;
+    let base_fee_per_gas = match rlp.item_count().map_err(decode_err)? {
+        16 => Some(val_at(&rlp, 15)?),
+        _ => None,
+    };
+    let header = WitnessHeader {
+        parent_hash: val_at(&rlp, 0)?,
+        uncles_hash: val_at(&rlp, 1)?,
+        author: val_at(&rlp, 2)?,
+        state_root: val_at(&rlp, 3)?,
+        transactions_root: val_at(&rlp, 4)?,
+        receipts_root: val_at(&rlp, 5)?,
+        logs_bloom: val_at(&rlp, 6)?,
+        difficulty: val_at(&rlp, 7)?,
+        number: val_at(&rlp, 8)?,
+        gas_limit: val_at(&rlp, 9)?,
+        gas_used: val_at(&rlp, 10)?,
+        timestamp: val_at(&rlp, 11)?,
+        extra_data: val_at::<Vec<u8>>(&rlp, 12)?.into(),
+        mix_hash: val_at(&rlp, 13)?,
+        nonce: val_at(&rlp, 14)?,
+        base_fee_per_gas,
+        hash: H256::from(keccak256(rlp.as_raw())),
+    };
+
+    Ok((header, offset))
+}
+
+fn decode_transaction(
+    witness: &[u8],
+    version: WitnessVersion,
+    base_fee: Option<U256>,
+    offset: usize,
+) -> Result<(WitnessTransaction, usize), Error> {
+    let tx_type = match witness[offset] {
+        0xc0..=0xff => 0,
+        tx_type if version == WitnessVersion::V1 => tx_type,
+        _ => return Err(decode_err("typed transactions require V1")),
+    };
+    let (mut tx, mut offset) = match tx_type {
+        0 => {
+            let (rlp, offset) = decode_list(witness, offset, &[9])?;
+            for i in 7..9 {
+                if !rlp.at(i).map_err(decode_err)?.is_empty() {
+                    return Err(decode_err("tx signature fields must be empty"));
+                }
+            }
+            let tx = WitnessTransaction {
+                tx_type,
+                nonce: val_at(&rlp, 0)?,
+                gas_price: val_at(&rlp, 1)?,
+                max_priority_fee_per_gas: None,
+                max_fee_per_gas: None,
+                gas: val_at(&rlp, 2)?,
+                to: decode_to(&rlp, 3)?,
+                value: val_at(&rlp, 4)?,
+                input: val_at::<Vec<u8>>(&rlp, 5)?.into(),
+                access_list: AccessList::default(),
+                chain_id: val_at(&rlp, 6)?,
+                from: Address::zero(),
+                r: U256::zero(),
+                s: U256::zero(),
+                v: None,
+            };
+            (tx, offset)
+        }
+        TX_TYPE_EIP2930 => {
+            let (rlp, offset) = decode_list(witness, offset + 1, &[8])?;
+            let tx = WitnessTransaction {
+                tx_type,
+                chain_id: val_at(&rlp, 0)?,
+                nonce: val_at(&rlp, 1)?,
+                gas_price: val_at(&rlp, 2)?,
+                max_priority_fee_per_gas: None,
+                max_fee_per_gas: None,
+                gas: val_at(&rlp, 3)?,
+                to: decode_to(&rlp, 4)?,
+                value: val_at(&rlp, 5)?,
+                input: val_at::<Vec<u8>>(&rlp, 6)?.into(),
+                access_list: val_at(&rlp, 7)?,
+                from: Address::zero(),
+                r: U256::zero(),
+                s: U256::zero(),
+                v: None,
+            };
+            (tx, offset)
+        }
+        TX_TYPE_EIP1559 => {
+            let (rlp, offset) = decode_list(witness, offset + 1, &[9])?;
+            let max_priority_fee: U256 = val_at(&rlp, 2)?;
+            let max_fee: U256 = val_at(&rlp, 3)?;
+            let tx = WitnessTransaction {
+                tx_type,
+                chain_id: val_at(&rlp, 0)?,
+                nonce: val_at(&rlp, 1)?,
+                gas_price: effective_gas_price(
+                    base_fee.unwrap_or_default(),
+                    max_priority_fee,
+                    max_fee,
+                ),
+                max_priority_fee_per_gas: Some(max_priority_fee),
+                max_fee_per_gas: Some(max_fee),
+                gas: val_at(&rlp, 4)?,
+                to: decode_to(&rlp, 5)?,
+                value: val_at(&rlp, 6)?,
+                input: val_at::<Vec<u8>>(&rlp, 7)?.into(),
+                access_list: val_at(&rlp, 8)?,
+                from: Address::zero(),
+                r: U256::zero(),
+                s: U256::zero(),
+                v: None,
+            };
+            (tx, offset)
+        }
+        tx_type => return Err(decode_err(format!("unknown tx type {}", tx_type))),
+    };
+
+    let from = load_word(witness, offset)?;
+    if from[..12].iter().any(|b| *b != 0) {
+        return Err(decode_err("invalid tx.from"));
+    }
+    tx.from = Address::from_slice(&from[12..]);
+    tx.r = U256::from_big_endian(load_word(witness, offset + WORD)?);
+    tx.s = U256::from_big_endian(load_word(witness, offset + 2 * WORD)?);
+    offset += 3 * WORD;
+    if version == WitnessVersion::V1 {
+        let v = U256::from_big_endian(load_word(witness, offset)?);
+        if v > U256::from(u64::MAX) {
+            return Err(decode_err("invalid tx.v"));
+        }
+        tx.v = Some(v.as_u64());
+        offset += WORD;
+    }
+
+    Ok((tx, offset))
+}
+
+/// Decodes a witness produced by `utils::encode_verifier_witness`.
+/// This does not validate the transactions, see `VerifierWitness::validate`.
+pub fn decode_verifier_witness(witness: &[u8]) -> Result<VerifierWitness, Error> {
+    let (version, offset) = WitnessVersion::of(witness)?;
+    let (header, mut offset) = decode_header(witness, version, offset)?;
+
+    let mut history_hashes = Vec::with_capacity(HISTORY_HASHES);
+    for _ in 0..HISTORY_HASHES {
+        history_hashes.push(H256::from_slice(load_word(witness, offset)?));
+        offset += WORD;
+    }
+
+    let mut transactions = Vec::new();
+    while offset < witness.len() {
+        let (tx, next) = decode_transaction(witness, version, header.base_fee_per_gas, offset)?;
+        transactions.push(tx);
+        offset = next;
+    }
+
+    Ok(VerifierWitness {
+        version,
+        header,
+        history_hashes,
+        transactions,
+    })
+}
+
+impl WitnessTransaction {
+    /// The signing hash of the EIP-155 list or the EIP-2718 envelope.
+    pub fn sighash(&self) -> H256 {
+        let mut buf = Vec::new();
+        let mut rlp = match self.tx_type {
+            TX_TYPE_EIP2930 => {
+                let mut rlp = RlpStream::new_list(8);
+                rlp.append(&self.chain_id);
+                rlp.append(&self.nonce);
+                rlp.append(&self.gas_price);
+                rlp
+            }
+            TX_TYPE_EIP1559 => {
+                let mut rlp = RlpStream::new_list(9);
+                rlp.append(&self.chain_id);
+                rlp.append(&self.nonce);
+                rlp.append(&self.max_priority_fee_per_gas.unwrap_or_default());
+                rlp.append(&self.max_fee_per_gas.unwrap_or_default());
+                rlp
+            }
+            _ => {
+                let mut rlp = RlpStream::new_list(9);
+                rlp.append(&self.nonce);
+                rlp.append(&self.gas_price);
+                rlp
+            }
+        };
+        rlp.append(&self.gas);
+        append_to(&mut rlp, &self.to);
+        rlp.append(&self.value);
+        rlp.append(&self.input.as_ref());
+        if self.tx_type == 0 {
+            rlp.append(&self.chain_id);
+            rlp.append_empty_data();
+            rlp.append_empty_data();
+        } else {
+            buf.push(self.tx_type);
+            rlp.append(&self.access_list);
+        }
+        buf.extend(rlp.out());
+
+        H256::from(keccak256(buf))
+    }
+
+    /// Returns the `v` of the signature, the EIP-155 `v` for legacy transactions
+    /// or the y parity for typed transactions.
+    /// If the witness does not contain the recovery bit, both are tried against `from`.
+    pub fn recover_v(&self) -> Result<u64, Error> {
+        let offset = match self.tx_type {
+            0 => self.chain_id * 2 + 35,
+            _ => 0,
+        };
+        let recovery_ids = match self.v {
+            Some(v) if v == offset || v == offset + 1 => v - offset..v - offset + 1,
+            Some(v) => {
+                return Err(Error::Other(format!(
+                    "witness: invalid v {} of tx {:?}",
+                    v,
+                    self.sighash()
+                )))
+            }
+            None => 0..2,
+        };
+
+        let sighash = self.sighash();
+        for recovery_id in recovery_ids {
+            let signature = Signature {
+                r: self.r,
+                s: self.s,
+                v: 27 + recovery_id,
+            };
+            if let Ok(from) = signature.recover(RecoveryMessage::Hash(sighash)) {
+                if from == self.from {
+                    return Ok(offset + recovery_id);
+                }
+            }
+        }
+
+        Err(Error::Other(format!(
+            "witness: signature of tx {:?} does not match from {:?}",
+            sighash, self.from
+        )))
+    }
+}
+
+impl VerifierWitness {
+    /// Checks that every transaction is signed by `from` for `chain_id`.
+    pub fn validate(&self, chain_id: u64) -> Result<(), Error> {
+        for (i, tx) in self.transactions.iter().enumerate() {
+            if tx.chain_id != chain_id {
+                return Err(Error::Other(format!(
+                    "witness: tx {} has chain id {}, expected {}",
+                    i, tx.chain_id, chain_id
+                )));
+            }
+            tx.recover_v()?;
+        }
+
+        Ok(())
+    }
+
+    /// Converts the witness into a block suitable for `utils::encode_verifier_witness`.
+    /// The `v` of the transactions is recovered if possible.
+    pub fn to_block(&self) -> Block<Transaction> {
+        let header = &self.header;
+        let transactions = self
+            .transactions
+            .iter()
+            .map(|tx| Transaction {
+                nonce: tx.nonce,
+                gas_price: Some(tx.gas_price),
+                max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
+                max_fee_per_gas: tx.max_fee_per_gas,
+                gas: tx.gas,
+                to: tx.to,
+                value: tx.value,
+                input: tx.input.clone(),
+                access_list: Some(tx.access_list.clone()).filter(|_| tx.tx_type != 0),
+                transaction_type: Some(tx.tx_type.into()),
+                chain_id: Some(tx.chain_id.into()),
+                from: tx.from,
+                v: tx
+                    .v
+                    .or_else(|| tx.recover_v().ok())
+                    .unwrap_or_default()
+                    .into(),
+                r: tx.r,
+                s: tx.s,
+                ..Default::default()
+            })
+            .collect();
+
+        Block {
+            hash: Some(header.hash),
+            parent_hash: header.parent_hash,
+            uncles_hash: header.uncles_hash,
+            author: Some(header.author),
+            state_root: header.state_root,
+            transactions_root: header.transactions_root,
+            receipts_root: header.receipts_root,
+            number: Some(header.number),
+            gas_used: header.gas_used,
+            gas_limit: header.gas_limit,
+            extra_data: header.extra_data.clone(),
+            logs_bloom: Some(header.logs_bloom),
+            timestamp: header.timestamp,
+            difficulty: header.difficulty,
+            mix_hash: Some(header.mix_hash),
+            nonce: Some(header.nonce),
+            base_fee_per_gas: header.base_fee_per_gas,
+            transactions,
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,362 @@
+mod common;
+
+use coordinator::block_builder::engine_jwt;
+use coordinator::block_builder::BlockBuilder;
+use coordinator::error::Error;
+use coordinator::structs::ForkchoiceStateV1;
+use coordinator::utils::JsonRpcClient;
+use ethers_core::types::{Address, Block, Bytes, Transaction, H256};
+use ethers_core::utils::keccak256;
+use hyper::{Body, Request, Uri};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use zkevm_common::json_rpc_server::JsonRpcServer;
+use zkevm_common::json_rpc_server::NoParams;
+
+const MOCK_BLOCK_HASH: H256 = H256::repeat_byte(0xbb);
+const JWT_SECRET: H256 = H256::repeat_byte(0x42);
+
+#[derive(Default)]
+struct MockNode {
+    /// methods in the order they were called
+    calls: Vec<String>,
+    /// transactions of the last payload
+    transactions: Vec<Bytes>,
+    /// transactions submitted with `eth_sendRawTransaction`
+    pool: Vec<Bytes>,
+    /// drops submitted transactions instead of adding them to the pool if true
+    evict: bool,
+    head: H256,
+    /// reports `SYNCING` for fork choice updates if true
+    syncing: bool,
+}
+
+type MockState = Arc<Mutex<MockNode>>;
+
+fn mock_block(transactions: &[Bytes]) -> Block<Transaction> {
+    Block {
+        hash: Some(MOCK_BLOCK_HASH),
+        transactions: transactions
+            .iter()
+            .map(|raw_tx| Transaction {
+                hash: H256::from(keccak256(raw_tx)),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn mock_rpc() -> JsonRpcServer<MockState> {
+    JsonRpcServer::new()
+        .method("miner_init", |state: MockState, _: NoParams| async move {
+            state.lock().await.calls.push("miner_init".to_string());
+            Ok::<_, String>(Address::zero())
+        })
+        .method(
+            "miner_sealBlock",
+            |state: MockState, (req,): (serde_json::Value,)| async move {
+                let mut state = state.lock().await;
+                state.calls.push("miner_sealBlock".to_string());
+                let transactions: Option<Vec<Bytes>> =
+                    serde_json::from_value(req["transactions"].clone()).unwrap();
+                Ok::<_, String>(mock_block(&transactions.unwrap_or_default()))
+            },
+        )
+        .method(
+            "miner_setHead",
+            |state: MockState, (hash,): (H256,)| async move {
+                let mut state = state.lock().await;
+                state.calls.push("miner_setHead".to_string());
+                state.head = hash;
+                Ok::<_, String>(true)
+            },
+        )
+        .method(
+            "engine_forkchoiceUpdatedV1",
+            |state: MockState, (fcs, attributes): (ForkchoiceStateV1, serde_json::Value)| async move {
+                let mut state = state.lock().await;
+                state.calls.push("engine_forkchoiceUpdatedV1".to_string());
+                if state.syncing {
+                    return Ok::<_, String>(serde_json::json!({
+                        "payloadStatus": { "status": "SYNCING" },
+                        "payloadId": null,
+                    }));
+                }
+                let payload_id = match attributes.is_null() {
+                    true => {
+                        state.head = fcs.head_block_hash;
+                        serde_json::Value::Null
+                    }
+                    false => {
+                        state.transactions = match attributes["noTxPool"].as_bool() {
+                            Some(true) => {
+                                serde_json::from_value(attributes["transactions"].clone())
+                                    .unwrap()
+                            }
+                            // standard payload attributes, the block is built from the pool
+                            _ => {
+                                assert!(attributes.get("transactions").is_none());
+                                state.pool.clone()
+                            }
+                        };
+                        "0x0000000000000001".into()
+                    }
+                };
+
+                Ok::<_, String>(serde_json::json!({
+                    "payloadStatus": { "status": "VALID" },
+                    "payloadId": payload_id,
+                }))
+            },
+        )
+        .method(
+            "engine_getPayloadV1",
+            |state: MockState, (payload_id,): (String,)| async move {
+                let mut state = state.lock().await;
+                state.calls.push("engine_getPayloadV1".to_string());
+                assert_eq!(payload_id, "0x0000000000000001");
+                Ok::<_, String>(serde_json::json!({
+                    "blockHash": MOCK_BLOCK_HASH,
+                    "transactions": state.transactions,
+                }))
+            },
+        )
+        .method(
+            "engine_newPayloadV1",
+            |state: MockState, (payload,): (serde_json::Value,)| async move {
+                state
+                    .lock()
+                    .await
+                    .calls
+                    .push("engine_newPayloadV1".to_string());
+                assert_eq!(payload["blockHash"], serde_json::json!(MOCK_BLOCK_HASH));
+                Ok::<_, String>(serde_json::json!({ "status": "VALID" }))
+            },
+        )
+        .method(
+            "eth_sendRawTransaction",
+            |state: MockState, (raw_tx,): (Bytes,)| async move {
+                let mut state = state.lock().await;
+                state.calls.push("eth_sendRawTransaction".to_string());
+                if state.pool.contains(&raw_tx) {
+                    return Err("already known".to_string());
+                }
+                if !state.evict {
+                    state.pool.push(raw_tx.clone());
+                }
+                Ok(H256::from(keccak256(raw_tx)))
+            },
+        )
+        .method(
+            "eth_getBlockByHash",
+            |state: MockState, (hash, _): (H256, bool)| async move {
+                let mut state = state.lock().await;
+                state.calls.push("eth_getBlockByHash".to_string());
+                assert_eq!(hash, MOCK_BLOCK_HASH);
+                Ok::<_, String>(mock_block(&state.transactions))
+            },
+        )
+}
+
+/// Returns `true` if `req` carries a valid engine api JWT.
+fn is_authorized(req: &Request<Body>) -> bool {
+    let token = req
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    let claims = token
+        .split('.')
+        .nth(1)
+        .and_then(|v| base64::decode_config(v, base64::URL_SAFE_NO_PAD).ok())
+        .and_then(|v| serde_json::from_slice::<serde_json::Value>(&v).ok())
+        .unwrap_or_default();
+
+    match claims["iat"].as_u64() {
+        None => false,
+        Some(iat) => engine_jwt(&JWT_SECRET, iat) == token,
+    }
+}
+
+/// Starts a mock node that serves the rpc on `/` and the authenticated engine api on `/engine`.
+async fn mock_node() -> (MockState, Uri, Uri) {
+    let state = MockState::default();
+    let addr = common::mock_node(mock_rpc(), state.clone(), |req| {
+        req.uri().path() != "/engine" || is_authorized(req)
+    })
+    .await;
+
+    let rpc_url = format!("http://{}/", addr).parse().unwrap();
+    let engine_url = format!("http://{}/engine", addr).parse().unwrap();
+    (state, rpc_url, engine_url)
+}
+
+fn chain_state(head_block_hash: H256) -> ForkchoiceStateV1 {
+    ForkchoiceStateV1 {
+        head_block_hash,
+        safe_block_hash: H256::zero(),
+        finalized_block_hash: H256::zero(),
+    }
+}
+
+#[tokio::test]
+async fn block_builder_miner() {
+    let (state, rpc_url, _) = mock_node().await;
+    let client = JsonRpcClient::new(5000);
+    let builder = BlockBuilder::Miner;
+    let txs = vec![Bytes::from(vec![1]), Bytes::from(vec![2])];
+
+    builder.init(&client, &rpc_url).await.expect("init");
+    let block = builder
+        .build_block(&client, &rpc_url, &chain_state(H256::zero()), 1, Some(&txs))
+        .await
+        .expect("build_block");
+    assert_eq!(block.hash, Some(MOCK_BLOCK_HASH));
+    assert_eq!(block.transactions.len(), txs.len());
+
+    builder
+        .set_head(&client, &rpc_url, &chain_state(MOCK_BLOCK_HASH))
+        .await
+        .expect("set_head");
+
+    let state = state.lock().await;
+    assert_eq!(state.head, MOCK_BLOCK_HASH);
+    assert_eq!(
+        state.calls,
+        ["miner_init", "miner_sealBlock", "miner_setHead"]
+    );
+}
+
+#[tokio::test]
+async fn block_builder_engine() {
+    let (state, rpc_url, engine_url) = mock_node().await;
+    let client = JsonRpcClient::new(5000);
+    let builder = BlockBuilder::Engine {
+        url: engine_url.clone(),
+        jwt_secret: JWT_SECRET,
+        tx_list: true,
+    };
+    let txs = vec![Bytes::from(vec![1]), Bytes::from(vec![2])];
+
+    builder.init(&client, &rpc_url).await.expect("init");
+    let block = builder
+        .build_block(&client, &rpc_url, &chain_state(H256::zero()), 1, Some(&txs))
+        .await
+        .expect("build_block");
+    assert_eq!(block.hash, Some(MOCK_BLOCK_HASH));
+    assert_eq!(block.transactions.len(), txs.len());
+
+    builder
+        .set_head(&client, &rpc_url, &chain_state(MOCK_BLOCK_HASH))
+        .await
+        .expect("set_head");
+
+    {
+        let state = state.lock().await;
+        assert_eq!(state.head, MOCK_BLOCK_HASH);
+        assert_eq!(state.transactions, txs);
+        assert_eq!(
+            state.calls,
+            [
+                "engine_forkchoiceUpdatedV1",
+                "engine_getPayloadV1",
+                "engine_newPayloadV1",
+                "eth_getBlockByHash",
+                "engine_forkchoiceUpdatedV1",
+            ]
+        );
+    }
+
+    // requests with a wrong secret are rejected
+    let builder = BlockBuilder::Engine {
+        url: engine_url,
+        jwt_secret: H256::repeat_byte(0x01),
+        tx_list: true,
+    };
+    let res = builder
+        .set_head(&client, &rpc_url, &chain_state(MOCK_BLOCK_HASH))
+        .await;
+    assert!(res.is_err());
+}
+
+#[tokio::test]
+async fn block_builder_engine_txpool() {
+    let (state, rpc_url, engine_url) = mock_node().await;
+    let client = JsonRpcClient::new(5000);
+    let builder = BlockBuilder::Engine {
+        url: engine_url,
+        jwt_secret: JWT_SECRET,
+        tx_list: false,
+    };
+    let pending = Bytes::from(vec![3]);
+    let txs = vec![Bytes::from(vec![1]), Bytes::from(vec![2])];
+    state.lock().await.pool.push(pending.clone());
+
+    // the transactions are submitted to the pool, which may add other transactions
+    let block = builder
+        .build_block(&client, &rpc_url, &chain_state(H256::zero()), 1, Some(&txs))
+        .await
+        .expect("build_block");
+    assert_eq!(block.transactions.len(), txs.len() + 1);
+    {
+        let state = state.lock().await;
+        assert_eq!(state.transactions, [vec![pending], txs.clone()].concat());
+        assert_eq!(
+            state.calls,
+            [
+                "eth_sendRawTransaction",
+                "eth_sendRawTransaction",
+                "engine_forkchoiceUpdatedV1",
+                "engine_getPayloadV1",
+                "engine_newPayloadV1",
+                "eth_getBlockByHash",
+            ]
+        );
+    }
+
+    // transactions that are already known are fine
+    builder
+        .build_block(&client, &rpc_url, &chain_state(H256::zero()), 1, Some(&txs))
+        .await
+        .expect("build_block");
+
+    // all transactions have to be included
+    {
+        let mut state = state.lock().await;
+        state.pool.clear();
+        state.evict = true;
+    }
+    let res = builder
+        .build_block(&client, &rpc_url, &chain_state(H256::zero()), 1, Some(&txs))
+        .await;
+    assert!(res.is_err());
+}
+
+#[tokio::test]
+async fn block_builder_engine_syncing() {
+    let (state, rpc_url, engine_url) = mock_node().await;
+    let client = JsonRpcClient::new(5000);
+    let builder = BlockBuilder::Engine {
+        url: engine_url,
+        jwt_secret: JWT_SECRET,
+        tx_list: true,
+    };
+    state.lock().await.syncing = true;
+
+    // a syncing node is retried
+    let err = builder
+        .set_head(&client, &rpc_url, &chain_state(MOCK_BLOCK_HASH))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::NotReady(_)));
+    assert!(err.is_retryable());
+
+    state.lock().await.syncing = false;
+    builder
+        .set_head(&client, &rpc_url, &chain_state(MOCK_BLOCK_HASH))
+        .await
+        .expect("set_head");
+    assert_eq!(state.lock().await.head, MOCK_BLOCK_HASH);
+}
@@ -0,0 +1,82 @@
+use coordinator::block_policy::BlockPolicy;
+use coordinator::block_policy::PendingBlock;
+
+fn pending(last_block_at: u64, pending_since: Option<u64>, pending_txs: u64) -> PendingBlock {
+    PendingBlock {
+        last_block_at,
+        pending_since,
+        pending_txs,
+        pending_gas: pending_txs * 21_000,
+    }
+}
+
+#[test]
+fn block_policy_default() {
+    // seal as soon as there is a pending transaction
+    let policy = BlockPolicy {
+        min_txs: 1,
+        ..Default::default()
+    };
+
+    assert!(!policy.should_seal(100, &pending(100, None, 0)));
+    assert!(policy.should_seal(100, &pending(100, Some(100), 1)));
+}
+
+#[test]
+fn block_policy_block_time() {
+    let policy = BlockPolicy {
+        block_time: 10,
+        min_txs: 1,
+        ..Default::default()
+    };
+
+    assert!(!policy.should_seal(105, &pending(100, Some(100), 5)));
+    assert!(policy.should_seal(110, &pending(100, Some(100), 5)));
+}
+
+#[test]
+fn block_policy_heartbeat() {
+    let policy = BlockPolicy {
+        heartbeat: Some(30),
+        min_txs: 1,
+        ..Default::default()
+    };
+
+    assert!(!policy.should_seal(129, &pending(100, None, 0)));
+    assert!(policy.should_seal(130, &pending(100, None, 0)));
+}
+
+#[test]
+fn block_policy_thresholds() {
+    let policy = BlockPolicy {
+        min_txs: 3,
+        min_gas: 100_000,
+        max_wait: Some(20),
+        ..Default::default()
+    };
+
+    // not enough transactions
+    assert!(!policy.should_seal(110, &pending(100, Some(100), 2)));
+    // enough transactions but not enough gas
+    assert!(!policy.should_seal(110, &pending(100, Some(100), 4)));
+    // enough transactions and gas
+    assert!(policy.should_seal(110, &pending(100, Some(100), 5)));
+    // waited long enough
+    assert!(policy.should_seal(120, &pending(100, Some(100), 1)));
+    // nothing to wait for
+    assert!(!policy.should_seal(120, &pending(100, None, 0)));
+}
+
+#[test]
+fn block_policy_timestamp_drift() {
+    let policy = BlockPolicy {
+        max_timestamp_drift: 2,
+        ..Default::default()
+    };
+
+    assert_eq!(policy.next_timestamp(100, 0), Some(100));
+    assert_eq!(policy.next_timestamp(100, 100), Some(101));
+    assert_eq!(policy.next_timestamp(100, 101), Some(102));
+    assert_eq!(policy.next_timestamp(100, 102), None);
+    assert_eq!(policy.next_timestamp(103, 102), Some(103));
+}
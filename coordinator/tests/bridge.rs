@@ -0,0 +1,92 @@
+use coordinator::structs::MessageBeacon;
+use ethers_core::abi::encode;
+use ethers_core::abi::Tokenizable;
+use ethers_core::types::Address;
+use ethers_core::types::Bytes;
+use ethers_core::types::Log;
+use ethers_core::types::H256;
+use ethers_core::types::U256;
+use ethers_core::utils::keccak256;
+use zkevm_common::bridge::{bridge_abi, marshal_proof, Message};
+
+#[test]
+fn message_encoding() {
+    let msg = Message::new(Address::repeat_byte(1), Address::repeat_byte(2), 3.into())
+        .with_fee(4.into())
+        .with_deadline(5.into())
+        .with_nonce(6.into())
+        .with_data(Bytes::from(vec![7, 8, 9]));
+
+    // `MessageDispatched` carries the abi encoded message, the id is the hash of it
+    let data = encode(&[
+        msg.from.into_token(),
+        msg.to.into_token(),
+        msg.value.into_token(),
+        msg.fee.into_token(),
+        msg.deadline.into_token(),
+        msg.nonce.into_token(),
+        msg.data.clone().into_token(),
+    ]);
+    assert_eq!(msg.id(), H256::from(keccak256(&data)));
+
+    let log = Log {
+        topics: vec![bridge_abi().event("MessageDispatched").unwrap().signature()],
+        data: data.into(),
+        ..Default::default()
+    };
+    assert_eq!(Message::from_log(&log).expect("from_log"), msg);
+
+    let beacon = MessageBeacon {
+        id: msg.id(),
+        from: msg.from,
+        to: msg.to,
+        value: msg.value,
+        fee: msg.fee,
+        deadline: msg.deadline,
+        nonce: msg.nonce,
+        calldata: msg.data.to_vec(),
+    };
+    assert_eq!(beacon.storage_slot(), msg.storage_slot());
+    assert_eq!(beacon.message(), msg);
+
+    let abi = bridge_abi();
+    let calldata = msg.dispatch_calldata();
+    let tokens = abi
+        .function("dispatchMessage")
+        .unwrap()
+        .decode_input(&calldata[4..])
+        .expect("decode_input");
+    assert_eq!(
+        &calldata[..4],
+        abi.function("dispatchMessage").unwrap().short_signature()
+    );
+    assert_eq!(tokens[0].clone().into_address(), Some(msg.to));
+    assert_eq!(tokens[1].clone().into_uint(), Some(msg.fee));
+    assert_eq!(tokens[2].clone().into_uint(), Some(msg.deadline));
+    assert_eq!(tokens[3].clone().into_uint(), Some(msg.nonce));
+    assert_eq!(tokens[4].clone().into_bytes(), Some(msg.data.to_vec()));
+
+    let proof = Bytes::from(vec![0xaa; 3]);
+    let calldata = msg.delivery_calldata(proof.clone());
+    let tokens = abi
+        .function("deliverMessageWithProof")
+        .unwrap()
+        .decode_input(&calldata[4..])
+        .expect("decode_input");
+    assert_eq!(tokens[0].clone().into_address(), Some(msg.from));
+    assert_eq!(tokens[2].clone().into_uint(), Some(U256::from(3)));
+    assert_eq!(tokens[7].clone().into_bytes(), Some(proof.to_vec()));
+}
+
+#[test]
+fn message_proof_encoding() {
+    let account_proof = vec![Bytes::from(vec![1, 2]), Bytes::from(vec![3])];
+    let storage_proof = vec![Bytes::from(vec![4])];
+    assert_eq!(
+        marshal_proof(&account_proof, &storage_proof).expect("marshal_proof"),
+        vec![2, 1, 2, 3, 1, 4]
+    );
+
+    let too_many = vec![Bytes::from(vec![0]); 256];
+    assert!(marshal_proof(&too_many, &storage_proof).is_err());
+}
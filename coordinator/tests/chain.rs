@@ -1,9 +1,7 @@
 mod common;
 
 use crate::common::get_shared_state;
-use crate::common::zkevm_abi;
 use coordinator::utils::*;
-use ethers_core::abi::encode;
 use ethers_core::abi::AbiParser;
 use ethers_core::abi::Tokenizable;
 use ethers_core::types::Address;
@@ -12,15 +10,14 @@ use ethers_core::types::TransactionReceipt;
 use ethers_core::types::H256;
 use ethers_core::types::U256;
 use ethers_core::types::U64;
-use ethers_core::utils::keccak256;
 use ethers_signers::Signer;
+use zkevm_common::bridge::{Layer, Message, MessageStatus, L2_MESSAGE_DELIVERER};
 use zkevm_common::json_rpc::jsonrpc_request;
-use zkevm_common::json_rpc::jsonrpc_request_client;
 
 #[tokio::test]
 async fn native_deposit() {
-    let abi = zkevm_abi();
     let shared_state = await_state!();
+    let bridge = shared_state.bridge_client().await;
     let mut deposits: Vec<H256> = Vec::new();
     let receiver = Address::zero();
     let mut expected_balance: U256 = jsonrpc_request(
@@ -30,48 +27,40 @@ async fn native_deposit() {
     )
     .await
     .expect("eth_getBalance");
-    let l1_bridge_addr = Some(shared_state.config.lock().await.l1_bridge);
+    let from_block: U64 = jsonrpc_request(
+        &shared_state.config.lock().await.l2_rpc_url,
+        "eth_blockNumber",
+        (),
+    )
+    .await
+    .expect("eth_blockNumber");
 
     {
         // create deposits
         for _ in 0..9 {
-            let from = shared_state.ro.l1_wallet.address();
-            let to = receiver;
-            let value = U256::from(1u64);
-            let fee = U256::zero();
-            let deadline = U256::from(0xffffffffffffffffu64);
-            let nonce: U256 = rand::random::<usize>().into();
-            let data = Bytes::from([]);
-
-            let calldata = abi
-                .function("dispatchMessage")
-                .unwrap()
-                .encode_input(&[
-                    to.into_token(),
-                    fee.into_token(),
-                    deadline.into_token(),
-                    nonce.into_token(),
-                    data.clone().into_token(),
-                ])
-                .expect("calldata");
-
-            let id: H256 = keccak256(encode(&[
-                from.into_token(),
-                to.into_token(),
-                value.into_token(),
-                fee.into_token(),
-                deadline.into_token(),
-                nonce.into_token(),
-                data.into_token(),
-            ]))
-            .into();
-
-            deposits.push(id);
-            expected_balance += value;
-            shared_state
-                .transaction_to_l1(l1_bridge_addr, value, calldata)
+            let msg = Message::new(
+                shared_state.ro.l1_wallet.address(),
+                receiver,
+                U256::from(1u64),
+            );
+            let tx_hash = bridge
+                .deposit(&shared_state.ro.l1_wallet, &msg)
+                .await
+                .expect("deposit");
+            bridge
+                .wait_for_receipt(Layer::L1, tx_hash, 60_000)
                 .await
                 .expect("receipt");
+            assert_eq!(
+                bridge
+                    .message_status(Layer::L1, &msg.id(), from_block)
+                    .await
+                    .expect("message_status"),
+                MessageStatus::Pending
+            );
+
+            deposits.push(msg.id());
+            expected_balance += msg.value;
         }
     }
 
@@ -80,6 +69,14 @@ async fn native_deposit() {
     // verify that all deposit are picked up
     {
         for id in deposits {
+            let status = bridge
+                .message_status(Layer::L1, &id, from_block)
+                .await
+                .expect("message_status");
+            assert!(
+                matches!(status, MessageStatus::Delivered(_)),
+                "message should be delivered"
+            );
             let found = shared_state
                 .rw
                 .lock()
@@ -106,9 +103,9 @@ async fn native_deposit() {
 
 #[tokio::test]
 async fn native_withdraw() {
-    let abi = zkevm_abi();
     let shared_state = await_state!();
-    let mut messages: Vec<H256> = Vec::new();
+    let bridge = shared_state.bridge_client().await;
+    let mut messages: Vec<Message> = Vec::new();
     let receiver = Address::zero();
     let mut expected_balance: U256 = jsonrpc_request(
         &shared_state.config.lock().await.l1_rpc_url,
@@ -118,10 +115,18 @@ async fn native_withdraw() {
     .await
     .expect("eth_getBalance");
 
-    shared_state.sync().await;
-    shared_state.mine().await;
+    let from_block: U64 = jsonrpc_request(
+        &shared_state.config.lock().await.l1_rpc_url,
+        "eth_blockNumber",
+        (),
+    )
+    .await
+    .expect("eth_blockNumber");
+
+    shared_state.sync().await.expect("sync");
+    shared_state.mine().await.expect("mine");
 
-    {
+    let block_number = {
         let mut tx_nonce: U256 = jsonrpc_request(
             &shared_state.config.lock().await.l2_rpc_url,
             "eth_getTransactionCount",
@@ -131,48 +136,24 @@ async fn native_withdraw() {
         .expect("nonce");
         let mut txs = vec![];
         for _ in 0..4 {
-            let from = shared_state.ro.l2_wallet.address();
-            let to = receiver;
-            let value = U256::from(1u64);
-            let fee = U256::zero();
-            let deadline = U256::from(0xffffffffffffffffu64);
-            let nonce: U256 = rand::random::<usize>().into();
-            let data = Bytes::from([]);
-            let calldata = abi
-                .function("dispatchMessage")
-                .unwrap()
-                .encode_input(&[
-                    to.into_token(),
-                    fee.into_token(),
-                    deadline.into_token(),
-                    nonce.into_token(),
-                    data.clone().into_token(),
-                ])
-                .expect("calldata");
-
-            let id: H256 = keccak256(encode(&[
-                from.into_token(),
-                to.into_token(),
-                value.into_token(),
-                fee.into_token(),
-                deadline.into_token(),
-                nonce.into_token(),
-                data.into_token(),
-            ]))
-            .into();
-
-            messages.push(id);
-            expected_balance += value;
+            let msg = Message::new(
+                shared_state.ro.l2_wallet.address(),
+                receiver,
+                U256::from(1u64),
+            );
+            expected_balance += msg.value;
             txs.push(
                 shared_state
                     .sign_l2(
-                        shared_state.ro.l2_message_dispatcher_addr,
-                        value,
+                        bridge.dispatcher(Layer::L2),
+                        msg.value + msg.fee,
                         tx_nonce,
-                        calldata,
+                        msg.dispatch_calldata(),
                     )
-                    .await,
+                    .await
+                    .expect("sign_l2"),
             );
+            messages.push(msg);
             tx_nonce = tx_nonce + 1;
         }
 
@@ -180,14 +161,40 @@ async fn native_withdraw() {
             .mine_block(Some(&txs))
             .await
             .expect("mine_block");
+        let block_number: U64 = jsonrpc_request(
+            &shared_state.config.lock().await.l2_rpc_url,
+            "eth_blockNumber",
+            (),
+        )
+        .await
+        .expect("eth_blockNumber");
+
+        block_number
+    };
+
+    for msg in messages.iter() {
+        assert_eq!(
+            bridge
+                .message_status(Layer::L2, &msg.id(), from_block)
+                .await
+                .expect("message_status"),
+            MessageStatus::Pending
+        );
     }
 
     finalize_chain!(shared_state);
 
+    let finalized = bridge
+        .wait_for_finalization(block_number, from_block, 60_000)
+        .await
+        .expect("wait_for_finalization");
+    assert!(finalized.number >= block_number);
+
     // verify that all messages are picked up
     {
-        shared_state.sync().await;
-        for id in messages {
+        shared_state.sync().await.expect("sync");
+        for msg in messages.iter() {
+            let id = msg.id();
             let found = shared_state
                 .rw
                 .lock()
@@ -196,6 +203,23 @@ async fn native_withdraw() {
                 .iter()
                 .any(|e| e == &id);
             assert!(found, "message id should exist");
+
+            let status = bridge
+                .message_status(Layer::L2, &id, from_block)
+                .await
+                .expect("message_status");
+            assert!(
+                matches!(status, MessageStatus::Delivered(_)),
+                "message should be delivered"
+            );
+
+            // the slot stays set after delivery, the proof can still be built
+            let proof = bridge
+                .build_delivery_proof(Layer::L2, msg, finalized.hash)
+                .await
+                .expect("build_delivery_proof");
+            assert_eq!(proof.proof.address, bridge.dispatcher(Layer::L2));
+            assert!(!proof.data.is_empty());
         }
     }
 
@@ -350,7 +374,7 @@ async fn hop_cross_chain_message() {
             .transaction_to_l2(Some(hop), amount, calldata)
             .await
             .expect("tx_hash");
-        shared_state.mine().await;
+        shared_state.mine().await.expect("mine");
         wait_for_tx!(tx_hash, &shared_state.config.lock().await.l2_rpc_url);
     }
 
@@ -368,7 +392,7 @@ async fn hop_cross_chain_message() {
             .transaction_to_l2(Some(hop), U256::zero(), calldata)
             .await
             .expect("tx_hash_commit");
-        shared_state.mine().await;
+        shared_state.mine().await.expect("mine");
         wait_for_tx!(tx_hash_commit, &shared_state.config.lock().await.l2_rpc_url);
     }
 
@@ -393,7 +417,6 @@ async fn hop_cross_chain_message() {
 
 #[tokio::test]
 async fn native_deposit_revert() {
-    let abi = zkevm_abi();
     let shared_state = await_state!();
     let mut deposits: Vec<H256> = Vec::new();
     let receiver = Address::zero();
@@ -419,41 +442,15 @@ async fn native_deposit_revert() {
         let mut txs = Vec::new();
         for i in 0..30 {
             let should_revert = i % 2 == 0;
-            let from = shared_state.ro.l1_wallet.address();
             let to = match should_revert {
-                true => shared_state.ro.l2_message_deliverer_addr,
+                true => L2_MESSAGE_DELIVERER,
                 false => receiver,
             };
-            let value = U256::from(1u64);
-            let fee = U256::zero();
-            let deadline = U256::from(0xffffffffffffffffu64);
-            let nonce: U256 = rand::random::<usize>().into();
-            let data = Bytes::from([]);
-
-            let calldata = abi
-                .function("dispatchMessage")
-                .unwrap()
-                .encode_input(&[
-                    to.into_token(),
-                    fee.into_token(),
-                    deadline.into_token(),
-                    nonce.into_token(),
-                    data.clone().into_token(),
-                ])
-                .expect("calldata");
-
-            let id: H256 = keccak256(encode(&[
-                from.into_token(),
-                to.into_token(),
-                value.into_token(),
-                fee.into_token(),
-                deadline.into_token(),
-                nonce.into_token(),
-                data.into_token(),
-            ]))
-            .into();
+            let msg = Message::new(shared_state.ro.l1_wallet.address(), to, U256::from(1u64));
+            let value = msg.value;
+            let calldata = msg.dispatch_calldata();
 
-            deposits.push(id);
+            deposits.push(msg.id());
             if !should_revert {
                 expected_balance += value;
             }
@@ -468,7 +465,8 @@ async fn native_deposit_revert() {
                     calldata,
                     tx_nonce,
                 )
-                .await,
+                .await
+                .expect("sign_transaction_l1"),
             );
 
             tx_nonce = tx_nonce + 1;
@@ -476,14 +474,15 @@ async fn native_deposit_revert() {
 
         let mut tx_hashes = Vec::new();
         for raw_tx in &txs {
-            let resp: Result<H256, String> = jsonrpc_request_client(
-                RPC_REQUEST_TIMEOUT,
-                &shared_state.ro.http_client,
-                &shared_state.config.lock().await.l1_rpc_url,
-                "eth_sendRawTransaction",
-                [raw_tx],
-            )
-            .await;
+            let resp: Result<H256, _> = shared_state
+                .ro
+                .http_client
+                .request(
+                    &shared_state.config.lock().await.l1_rpc_url,
+                    "eth_sendRawTransaction",
+                    [raw_tx],
+                )
+                .await;
 
             tx_hashes.push(resp.unwrap());
         }
@@ -535,7 +534,7 @@ async fn zero_eth_transfer() {
         )
         .await
         .expect("tx_hash");
-    shared_state.mine().await;
+    shared_state.mine().await.expect("mine");
     wait_for_tx!(tx_hash, &shared_state.config.lock().await.l2_rpc_url);
 
     finalize_chain!(shared_state);
@@ -553,7 +552,7 @@ async fn keccak() {
         .transaction_to_l2(None, U256::zero(), bytecode)
         .await
         .expect("tx_hash");
-    shared_state.mine().await;
+    shared_state.mine().await.expect("mine");
     shared_state.config.lock().await.dummy_prover = true;
     finalize_chain!(shared_state);
 
@@ -563,7 +562,7 @@ async fn keccak() {
         .transaction_to_l2(contract_addr, U256::zero(), vec![])
         .await
         .expect("tx_hash");
-    shared_state.mine().await;
+    shared_state.mine().await.expect("mine");
     wait_for_tx!(tx_hash, &shared_state.config.lock().await.l2_rpc_url);
     shared_state.config.lock().await.dummy_prover = false;
     shared_state.config.lock().await.mock_prover = true;
@@ -573,43 +572,18 @@ async fn keccak() {
 
 #[tokio::test]
 async fn l1_l2_sync_test() {
-    let abi = zkevm_abi();
     let shared_state = await_state!();
+    let bridge = shared_state.bridge_client().await;
     let mut deposits: Vec<H256> = Vec::new();
 
     for _ in 0..2 {
-        let l1_bridge_addr = Some(shared_state.config.lock().await.l1_bridge);
         // create deposits
-        let from = shared_state.ro.l1_wallet.address();
-        let to = Address::zero();
-        let value = U256::from(1u64);
-        let fee = U256::zero();
-        let deadline = U256::from(0xffffffffffffffffu64);
-        let nonce: U256 = rand::random::<usize>().into();
-        let data = Bytes::from([]);
-        let calldata = abi
-            .function("dispatchMessage")
-            .unwrap()
-            .encode_input(&[
-                to.into_token(),
-                fee.into_token(),
-                deadline.into_token(),
-                nonce.into_token(),
-                data.clone().into_token(),
-            ])
-            .expect("calldata");
-
-        let id: H256 = keccak256(encode(&[
-            from.into_token(),
-            to.into_token(),
-            value.into_token(),
-            fee.into_token(),
-            deadline.into_token(),
-            nonce.into_token(),
-            data.into_token(),
-        ]))
-        .into();
-        deposits.push(id);
+        let msg = Message::new(
+            shared_state.ro.l1_wallet.address(),
+            Address::zero(),
+            U256::from(1u64),
+        );
+        deposits.push(msg.id());
 
         // create a block with zero logs before the bridge deposit
         let _ = shared_state
@@ -621,8 +595,12 @@ async fn l1_l2_sync_test() {
             .await
             .expect("receipt");
         // deposit
-        shared_state
-            .transaction_to_l1(l1_bridge_addr, value, calldata)
+        let tx_hash = bridge
+            .deposit(&shared_state.ro.l1_wallet, &msg)
+            .await
+            .expect("deposit");
+        bridge
+            .wait_for_receipt(Layer::L1, tx_hash, 60_000)
             .await
             .expect("receipt");
     }
@@ -0,0 +1,107 @@
+use coordinator::circuit_capacity::select_transactions;
+use ethers_core::types::{Address, Bytes, Transaction, H256};
+use std::collections::HashSet;
+use zkevm_common::prover::CircuitConfig;
+
+fn tx(id: u8, from: u8, nonce: u64, gas: u64, input: usize) -> Transaction {
+    Transaction {
+        hash: H256::repeat_byte(id),
+        from: Address::repeat_byte(from),
+        to: Some(Address::zero()),
+        nonce: nonce.into(),
+        gas: gas.into(),
+        input: Bytes::from(vec![1; input]),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn circuit_capacity_tiers() {
+    assert_eq!(
+        CircuitConfig::for_gas(21_000).unwrap().block_gas_limit,
+        63000
+    );
+    assert_eq!(
+        CircuitConfig::for_gas(63_001).unwrap().block_gas_limit,
+        150000
+    );
+    assert_eq!(CircuitConfig::max().block_gas_limit, 300000);
+    assert!(CircuitConfig::for_gas(300_001).is_none());
+}
+
+#[test]
+fn circuit_capacity_select_all() {
+    let config = CircuitConfig::max();
+    let pending = vec![tx(1, 1, 0, 21_000, 0), tx(2, 2, 0, 21_000, 100)];
+    let selection = select_transactions(config, &pending, &HashSet::new());
+
+    assert!(selection.is_complete());
+    assert_eq!(selection.included.len(), 2);
+    assert_eq!(selection.usage.calldata, 100);
+    assert_eq!(selection.usage.gas, 42_000);
+}
+
+#[test]
+fn circuit_capacity_select_within_limits() {
+    let config = CircuitConfig::max();
+    let pending = vec![
+        tx(1, 1, 0, 200_000, 0),
+        // does not fit into the block anymore
+        tx(2, 1, 1, 200_000, 0),
+        // same sender, must not be included before its predecessor
+        tx(3, 1, 2, 21_000, 0),
+        tx(4, 2, 0, 21_000, 0),
+        // can never be proven
+        tx(5, 3, 0, 21_000, config.max_calldata + 1),
+        tx(6, 3, 1, 21_000, 0),
+    ];
+    let selection = select_transactions(config, &pending, &HashSet::new());
+
+    let included: Vec<H256> = selection.included.iter().map(|tx| tx.hash).collect();
+    assert_eq!(included, [H256::repeat_byte(1), H256::repeat_byte(4)]);
+    assert_eq!(
+        selection.deferred,
+        [
+            H256::repeat_byte(2),
+            H256::repeat_byte(3),
+            H256::repeat_byte(6)
+        ]
+    );
+    assert_eq!(selection.unprovable, [H256::repeat_byte(5)]);
+    assert!(!selection.is_complete());
+}
+
+#[test]
+fn circuit_capacity_select_excluded() {
+    let config = CircuitConfig::max();
+    let pending = vec![tx(1, 1, 0, 21_000, 0), tx(2, 2, 0, 21_000, 0)];
+    let excluded = HashSet::from([H256::repeat_byte(1)]);
+    let selection = select_transactions(config, &pending, &excluded);
+
+    assert_eq!(selection.included.len(), 1);
+    assert_eq!(selection.excluded, [H256::repeat_byte(1)]);
+    assert!(!selection.is_complete());
+}
+
+#[test]
+fn circuit_capacity_select_gas_limit() {
+    let config = CircuitConfig::max();
+    // the gas limit exceeds the circuit but the gas used may not
+    let pending = vec![tx(1, 1, 0, 1_000_000, 0), tx(2, 2, 0, 21_000, 0)];
+    let selection = select_transactions(config, &pending, &HashSet::new());
+
+    let included: Vec<H256> = selection.included.iter().map(|tx| tx.hash).collect();
+    assert_eq!(included, [H256::repeat_byte(1)]);
+    assert_eq!(selection.deferred, [H256::repeat_byte(2)]);
+    assert!(selection.unprovable.is_empty());
+    assert!(selection.unchecked_gas);
+
+    // only on its own
+    let pending = vec![tx(2, 2, 0, 21_000, 0), tx(1, 1, 0, 1_000_000, 0)];
+    let selection = select_transactions(config, &pending, &HashSet::new());
+
+    let included: Vec<H256> = selection.included.iter().map(|tx| tx.hash).collect();
+    assert_eq!(included, [H256::repeat_byte(2)]);
+    assert_eq!(selection.deferred, [H256::repeat_byte(1)]);
+    assert!(!selection.unchecked_gas);
+}
@@ -3,14 +3,20 @@ use coordinator::shared_state::SharedState;
 use ethers_core::abi::decode;
 use ethers_core::abi::AbiParser;
 use ethers_core::abi::ParamType;
+use ethers_core::types::Address;
 use ethers_core::types::Bytes;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
 use serde::de::IntoDeserializer;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::BufReader;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::sync::OnceCell;
+use zkevm_common::json_rpc_server::JsonRpcServer;
 
 fn deserialize_bytes<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
     let str = String::deserialize(deserializer).expect("String");
@@ -61,6 +67,24 @@ impl ContractArtifact {
         calldata: &Bytes,
         shared_state: &SharedState,
     ) -> Result<Trace, String> {
+        self.l1_trace_with_code(calldata, shared_state, &[]).await
+    }
+
+    /// Like `l1_trace`, with additional contracts `code` deployed at the given addresses.
+    pub async fn l1_trace_with_code(
+        &self,
+        calldata: &Bytes,
+        shared_state: &SharedState,
+        code: &[(Address, Bytes)],
+    ) -> Result<Trace, String> {
+        let mut state_overrides = serde_json::json!({
+            "0x00000000000000000000000000000000000f0000": {
+                "code": self.bin_runtime,
+            },
+        });
+        for (addr, code) in code {
+            state_overrides[format!("{:?}", addr)] = serde_json::json!({ "code": code });
+        }
         let req = serde_json::json!([
             {
                 "to": "0x00000000000000000000000000000000000f0000",
@@ -69,11 +93,7 @@ impl ContractArtifact {
             "latest",
             {
                 "Limit": 1,
-                "stateOverrides": {
-                    "0x00000000000000000000000000000000000f0000": {
-                        "code": self.bin_runtime,
-                    },
-                },
+                "stateOverrides": state_overrides,
             },
         ]);
         let trace: serde_json::Value = shared_state
@@ -82,7 +102,10 @@ impl ContractArtifact {
             .expect("debug_traceCall");
         let trace: Trace = serde_json::from_value(trace).unwrap();
         if trace.failed {
-            let revert_reason = decode(&[ParamType::String], &trace.return_value.as_ref()[4..]);
+            let revert_reason = decode(
+                &[ParamType::String],
+                trace.return_value.as_ref().get(4..).unwrap_or_default(),
+            );
             if revert_reason.is_ok() {
                 return Err(format!("{:?}", revert_reason));
             }
@@ -100,15 +123,6 @@ pub fn proxy_abi() -> ethers_core::abi::Contract {
         .unwrap()
 }
 
-pub fn zkevm_abi() -> ethers_core::abi::Contract {
-    AbiParser::default()
-        .parse(&[
-            // zkevm native bridge
-            "function dispatchMessage(address to, uint256 fee, uint256 deadline, uint256 nonce, bytes calldata _data) external payable",
-        ])
-        .expect("parse abi")
-}
-
 static ONCE: OnceCell<Mutex<SharedState>> = OnceCell::const_new();
 
 pub async fn get_shared_state() -> &'static Mutex<SharedState> {
@@ -117,8 +131,8 @@ pub async fn get_shared_state() -> &'static Mutex<SharedState> {
             env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug"))
                 .is_test(true)
                 .try_init();
-        let shared_state = SharedState::from_env().await;
-        shared_state.init().await;
+        let shared_state = SharedState::from_env().await.expect("shared state");
+        shared_state.init().await.expect("init");
 
         Mutex::new(shared_state)
     })
@@ -136,10 +150,10 @@ macro_rules! await_state {
 macro_rules! sync {
     ($shared_state:expr) => {
         // sync bridge and process events
-        $shared_state.sync().await;
+        $shared_state.sync().await.expect("sync");
         while $shared_state.rw.lock().await.l1_message_queue.len() > 0 {
-            $shared_state.mine().await;
-            $shared_state.sync().await;
+            $shared_state.mine().await.expect("mine");
+            $shared_state.sync().await.expect("sync");
             sleep!(300);
         }
     };
@@ -177,14 +191,14 @@ macro_rules! finalize_chain {
             drop(rw);
 
             sync!($shared_state);
-            $shared_state.submit_blocks().await;
+            $shared_state.submit_blocks().await.expect("submit_blocks");
             $shared_state
                 .finalize_blocks()
                 .await
                 .expect("finalize_blocks");
             sync!($shared_state);
             while $shared_state.rw.lock().await.l2_message_queue.len() != 0 {
-                $shared_state.relay_to_l1().await;
+                $shared_state.relay_to_l1().await.expect("relay_to_l1");
                 sync!($shared_state);
             }
         }
@@ -198,3 +212,37 @@ macro_rules! sleep {
         sleep(Duration::from_millis($ms)).await;
     }};
 }
+
+/// Serves `rpc` with `state` on a random local port.
+/// Requests for which `is_authorized` returns `false` are answered with `401`.
+pub async fn mock_node<S: Clone + Send + Sync + 'static>(
+    rpc: JsonRpcServer<S>,
+    state: S,
+    is_authorized: fn(&Request<Body>) -> bool,
+) -> SocketAddr {
+    let rpc = Arc::new(rpc);
+    let service = make_service_fn(move |_| {
+        let state = state.clone();
+        let rpc = rpc.clone();
+        let service = service_fn(move |req: Request<Body>| {
+            let state = state.clone();
+            let rpc = rpc.clone();
+            async move {
+                if !is_authorized(&req) {
+                    let mut resp = Response::default();
+                    *resp.status_mut() = StatusCode::UNAUTHORIZED;
+                    return Ok::<_, hyper::Error>(resp);
+                }
+
+                Ok(rpc.handle(state, req).await)
+            }
+        });
+
+        async move { Ok::<_, hyper::Error>(service) }
+    });
+    let server = Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(service);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+
+    addr
+}
@@ -62,7 +62,7 @@ macro_rules! deploy_l2 {
             .transaction_to_l2(None, U256::zero(), $DEPLOY_CODE)
             .await
             .expect("tx_hash");
-        shared_state.mine().await;
+        shared_state.mine().await.expect("mine");
 
         let receipt: TransactionReceipt = shared_state
             .request_l2("eth_getTransactionReceipt", [tx_hash])
@@ -85,7 +85,7 @@ macro_rules! deploy_l2 {
             )
             .await
             .expect("tx_hash");
-        shared_state.mine().await;
+        shared_state.mine().await.expect("mine");
 
         let receipt: TransactionReceipt = shared_state
             .request_l2("eth_getTransactionReceipt", [tx_hash])
@@ -0,0 +1,42 @@
+mod common;
+
+use coordinator::structs::BlockHeader;
+use coordinator::utils::get_header_rlp;
+use coordinator::utils::JsonRpcClient;
+use ethers_core::types::{Bytes, H256};
+use ethers_core::utils::keccak256;
+use hyper::Uri;
+use zkevm_common::json_rpc_server::JsonRpcServer;
+
+const HEADER_RLP: [u8; 4] = [0xc3, 0x01, 0x02, 0x03];
+
+/// Starts a node that returns `HEADER_RLP` for any block number.
+async fn mock_node() -> Uri {
+    let rpc = JsonRpcServer::new().method("debug_getHeaderRlp", |_: (), (_,): (u64,)| async move {
+        Ok::<_, String>(Bytes::from(HEADER_RLP.to_vec()))
+    });
+    let addr = common::mock_node(rpc, (), |_| true).await;
+
+    format!("http://{}/", addr).parse().unwrap()
+}
+
+#[tokio::test]
+async fn header_rlp_hash() {
+    let uri = mock_node().await;
+    let client = JsonRpcClient::new(5000);
+    let mut header = BlockHeader {
+        parent_hash: H256::zero(),
+        hash: H256::from(keccak256(HEADER_RLP)),
+        number: 1.into(),
+        state_root: H256::zero(),
+    };
+
+    let header_rlp = get_header_rlp(&client, &uri, &header)
+        .await
+        .expect("header rlp");
+    assert_eq!(header_rlp.as_ref(), HEADER_RLP);
+
+    // e.g. the chain reorged between fetching the header and the rlp
+    header.hash = H256::repeat_byte(1);
+    assert!(get_header_rlp(&client, &uri, &header).await.is_err());
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use hyper::{Body, Client, Method, Request, StatusCode};
+use serde_json::json;
+use std::net::SocketAddr;
+use zkevm_common::json_rpc_server::JsonRpcServer;
+
+/// Starts a node that echoes the parameter of `echo`.
+async fn mock_node() -> SocketAddr {
+    let rpc = JsonRpcServer::new().method("echo", |_: (), (value,): (u64,)| async move {
+        Ok::<_, String>(value)
+    });
+
+    common::mock_node(rpc, (), |_| true).await
+}
+
+/// Posts `body` and returns the status and the decoded response, if any.
+async fn post(addr: &SocketAddr, body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(format!("http://{}/", addr))
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let resp = Client::new().request(req).await.expect("request");
+    let status = resp.status();
+    let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+    let value = match body.is_empty() {
+        true => serde_json::Value::Null,
+        false => serde_json::from_slice(&body).expect("json"),
+    };
+
+    (status, value)
+}
+
+#[tokio::test]
+async fn json_rpc_server_null_id() {
+    let addr = mock_node().await;
+
+    // a null id is a valid request and must be answered
+    let (status, resp) = post(
+        &addr,
+        json!({ "jsonrpc": "2.0", "id": null, "method": "echo", "params": [7] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp, json!({ "jsonrpc": "2.0", "id": null, "result": 7 }));
+
+    // errors are answered with the null id as well
+    let (_, resp) = post(
+        &addr,
+        json!({ "jsonrpc": "2.0", "id": null, "method": "unknown", "params": [] }),
+    )
+    .await;
+    assert_eq!(resp["id"], serde_json::Value::Null);
+    assert!(resp["error"].is_object());
+
+    // without an id the request is a notification
+    let (status, _) = post(
+        &addr,
+        json!({ "jsonrpc": "2.0", "method": "echo", "params": [7] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // only the requests of a batch are answered
+    let (_, resp) = post(
+        &addr,
+        json!([
+            { "jsonrpc": "2.0", "method": "echo", "params": [1] },
+            { "jsonrpc": "2.0", "id": null, "method": "echo", "params": [2] },
+            { "jsonrpc": "2.0", "id": 3, "method": "echo", "params": [3] },
+        ]),
+    )
+    .await;
+    assert_eq!(
+        resp,
+        json!([
+            { "jsonrpc": "2.0", "id": null, "result": 2 },
+            { "jsonrpc": "2.0", "id": 3, "result": 3 },
+        ])
+    );
+}
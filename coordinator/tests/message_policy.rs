@@ -0,0 +1,65 @@
+use coordinator::message_policy::MessageDelivery;
+use coordinator::message_policy::MessagePolicy;
+use coordinator::structs::MessageBeacon;
+use ethers_core::types::H256;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+fn message(id: u8) -> MessageBeacon {
+    MessageBeacon {
+        id: H256::repeat_byte(id),
+        from: Default::default(),
+        to: Default::default(),
+        value: Default::default(),
+        fee: Default::default(),
+        deadline: Default::default(),
+        nonce: Default::default(),
+        calldata: Vec::new(),
+    }
+}
+
+#[test]
+fn message_policy_order() {
+    let policy = MessagePolicy {
+        max_age: 60,
+        max_attempts: 3,
+    };
+    let mut queue: VecDeque<MessageBeacon> = (1..=4).map(message).collect();
+    let deliveries: HashMap<H256, MessageDelivery> = [(1, 90), (2, 30), (3, 10), (4, 50)]
+        .into_iter()
+        .map(|(id, queued_at)| {
+            let id = H256::repeat_byte(id);
+            (id, MessageDelivery::new(id, queued_at))
+        })
+        .collect();
+
+    policy.order(100, &mut queue, &deliveries);
+    let ids: Vec<H256> = queue.iter().map(|msg| msg.id).collect();
+    // 3 and 2 are forced, oldest first
+    assert_eq!(
+        ids,
+        [
+            H256::repeat_byte(3),
+            H256::repeat_byte(2),
+            H256::repeat_byte(1),
+            H256::repeat_byte(4)
+        ]
+    );
+}
+
+#[test]
+fn message_policy_attempts() {
+    let policy = MessagePolicy {
+        max_age: 60,
+        max_attempts: 2,
+    };
+    let mut delivery = MessageDelivery::new(H256::zero(), 0);
+
+    assert!(!policy.record_failure(&mut delivery, "execution reverted: a".to_string()));
+    assert!(policy.record_failure(&mut delivery, "execution reverted: b".to_string()));
+    assert_eq!(delivery.attempts, 2);
+    assert_eq!(
+        delivery.errors,
+        ["execution reverted: a", "execution reverted: b"]
+    );
+}
@@ -3,8 +3,8 @@ use coordinator::shared_state::SharedState;
 // test for: https://github.com/privacy-scaling-explorations/zkevm-chain/issues/5
 #[tokio::test]
 async fn access_list_regression() {
-    let shared_state = SharedState::from_env().await;
-    shared_state.init().await;
+    let shared_state = SharedState::from_env().await.expect("shared state");
+    shared_state.init().await.expect("init");
 
     // CODESIZE
     // CODESIZE
@@ -0,0 +1,95 @@
+use coordinator::mpt::verify_account_proof;
+use coordinator::mpt::verify_eth_proof;
+use coordinator::mpt::verify_storage_proof;
+use coordinator::structs::ProofRequest;
+use ethers_core::types::Bytes;
+use ethers_core::types::H256;
+use ethers_core::types::U256;
+use std::fs::File;
+use std::io::BufReader;
+use zkevm_common::bridge::marshal_proof;
+
+#[derive(Debug, serde::Deserialize)]
+struct BlockHeader {
+    #[serde(rename = "stateRoot")]
+    state_root: H256,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct TestData {
+    block: BlockHeader,
+    proof: ProofRequest,
+}
+
+fn load_test_data() -> Vec<TestData> {
+    std::fs::read_dir("tests/patricia/")
+        .unwrap()
+        .map(|entry| {
+            let file = File::open(entry.expect("path").path()).expect("file");
+            serde_json::from_reader(BufReader::new(file)).expect("json")
+        })
+        .collect()
+}
+
+#[test]
+fn mpt_verify_proof() {
+    for test_data in load_test_data() {
+        let state_root = test_data.block.state_root;
+        let proof = test_data.proof;
+        verify_eth_proof(&state_root, &proof).expect("verify_eth_proof");
+
+        let account = verify_account_proof(&state_root, &proof.address, &proof.account_proof)
+            .expect("account proof")
+            .expect("account");
+        assert_eq!(account.storage_root, proof.storage_hash);
+        assert_eq!(account.nonce, proof.nonce);
+        assert_eq!(account.balance, proof.balance);
+
+        for storage_proof in proof.storage_proof.iter() {
+            let value = verify_storage_proof(
+                &account.storage_root,
+                &storage_proof.key,
+                &storage_proof.proof,
+            )
+            .expect("storage proof");
+            assert_eq!(value, storage_proof.value, "{:?}", storage_proof.key);
+        }
+    }
+}
+
+#[test]
+fn mpt_verify_proof_invalid() {
+    for test_data in load_test_data() {
+        let state_root = test_data.block.state_root;
+        let mut proof = test_data.proof;
+
+        // wrong state root
+        assert!(verify_eth_proof(&H256::repeat_byte(1), &proof).is_err());
+
+        // wrong storage value
+        let mut storage_proof = proof.storage_proof.pop().expect("storage proof");
+        storage_proof.value += U256::one();
+        proof.storage_proof.push(storage_proof);
+        assert!(verify_eth_proof(&state_root, &proof).is_err());
+
+        // tampered account proof
+        let node = proof.account_proof.last_mut().expect("account proof");
+        let mut data = node.to_vec();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        *node = Bytes::from(data);
+        assert!(verify_account_proof(&state_root, &proof.address, &proof.account_proof).is_err());
+    }
+}
+
+#[test]
+fn mpt_marshal_proof_limit() {
+    let proof = vec![Bytes::from(vec![0xc0]); 255];
+    let encoded = marshal_proof(&proof, &[]).expect("255 nodes");
+    assert_eq!(encoded[0], 255);
+    assert_eq!(encoded.len(), 255 + 2);
+
+    let proof = vec![Bytes::from(vec![0xc0]); 256];
+    assert!(marshal_proof(&proof, &[]).is_err());
+    assert!(marshal_proof(&[], &proof).is_err());
+}
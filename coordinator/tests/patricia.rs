@@ -3,7 +3,6 @@ mod common;
 use crate::common::ContractArtifact;
 use coordinator::shared_state::SharedState;
 use coordinator::structs::ProofRequest;
-use coordinator::utils::marshal_proof;
 use ethers_core::abi::AbiParser;
 use ethers_core::abi::Tokenizable;
 use ethers_core::types::Bytes;
@@ -11,6 +10,7 @@ use ethers_core::types::H256;
 use ethers_core::types::U256;
 use std::fs::File;
 use std::io::BufReader;
+use zkevm_common::bridge::marshal_proof;
 
 #[derive(Debug, serde::Deserialize)]
 struct BlockHeader {
@@ -32,8 +32,8 @@ async fn patricia_validator() {
         ])
         .expect("parse abi");
 
-    let shared_state = SharedState::from_env().await;
-    shared_state.init().await;
+    let shared_state = SharedState::from_env().await.expect("shared state");
+    shared_state.init().await.expect("init");
 
     let mut cumulative_gas = 0;
     let mut samples = 0;
@@ -48,8 +48,9 @@ async fn patricia_validator() {
 
         for storage_proof in proof.storage_proof {
             let storage_key = storage_proof.key;
-            let proof_data: Bytes =
-                Bytes::from(marshal_proof(&proof.account_proof, &storage_proof.proof));
+            let proof_data: Bytes = Bytes::from(
+                marshal_proof(&proof.account_proof, &storage_proof.proof).expect("marshal_proof"),
+            );
             let calldata = abi
                 .function("testPatricia")
                 .unwrap()
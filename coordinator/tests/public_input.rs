@@ -0,0 +1,186 @@
+mod common;
+
+use crate::common::ContractArtifact;
+use coordinator::public_input::public_input_commitment;
+use coordinator::shared_state::SharedState;
+use coordinator::utils::encode_verifier_witness;
+use ethers_core::abi::AbiParser;
+use ethers_core::abi::Token;
+use ethers_core::abi::Tokenizable;
+use ethers_core::types::Block;
+use ethers_core::types::Bytes;
+use ethers_core::types::Transaction;
+use ethers_core::types::H256;
+use ethers_core::types::U256;
+use lzma::LzmaReader;
+use std::fs::File;
+
+#[derive(Debug, serde::Deserialize)]
+struct PublicInputs {
+    max_txs: U256,
+    max_calldata: U256,
+    chain_id: U256,
+    state_root_prev: U256,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct TestData {
+    block: Block<Transaction>,
+    block_hashes: Vec<H256>,
+    public_inputs: PublicInputs,
+}
+
+struct TestCase {
+    path: std::path::PathBuf,
+    witness: Bytes,
+    max_txs: usize,
+    max_calldata: usize,
+    chain_id: u64,
+    state_root_prev: H256,
+    block: Block<Transaction>,
+}
+
+fn load_test_cases() -> Vec<TestCase> {
+    std::fs::read_dir("tests/verifier/")
+        .unwrap()
+        .map(|entry| {
+            let path = entry.expect("path").path();
+            let file = File::open(&path).expect("file");
+            let reader = LzmaReader::new_decompressor(file).unwrap();
+            let test_data: TestData = serde_json::from_reader(reader).expect("json");
+            let chain_id = test_data.public_inputs.chain_id.as_u64();
+            let witness = encode_verifier_witness(
+                &test_data.block,
+                test_data.block_hashes.as_slice(),
+                &chain_id,
+            )
+            .expect("encode_verifier_witness")
+            .into();
+            let mut state_root_prev = H256::zero();
+            test_data
+                .public_inputs
+                .state_root_prev
+                .to_big_endian(state_root_prev.as_bytes_mut());
+
+            TestCase {
+                path,
+                witness,
+                max_txs: test_data.public_inputs.max_txs.as_usize(),
+                max_calldata: test_data.public_inputs.max_calldata.as_usize(),
+                chain_id,
+                state_root_prev,
+                block: test_data.block,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn public_input_commitment_table() {
+    for test in load_test_cases() {
+        let table = public_input_commitment(
+            test.max_txs,
+            test.max_calldata,
+            test.chain_id,
+            &test.state_root_prev,
+            &test.witness,
+        )
+        .expect("public_input_commitment");
+
+        assert_eq!(table.len(), 5, "{:?}", test.path);
+        assert_eq!(table[2], U256::from(test.chain_id), "{:?}", test.path);
+        // the randomness of the rlc is zero, therefore only the first byte remains
+        assert_eq!(
+            table[3],
+            U256::from(test.block.state_root[0]),
+            "{:?}",
+            test.path
+        );
+        assert_eq!(
+            table[4],
+            U256::from(test.state_root_prev[0]),
+            "{:?}",
+            test.path
+        );
+
+        // the witness must fit into the circuit
+        let calldata: usize = test
+            .block
+            .transactions
+            .iter()
+            .map(|tx| tx.input.len())
+            .sum();
+        if !test.block.transactions.is_empty() {
+            let res = public_input_commitment(
+                test.block.transactions.len() - 1,
+                test.max_calldata,
+                test.chain_id,
+                &test.state_root_prev,
+                &test.witness,
+            );
+            assert!(res.is_err(), "{:?}", test.path);
+        }
+        if calldata > 0 {
+            let res = public_input_commitment(
+                test.max_txs,
+                calldata - 1,
+                test.chain_id,
+                &test.state_root_prev,
+                &test.witness,
+            );
+            assert!(res.is_err(), "{:?}", test.path);
+        }
+    }
+}
+
+#[tokio::test]
+async fn public_input_commitment_contract() {
+    let abi = AbiParser::default()
+        .parse(&[
+               "function testPublicInputCommitment(uint256 MAX_TXS, uint256 MAX_CALLDATA, uint256 chainId, uint256 parentStateRoot, bytes calldata witness) returns (uint256[])",
+        ])
+        .expect("parse abi");
+    let shared_state = SharedState::from_env().await.expect("shared state");
+    shared_state.init().await.expect("init");
+
+    for test in load_test_cases() {
+        let calldata = abi
+            .function("testPublicInputCommitment")
+            .unwrap()
+            .encode_input(&[
+                U256::from(test.max_txs).into_token(),
+                U256::from(test.max_calldata).into_token(),
+                U256::from(test.chain_id).into_token(),
+                U256::from(test.state_root_prev.as_bytes()).into_token(),
+                test.witness.clone().into_token(),
+            ])
+            .expect("calldata");
+        let trace = ContractArtifact::load("ZkEvmTest")
+            .l1_trace(&Bytes::from(calldata), &shared_state)
+            .await
+            .unwrap();
+        let mut result = abi
+            .function("testPublicInputCommitment")
+            .unwrap()
+            .decode_output(trace.return_value.as_ref())
+            .expect("decode output");
+        let expected: Vec<U256> = result
+            .pop()
+            .unwrap()
+            .into_array()
+            .unwrap()
+            .into_iter()
+            .map(|e: Token| e.into_uint().unwrap())
+            .collect();
+
+        let table = public_input_commitment(
+            test.max_txs,
+            test.max_calldata,
+            test.chain_id,
+            &test.state_root_prev,
+            &test.witness,
+        )
+        .expect("public_input_commitment");
+        assert_eq!(table, expected, "{:?}", test.path);
+    }
+}
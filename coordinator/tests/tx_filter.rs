@@ -0,0 +1,57 @@
+use coordinator::tx_filter::TransactionFilter;
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::{Address, Bytes, Transaction, TransactionRequest};
+use ethers_signers::{LocalWallet, Signer};
+
+const DELIVERER: Address = Address::repeat_byte(0x10);
+
+fn signed_tx(wallet: &LocalWallet, to: Address) -> Bytes {
+    let tx: TypedTransaction = TransactionRequest::new()
+        .to(to)
+        .nonce(0)
+        .gas(100_000)
+        .gas_price(1)
+        .chain_id(wallet.chain_id())
+        .into();
+    let sig = wallet.sign_transaction_sync(&tx);
+    tx.rlp_signed(&sig)
+}
+
+fn filter(coordinator: &LocalWallet) -> TransactionFilter {
+    TransactionFilter {
+        restricted_addr: DELIVERER,
+        coordinator_addr: coordinator.address(),
+    }
+}
+
+#[test]
+fn tx_filter_is_allowed() {
+    let coordinator = LocalWallet::new(&mut rand::thread_rng());
+    let filter = filter(&coordinator);
+    let tx = |from, to| Transaction {
+        from,
+        to: Some(to),
+        ..Default::default()
+    };
+
+    assert!(filter.is_allowed(&tx(Address::zero(), Address::zero())));
+    assert!(filter.is_allowed(&tx(coordinator.address(), DELIVERER)));
+    assert!(!filter.is_allowed(&tx(Address::zero(), DELIVERER)));
+    // contract creations
+    assert!(filter.is_allowed(&Transaction::default()));
+}
+
+#[test]
+fn tx_filter_raw_transaction() {
+    let coordinator = LocalWallet::new(&mut rand::thread_rng()).with_chain_id(99u64);
+    let user = LocalWallet::new(&mut rand::thread_rng()).with_chain_id(99u64);
+    let filter = filter(&coordinator);
+
+    let raw = signed_tx(&user, Address::zero());
+    assert_eq!(filter.check_raw_transaction(&raw), Ok(()));
+    let raw = signed_tx(&coordinator, DELIVERER);
+    assert_eq!(filter.check_raw_transaction(&raw), Ok(()));
+    let raw = signed_tx(&user, DELIVERER);
+    assert!(filter.check_raw_transaction(&raw).is_err());
+    assert!(filter.check_raw_transaction(&[0xff, 0x01]).is_err());
+}
@@ -6,13 +6,17 @@ use coordinator::utils::*;
 use ethers_core::abi::AbiParser;
 use ethers_core::abi::Token;
 use ethers_core::abi::Tokenizable;
+use ethers_core::types::Address;
 use ethers_core::types::Block;
 use ethers_core::types::Bytes;
 use ethers_core::types::Transaction;
 use ethers_core::types::H256;
 use ethers_core::types::U256;
+use ethers_core::utils::keccak256;
 use lzma::LzmaReader;
 use std::fs::File;
+use zkevm_common::prover::ProofResult;
+use zkevm_common::prover::AGGREGATION_ACCUMULATOR_LEN;
 
 const COLUMNS: [&str; 12] = [
     "q_block_table",
@@ -56,8 +60,8 @@ async fn witness_verifier() {
                "function testPublicInput(uint256 MAX_TXS, uint256 MAX_CALLDATA, uint256 chainId, uint256 parentStateRoot, bytes calldata witness) external returns (uint256[])",
         ])
         .expect("parse abi");
-    let shared_state = SharedState::from_env().await;
-    shared_state.init().await;
+    let shared_state = SharedState::from_env().await.expect("shared state");
+    shared_state.init().await.expect("init");
 
     for entry in std::fs::read_dir("tests/verifier/").unwrap() {
         let path = entry.expect("path").path();
@@ -134,3 +138,85 @@ async fn witness_verifier() {
         assert!(success);
     }
 }
+
+#[tokio::test]
+async fn finalize_block_verifier_label() {
+    let abi = AbiParser::default()
+        .parse(&[
+            "function testFinalizeBlock(bytes32 commitmentHash, bytes calldata proof) external",
+        ])
+        .expect("parse abi");
+    let shared_state = SharedState::from_env().await.expect("shared state");
+    shared_state.init().await.expect("init");
+
+    let block_hash = H256::from_low_u64_be(1);
+    let commitment: Vec<U256> = (1..=5).map(U256::from).collect();
+    let commitment_hash = {
+        let mut buf = vec![0u8; 32 * commitment.len()];
+        for (i, v) in commitment.iter().enumerate() {
+            v.to_big_endian(&mut buf[i * 32..(i + 1) * 32]);
+        }
+        H256::from(keccak256(buf))
+    };
+    let accumulator: Vec<U256> = (100..100 + AGGREGATION_ACCUMULATOR_LEN as u64)
+        .map(U256::from)
+        .collect();
+
+    let finalize = |label: &str, instance: Vec<U256>| {
+        let proof = ProofResult {
+            label: label.to_string(),
+            instance,
+            proof: vec![0xff; 64].into(),
+            ..Default::default()
+        };
+        let calldata = abi
+            .function("testFinalizeBlock")
+            .unwrap()
+            .encode_input(&[
+                commitment_hash.into_token(),
+                encode_finalize_proof(&block_hash, &proof).into_token(),
+            ])
+            .expect("calldata");
+        // a verifier that accepts any proof
+        let mut verifier = [0u8; 20];
+        verifier[20 - label.len()..].copy_from_slice(label.as_bytes());
+        let code = [(Address::from(verifier), Bytes::from(vec![0x00]))];
+        let shared_state = &shared_state;
+
+        async move {
+            ContractArtifact::load("ZkEvmL1BridgeTest")
+                .l1_trace_with_code(&Bytes::from(calldata), shared_state, &code)
+                .await
+        }
+    };
+
+    // the commitment is the instance of circuit proofs
+    finalize("super-63000", commitment.clone())
+        .await
+        .expect("circuit proof");
+    // and follows the accumulator for aggregation proofs with the exposed instance
+    finalize(
+        "super-63000-ai",
+        [accumulator.clone(), commitment.clone()].concat(),
+    )
+    .await
+    .expect("aggregation proof");
+    assert!(finalize("super-63000-ai", commitment.clone())
+        .await
+        .is_err());
+    // the commitment is not part of other aggregation proofs
+    assert!(finalize("super-63000-a", accumulator.clone())
+        .await
+        .is_err());
+    // aggregation proofs of several blocks are rejected, whatever their instance
+    assert!(finalize(
+        "super-63000-a2i",
+        [accumulator.clone(), commitment.clone()].concat(),
+    )
+    .await
+    .is_err());
+    assert!(finalize("super-63000-a2i", commitment.clone())
+        .await
+        .is_err());
+    assert!(finalize("super-63000-a2", accumulator).await.is_err());
+}
@@ -0,0 +1,313 @@
+use coordinator::public_input::public_input_commitment;
+use coordinator::utils::encode_verifier_witness;
+use coordinator::witness::decode_verifier_witness;
+use coordinator::witness::witness_header;
+use coordinator::witness::WitnessVersion;
+use coordinator::witness::HISTORY_HASHES;
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::transaction::eip2930::AccessList;
+use ethers_core::types::transaction::eip2930::AccessListItem;
+use ethers_core::types::transaction::eip2930::Eip2930TransactionRequest;
+use ethers_core::types::Address;
+use ethers_core::types::Block;
+use ethers_core::types::Eip1559TransactionRequest;
+use ethers_core::types::Transaction;
+use ethers_core::types::TransactionRequest;
+use ethers_core::types::H256;
+use ethers_core::types::U256;
+use ethers_core::types::U64;
+use ethers_core::utils::keccak256;
+use ethers_signers::LocalWallet;
+use ethers_signers::Signer;
+use lzma::LzmaReader;
+use std::fs::File;
+
+#[derive(Debug, serde::Deserialize)]
+struct PublicInputs {
+    chain_id: U256,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct TestData {
+    block: Block<Transaction>,
+    block_hashes: Vec<H256>,
+    public_inputs: PublicInputs,
+}
+
+fn load_test_data() -> Vec<(std::path::PathBuf, TestData)> {
+    std::fs::read_dir("tests/verifier/")
+        .unwrap()
+        .map(|entry| {
+            let path = entry.expect("path").path();
+            let file = File::open(&path).expect("file");
+            let reader = LzmaReader::new_decompressor(file).unwrap();
+            let test_data: TestData = serde_json::from_reader(reader).expect("json");
+            (path, test_data)
+        })
+        .collect()
+}
+
+fn signed_tx(wallet: &LocalWallet, chain_id: u64, nonce: u64, to: Option<Address>) -> Transaction {
+    let mut req = TransactionRequest::new()
+        .nonce(nonce)
+        .gas(21000)
+        .gas_price(1_000_000_000u64)
+        .value(nonce + 1)
+        .data(vec![0xde, 0xad, nonce as u8])
+        .chain_id(chain_id);
+    if let Some(to) = to {
+        req = req.to(to);
+    }
+    let sig = wallet.sign_transaction_sync(&TypedTransaction::Legacy(req.clone()));
+
+    Transaction {
+        nonce: req.nonce.unwrap(),
+        gas_price: req.gas_price,
+        gas: req.gas.unwrap(),
+        to: req.to.and_then(|to| to.as_address().copied()),
+        value: req.value.unwrap(),
+        input: req.data.unwrap(),
+        chain_id: Some(chain_id.into()),
+        from: wallet.address(),
+        v: sig.v.into(),
+        r: sig.r,
+        s: sig.s,
+        ..Default::default()
+    }
+}
+
+fn signed_typed_tx(wallet: &LocalWallet, chain_id: u64, nonce: u64, base_fee: U256) -> Transaction {
+    let access_list = AccessList(vec![AccessListItem {
+        address: Address::repeat_byte(0x33),
+        storage_keys: vec![H256::repeat_byte(nonce as u8)],
+    }]);
+    let to = Address::repeat_byte(0x11);
+    let mut tx = Transaction {
+        nonce: nonce.into(),
+        gas: 50000.into(),
+        to: Some(to),
+        value: (nonce + 1).into(),
+        input: vec![0xbe, 0xef, nonce as u8].into(),
+        chain_id: Some(chain_id.into()),
+        from: wallet.address(),
+        access_list: Some(access_list.clone()),
+        ..Default::default()
+    };
+    let typed = if nonce % 2 == 0 {
+        tx.transaction_type = Some(2.into());
+        tx.max_priority_fee_per_gas = Some(U256::from(nonce + 1));
+        tx.max_fee_per_gas = Some(U256::from(1_000_000_000u64));
+        tx.gas_price = Some(std::cmp::min(
+            tx.max_fee_per_gas.unwrap(),
+            base_fee + tx.max_priority_fee_per_gas.unwrap(),
+        ));
+        TypedTransaction::Eip1559(
+            Eip1559TransactionRequest::new()
+                .nonce(nonce)
+                .gas(tx.gas)
+                .to(to)
+                .value(tx.value)
+                .data(tx.input.clone())
+                .chain_id(chain_id)
+                .access_list(access_list)
+                .max_priority_fee_per_gas(tx.max_priority_fee_per_gas.unwrap())
+                .max_fee_per_gas(tx.max_fee_per_gas.unwrap()),
+        )
+    } else {
+        tx.transaction_type = Some(1.into());
+        tx.gas_price = Some(U256::from(1_000_000_000u64));
+        TypedTransaction::Eip2930(Eip2930TransactionRequest::new(
+            TransactionRequest::new()
+                .nonce(nonce)
+                .gas(tx.gas)
+                .gas_price(tx.gas_price.unwrap())
+                .to(to)
+                .value(tx.value)
+                .data(tx.input.clone())
+                .chain_id(chain_id),
+            access_list,
+        ))
+    };
+    let sig = wallet.sign_transaction_sync(&typed);
+    // y parity
+    tx.v = (sig.v - (chain_id * 2 + 35)).into();
+    tx.r = sig.r;
+    tx.s = sig.s;
+
+    tx
+}
+
+#[test]
+fn witness_round_trip() {
+    for (path, test_data) in load_test_data() {
+        let chain_id = test_data.public_inputs.chain_id.as_u64();
+        let block = test_data.block;
+        let witness = encode_verifier_witness(&block, &test_data.block_hashes, &chain_id)
+            .expect("encode_verifier_witness");
+        let decoded = decode_verifier_witness(&witness).expect("decode_verifier_witness");
+
+        assert_eq!(decoded.history_hashes, test_data.block_hashes, "{:?}", path);
+        let header = &decoded.header;
+        assert_eq!(header.parent_hash, block.parent_hash, "{:?}", path);
+        assert_eq!(header.uncles_hash, block.uncles_hash, "{:?}", path);
+        assert_eq!(Some(header.author), block.author, "{:?}", path);
+        assert_eq!(header.state_root, block.state_root, "{:?}", path);
+        assert_eq!(
+            header.transactions_root, block.transactions_root,
+            "{:?}",
+            path
+        );
+        assert_eq!(header.receipts_root, block.receipts_root, "{:?}", path);
+        assert_eq!(Some(header.logs_bloom), block.logs_bloom, "{:?}", path);
+        assert_eq!(header.difficulty, block.difficulty, "{:?}", path);
+        assert_eq!(Some(header.number), block.number, "{:?}", path);
+        assert_eq!(header.gas_limit, block.gas_limit, "{:?}", path);
+        assert_eq!(header.gas_used, block.gas_used, "{:?}", path);
+        assert_eq!(header.timestamp, block.timestamp, "{:?}", path);
+        assert_eq!(header.extra_data, block.extra_data, "{:?}", path);
+        assert_eq!(Some(header.mix_hash), block.mix_hash, "{:?}", path);
+        assert_eq!(Some(header.nonce), block.nonce, "{:?}", path);
+
+        assert_eq!(
+            decoded.transactions.len(),
+            block.transactions.len(),
+            "{:?}",
+            path
+        );
+        for (tx, expected) in decoded.transactions.iter().zip(block.transactions.iter()) {
+            assert_eq!(tx.nonce, expected.nonce, "{:?}", path);
+            assert_eq!(Some(tx.gas_price), expected.gas_price, "{:?}", path);
+            assert_eq!(tx.gas, expected.gas, "{:?}", path);
+            assert_eq!(tx.to, expected.to, "{:?}", path);
+            assert_eq!(tx.value, expected.value, "{:?}", path);
+            assert_eq!(tx.input, expected.input, "{:?}", path);
+            assert_eq!(tx.chain_id, chain_id, "{:?}", path);
+            assert_eq!(tx.from, expected.from, "{:?}", path);
+            assert_eq!(tx.r, expected.r, "{:?}", path);
+            assert_eq!(tx.s, expected.s, "{:?}", path);
+        }
+
+        // encode(decode(witness)) == witness
+        let block = decoded.to_block();
+        let encoded = encode_verifier_witness(&block, &decoded.history_hashes, &chain_id)
+            .expect("encode_verifier_witness");
+        assert_eq!(encoded, witness, "{:?}", path);
+        assert_eq!(
+            decode_verifier_witness(&encoded).expect("decode_verifier_witness"),
+            decoded,
+            "{:?}",
+            path
+        );
+
+        // truncated data
+        assert!(
+            decode_verifier_witness(&witness[..witness.len() - 1]).is_err(),
+            "{:?}",
+            path
+        );
+    }
+}
+
+#[test]
+fn witness_signatures() {
+    let (_, test_data) = load_test_data().pop().expect("test data");
+    let chain_id = 99;
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+    let mut block = test_data.block;
+    block.transactions = (0..8)
+        .map(|nonce| {
+            let to = Some(Address::repeat_byte(0x11)).filter(|_| nonce % 2 == 0);
+            signed_tx(&wallet, chain_id, nonce, to)
+        })
+        .collect();
+
+    let witness = encode_verifier_witness(&block, &vec![H256::zero(); HISTORY_HASHES], &chain_id)
+        .expect("encode_verifier_witness");
+    let decoded = decode_verifier_witness(&witness).expect("decode_verifier_witness");
+    decoded.validate(chain_id).expect("validate");
+    for (tx, expected) in decoded.transactions.iter().zip(block.transactions.iter()) {
+        assert_eq!(tx.recover_v().expect("recover_v"), expected.v.as_u64());
+    }
+    assert!(decoded.validate(chain_id + 1).is_err());
+
+    // wrong sender
+    let mut tampered = decoded.clone();
+    tampered.transactions[3].from = Address::repeat_byte(0x22);
+    assert!(tampered.validate(chain_id).is_err());
+
+    // signed for another chain
+    let witness =
+        encode_verifier_witness(&block, &vec![H256::zero(); HISTORY_HASHES], &(chain_id + 1))
+            .expect("encode_verifier_witness");
+    let decoded = decode_verifier_witness(&witness).expect("decode_verifier_witness");
+    assert!(decoded.validate(chain_id + 1).is_err());
+}
+
+#[test]
+fn witness_typed_transactions() {
+    let (_, test_data) = load_test_data().pop().expect("test data");
+    let chain_id = 99;
+    let base_fee = U256::from(7);
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+    let mut block = test_data.block;
+    block.base_fee_per_gas = Some(base_fee);
+    block.transactions = (0..8)
+        .map(|nonce| match nonce % 3 {
+            0 => signed_tx(&wallet, chain_id, nonce, None),
+            _ => signed_typed_tx(&wallet, chain_id, nonce, base_fee),
+        })
+        .collect();
+    let history_hashes = vec![H256::zero(); HISTORY_HASHES];
+
+    assert_eq!(WitnessVersion::for_block(&block), WitnessVersion::V1);
+    assert!(WitnessVersion::V0
+        .encode(&block, &history_hashes, &chain_id)
+        .is_err());
+
+    let witness = encode_verifier_witness(&block, &history_hashes, &chain_id)
+        .expect("encode_verifier_witness");
+    let decoded = decode_verifier_witness(&witness).expect("decode_verifier_witness");
+    assert_eq!(decoded.version, WitnessVersion::V1);
+    assert_eq!(decoded.header.base_fee_per_gas, Some(base_fee));
+    assert_eq!(
+        H256::from(keccak256(witness_header(&witness).expect("witness_header"))),
+        decoded.header.hash
+    );
+    decoded.validate(chain_id).expect("validate");
+    for (tx, expected) in decoded.transactions.iter().zip(block.transactions.iter()) {
+        assert_eq!(
+            U64::from(tx.tx_type),
+            expected.transaction_type.unwrap_or_default()
+        );
+        assert_eq!(Some(tx.gas_price), expected.gas_price);
+        assert_eq!(tx.max_fee_per_gas, expected.max_fee_per_gas);
+        assert_eq!(
+            tx.access_list,
+            expected.access_list.clone().unwrap_or_default()
+        );
+        assert_eq!(tx.v, Some(expected.v.as_u64()));
+        assert_eq!(tx.recover_v().expect("recover_v"), expected.v.as_u64());
+    }
+
+    // encode(decode(witness)) == witness
+    let encoded = encode_verifier_witness(&decoded.to_block(), &history_hashes, &chain_id)
+        .expect("encode_verifier_witness");
+    assert_eq!(encoded, witness);
+
+    // the recovery bit is part of V1
+    let mut tampered = decoded.clone();
+    let tx = &mut tampered.transactions[1];
+    tx.v = Some(tx.v.unwrap() ^ 1);
+    assert!(tampered.validate(chain_id).is_err());
+
+    let calldata: usize = block.transactions.iter().map(|tx| tx.input.len()).sum();
+    let table = public_input_commitment(
+        block.transactions.len(),
+        calldata,
+        chain_id,
+        &H256::zero(),
+        &witness,
+    )
+    .expect("public_input_commitment");
+    assert_eq!(table[2], U256::from(chain_id));
+}
@@ -10,8 +10,8 @@ use plonk_verifier::loader::halo2::halo2_wrong_ecc::{
     self,
     integer::rns::Rns,
     maingate::{
-        MainGate, MainGateConfig, MainGateInstructions, RangeChip, RangeConfig, RangeInstructions,
-        RegionCtx,
+        AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RangeChip, RangeConfig,
+        RangeInstructions, RegionCtx,
     },
     EccConfig,
 };
@@ -49,6 +49,7 @@ type Halo2Loader<'a> = loader::halo2::Halo2Loader<'a, G1Affine, BaseFieldEccChip
 pub type PoseidonTranscript<L, S> =
     system::halo2::transcript::halo2::PoseidonTranscript<G1Affine, L, S, T, RATE, R_F, R_P>;
 
+#[derive(Clone)]
 pub struct Snark {
     pub protocol: Protocol<G1Affine>,
     pub instances: Vec<Vec<Fr>>,
@@ -104,12 +105,16 @@ impl SnarkWitness {
     }
 }
 
+/// Returns the accumulator and the assigned instances of all `snarks`.
 pub fn aggregate<'a>(
     svk: &Svk,
     loader: &Rc<Halo2Loader<'a>>,
     snarks: &[SnarkWitness],
     as_proof: Value<&'_ [u8]>,
-) -> KzgAccumulator<G1Affine, Rc<Halo2Loader<'a>>> {
+) -> (
+    KzgAccumulator<G1Affine, Rc<Halo2Loader<'a>>>,
+    Vec<AssignedValue<Fr>>,
+) {
     let assign_instances = |instances: &[Vec<Value<Fr>>]| {
         instances
             .iter()
@@ -122,11 +127,18 @@ pub fn aggregate<'a>(
             .collect_vec()
     };
 
+    let mut assigned_instances = Vec::new();
     let accumulators = snarks
         .iter()
         .flat_map(|snark| {
             let protocol = snark.protocol.loaded(loader);
             let instances = assign_instances(&snark.instances);
+            assigned_instances.extend(
+                instances
+                    .iter()
+                    .flatten()
+                    .map(|instance| instance.assigned()),
+            );
             let mut transcript =
                 PoseidonTranscript::<Rc<Halo2Loader>, _>::new(loader, snark.proof());
             let proof = Plonk::read_proof(svk, &protocol, &instances, &mut transcript).unwrap();
@@ -140,7 +152,7 @@ pub fn aggregate<'a>(
         As::verify(&Default::default(), &accumulators, &proof).unwrap()
     };
 
-    acccumulator
+    (acccumulator, assigned_instances)
 }
 
 #[derive(Clone)]
@@ -185,6 +197,8 @@ pub struct AggregationCircuit {
     svk: Svk,
     snarks: Vec<SnarkWitness>,
     instances: Vec<Fr>,
+    snark_instances: Vec<Fr>,
+    expose_instances: bool,
     as_proof: Value<Vec<u8>>,
 }
 
@@ -196,6 +210,10 @@ impl AggregationCircuit {
     ) -> Self {
         let svk = params.get_g()[0].into();
         let snarks = snarks.into_iter().collect_vec();
+        let snark_instances = snarks
+            .iter()
+            .flat_map(|snark| snark.instances.iter().flatten().copied())
+            .collect_vec();
 
         let accumulators = snarks
             .iter()
@@ -230,20 +248,40 @@ impl AggregationCircuit {
             svk,
             snarks: snarks.into_iter().map_into().collect(),
             instances,
+            snark_instances,
+            expose_instances: false,
             as_proof: Value::known(as_proof),
         }
     }
 
+    /// If true, the instances of the snarks are exposed as public inputs
+    /// after the accumulator limbs, in the order of the snarks.
+    pub fn with_exposed_instances(mut self, expose_instances: bool) -> Self {
+        self.expose_instances = expose_instances;
+        self
+    }
+
     pub fn accumulator_indices() -> Vec<(usize, usize)> {
         (0..4 * LIMBS).map(|idx| (0, idx)).collect()
     }
 
-    pub fn num_instance() -> Vec<usize> {
-        vec![4 * LIMBS]
+    pub fn num_instance(&self) -> Vec<usize> {
+        let num_snark_instances = match self.expose_instances {
+            true => self
+                .snarks
+                .iter()
+                .flat_map(|snark| snark.instances.iter().map(Vec::len))
+                .sum(),
+            false => 0,
+        };
+        vec![4 * LIMBS + num_snark_instances]
     }
 
     pub fn instance(&self) -> Vec<Vec<Fr>> {
-        vec![self.instances.clone()]
+        match self.expose_instances {
+            true => vec![[self.instances.as_slice(), &self.snark_instances].concat()],
+            false => vec![self.instances.clone()],
+        }
     }
 
     pub fn as_proof(&self) -> Value<&[u8]> {
@@ -264,6 +302,8 @@ impl Circuit<Fr> for AggregationCircuit {
                 .map(SnarkWitness::without_witnesses)
                 .collect(),
             instances: Vec::new(),
+            snark_instances: Vec::new(),
+            expose_instances: self.expose_instances,
             as_proof: Value::unknown(),
         }
     }
@@ -286,14 +326,15 @@ impl Circuit<Fr> for AggregationCircuit {
 
         range_chip.load_table(&mut layouter)?;
 
-        let accumulator_limbs = layouter.assign_region(
+        let (accumulator_limbs, instances) = layouter.assign_region(
             || "",
             |region| {
                 let ctx = RegionCtx::new(region, 0);
 
                 let ecc_chip = config.ecc_chip();
                 let loader = Halo2Loader::new(ecc_chip, ctx);
-                let accumulator = aggregate(&self.svk, &loader, &self.snarks, self.as_proof());
+                let (accumulator, instances) =
+                    aggregate(&self.svk, &loader, &self.snarks, self.as_proof());
 
                 let accumulator_limbs = [accumulator.lhs, accumulator.rhs]
                     .iter()
@@ -306,12 +347,16 @@ impl Circuit<Fr> for AggregationCircuit {
                     .into_iter()
                     .flatten();
 
-                Ok(accumulator_limbs)
+                Ok((accumulator_limbs, instances))
             },
         )?;
 
-        for (row, limb) in accumulator_limbs.enumerate() {
-            main_gate.expose_public(layouter.namespace(|| ""), limb, row)?;
+        let instances = match self.expose_instances {
+            true => instances,
+            false => Vec::new(),
+        };
+        for (row, cell) in accumulator_limbs.chain(instances).enumerate() {
+            main_gate.expose_public(layouter.namespace(|| ""), cell, row)?;
         }
 
         Ok(())
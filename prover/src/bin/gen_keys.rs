@@ -0,0 +1,63 @@
+use clap::Parser;
+use env_logger::Env;
+use prover::key_store::KeyStore;
+use prover::shared_state::SharedState;
+use std::sync::Arc;
+use zkevm_common::circuit_autogen::CIRCUIT_CONFIGS;
+use zkevm_common::prover::*;
+
+#[derive(Parser, Debug)]
+#[clap(version, about)]
+/// Pregenerates the verifying keys used by `prover_rpcd` into a key store directory.
+/// Keys for aggregation circuits are generated on first use.
+/// This saves `keygen_vk` only, `prover_rpcd` still derives the proving keys with `keygen_pk`
+/// on first use, see `KeyStore`.
+pub(crate) struct GenKeysConfig {
+    #[clap(long, env = "PROVERD_KEY_STORE")]
+    /// The directory to write the keys to.
+    key_store: String,
+    #[clap(long, default_value = "super")]
+    /// The name of the circuit, `super`, `pi` or `dummy`.
+    circuit: String,
+    #[clap(long)]
+    /// Parameters file or directory, same as `ProofRequestOptions::param`.
+    param: Option<String>,
+    #[clap(long)]
+    /// Only generates the key for the circuit parameters that cover this amount of gas.
+    /// Otherwise generates keys for all circuit parameters.
+    gas: Option<u64>,
+    #[clap(long, default_value_t = false)]
+    /// Generates insecure circuit parameters on the fly if `param` is not given.
+    allow_setup: bool,
+}
+
+#[tokio::main]
+async fn main() {
+    let config = GenKeysConfig::parse();
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let key_store = KeyStore::open(&config.key_store).expect("key store");
+    let shared_state = SharedState::new(String::new(), None)
+        .with_key_store(Arc::new(key_store))
+        .with_params_setup(config.allow_setup);
+    let options = ProofRequestOptions {
+        circuit: config.circuit,
+        param: config.param,
+        ..Default::default()
+    };
+    let gas_limits: Vec<u64> = match config.gas {
+        Some(gas) => vec![gas],
+        None => CIRCUIT_CONFIGS
+            .iter()
+            .map(|e| e.block_gas_limit as u64)
+            .collect(),
+    };
+
+    for gas in gas_limits {
+        let cache_key = shared_state
+            .pregen_key(&options, gas)
+            .await
+            .expect("pregen_key");
+        println!("{}", cache_key);
+    }
+}
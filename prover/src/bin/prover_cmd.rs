@@ -1,6 +1,12 @@
 use env_logger::Env;
+use prover::key_store::KeyStore;
 use prover::shared_state::SharedState;
+use prover::worker::WORKER_ARG;
 use std::env::var;
+use std::sync::Arc;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
 use zkevm_common::prover::*;
 
 /// This command generates and prints the proofs to stdout.
@@ -8,10 +14,21 @@ use zkevm_common::prover::*;
 /// - PROVERD_BLOCK_NUM - the block number to generate the proof for
 /// - PROVERD_RPC_URL - a geth http rpc that supports the debug namespace
 /// - PROVERD_PARAMS_PATH - a path to a file generated with the gen_params tool
+///
+/// With `--worker`, reads `ProofRequestOptions` line by line from stdin instead and prints
+/// the result or error of each request as a line to stdout until stdin is closed,
+/// see `prover::worker::Worker`.
+/// Optional environment variables in this mode:
+/// - PROVERD_KEY_STORE - a key store directory, see `prover_rpcd`
+/// - PROVERD_ALLOW_SETUP - `true` to generate insecure circuit parameters on the fly
 #[tokio::main]
 async fn main() {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
+    if std::env::args().any(|arg| arg == WORKER_ARG) {
+        return worker().await;
+    }
+
     let block_num: u64 = var("PROVERD_BLOCK_NUM")
         .expect("PROVERD_BLOCK_NUM env var")
         .parse()
@@ -47,3 +64,25 @@ async fn main() {
 
     serde_json::to_writer(std::io::stdout(), &result).expect("serialize and write");
 }
+
+async fn worker() {
+    let mut state = SharedState::new(String::new(), None)
+        .with_params_setup(var("PROVERD_ALLOW_SETUP").map_or(false, |e| e == "true"));
+    if let Ok(path) = var("PROVERD_KEY_STORE") {
+        let key_store = KeyStore::open(path).expect("key store");
+        state = state.with_key_store(Arc::new(key_store));
+    }
+
+    // the same state is used for all requests to keep the caches
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    while let Some(line) = lines.next_line().await.expect("read stdin") {
+        let request: ProofRequestOptions =
+            serde_json::from_str(&line).expect("ProofRequestOptions");
+        let result = state.compute_proof(&request).await;
+        let mut output = serde_json::to_vec(&result).expect("serialize");
+        output.push(b'\n');
+        stdout.write_all(&output).await.expect("write stdout");
+        stdout.flush().await.expect("flush stdout");
+    }
+}
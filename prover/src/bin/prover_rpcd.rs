@@ -1,8 +1,14 @@
 use clap::Parser;
 use env_logger::Env;
 
+use prover::key_store::KeyStore;
 use prover::server::serve;
 use prover::shared_state::SharedState;
+use prover::shared_state::DEFAULT_LEASE_TTL;
+use prover::task_store::DirTaskStore;
+use prover::task_store::TaskRetention;
+use prover::worker::Worker;
+use std::sync::Arc;
 
 #[derive(Parser, Debug)]
 #[clap(version, about)]
@@ -14,7 +20,57 @@ pub(crate) struct ProverdConfig {
     bind: String,
     #[clap(long, env = "PROVERD_LOOKUP")]
     /// A `HOSTNAME:PORT` conformant string that will be used for DNS service discovery of other nodes.
-    lookup: String,
+    lookup: Option<String>,
+    #[clap(long, env = "PROVERD_PEERS", value_delimiter = ',')]
+    /// Comma separated `HOST:PORT` addresses of other nodes.
+    peers: Vec<String>,
+    #[clap(long, env = "PROVERD_ADVERTISE")]
+    /// The `HOST:PORT` address other nodes can reach this node at.
+    /// If set, this node announces itself to the nodes from `lookup` and `peers`.
+    advertise: Option<String>,
+    #[clap(long, env = "PROVERD_PEER_SECRET")]
+    /// The secret shared by all nodes to authenticate peer messages.
+    /// Without it, task information from other nodes is rejected.
+    peer_secret: Option<String>,
+    #[clap(long, env = "PROVERD_AUTH_TOKEN")]
+    /// If set, json-rpc requests must contain a `authorization: Bearer <token>` header.
+    /// Also used for requests to other nodes.
+    auth_token: Option<String>,
+    #[clap(long, env = "PROVERD_TASK_STORE")]
+    /// If set, tasks and results are persisted inside this directory
+    /// and loaded again on startup.
+    task_store: Option<String>,
+    #[clap(long, env = "PROVERD_TASK_RETENTION")]
+    /// Removes completed tasks after this amount of seconds.
+    task_retention: Option<u64>,
+    #[clap(long, env = "PROVERD_TASK_RETENTION_COUNT")]
+    /// Keeps at most this amount of completed tasks.
+    task_retention_count: Option<usize>,
+    #[clap(long, env = "PROVERD_KEY_STORE")]
+    /// If set, verifying keys are persisted inside this directory and reused on restarts.
+    /// Proving keys are still derived on first use, see `KeyStore`.
+    /// See the `gen_keys` command to pregenerate them.
+    key_store: Option<String>,
+    #[clap(long, env = "PROVERD_ALLOW_SETUP", default_value_t = false)]
+    /// Generates insecure circuit parameters on the fly for requests without a params file.
+    /// Only meant for development.
+    allow_setup: bool,
+    #[clap(long, env = "PROVERD_WORKER")]
+    /// If set, proofs are computed by this `prover_cmd` binary inside a child process.
+    /// A crash of the child process is recorded as an error of the task.
+    /// Required to interrupt the computation of cancelled or obsolete tasks.
+    worker: Option<String>,
+    #[clap(long, env = "PROVERD_WORKER_MEMORY_LIMIT")]
+    /// Limits the virtual memory of the worker process to this amount of megabytes.
+    worker_memory_limit: Option<u64>,
+    #[clap(long, env = "PROVERD_LEASE_TTL", default_value_t = DEFAULT_LEASE_TTL)]
+    /// Seconds until other nodes take over a task from an unresponsive node.
+    lease_ttl: u64,
+    #[clap(long, env = "PROVERD_VERIFY_RESULTS", default_value_t = true, action = clap::ArgAction::Set)]
+    /// Verifies proofs of other nodes before accepting them.
+    /// Requires access to the block rpc and the circuit parameters of the task,
+    /// results are not accepted while they can not be verified.
+    verify_results: bool,
 }
 
 #[tokio::main]
@@ -22,7 +78,42 @@ async fn main() {
     let config = ProverdConfig::parse();
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
-    let shared_state = SharedState::new(SharedState::random_worker_id(), Some(config.lookup));
+    let has_peers = config.lookup.is_some() || !config.peers.is_empty();
+    let mut shared_state = SharedState::new(SharedState::random_worker_id(), config.lookup)
+        .with_static_peers(config.peers)
+        .with_advertise_addr(config.advertise)
+        .with_auth_token(config.auth_token)
+        .with_params_setup(config.allow_setup)
+        .with_lease_ttl(config.lease_ttl)
+        .with_result_verification(config.verify_results)
+        .with_task_retention(TaskRetention {
+            max_age: config.task_retention,
+            max_completed: config.task_retention_count,
+        });
+    match config.peer_secret {
+        Some(peer_secret) => shared_state = shared_state.with_peer_secret(peer_secret),
+        None if has_peers => log::warn!("PROVERD_PEER_SECRET is not set, peers are not trusted"),
+        None => {}
+    }
+    if let Some(cmd) = config.worker {
+        let mut worker = Worker::new(cmd)
+            .with_memory_limit(config.worker_memory_limit.map(|e| e * 1024 * 1024))
+            .with_env("PROVERD_ALLOW_SETUP", &config.allow_setup.to_string());
+        if let Some(path) = &config.key_store {
+            worker = worker.with_env("PROVERD_KEY_STORE", path);
+        }
+        shared_state = shared_state.with_worker(worker);
+    }
+    if let Some(path) = config.key_store {
+        let key_store = KeyStore::open(path).expect("key store");
+        shared_state = shared_state.with_key_store(Arc::new(key_store));
+    }
+    if let Some(path) = config.task_store {
+        let task_store = DirTaskStore::open(path).expect("task store");
+        shared_state = shared_state
+            .with_task_store(Arc::new(task_store))
+            .expect("load tasks");
+    }
     {
         // start the http server
         let h1 = serve(&shared_state, &config.bind);
@@ -58,6 +149,7 @@ async fn main() {
                 // occur due to network services
                 let _ = tokio::spawn(async move {
                     log::debug!("task: merge_tasks_from_peers");
+                    let _ = ctx.announce().await;
                     let _ = ctx.merge_tasks_from_peers().await;
                 })
                 .await;
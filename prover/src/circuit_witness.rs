@@ -123,7 +123,19 @@ impl CircuitWitness {
 
     pub fn public_data(&self) -> PublicData {
         let chain_id = self.block.chain_id;
-        let eth_block = self.eth_block.clone();
+        let mut eth_block = self.eth_block.clone();
+        let base_fee = eth_block.base_fee_per_gas.unwrap_or_default();
+        // the tx table expects the effective gas price of EIP-1559 transactions,
+        // same as the verifier witness
+        for tx in eth_block.transactions.iter_mut() {
+            if let (None, Some(max_fee)) = (tx.gas_price, tx.max_fee_per_gas) {
+                let max_priority_fee = tx.max_priority_fee_per_gas.unwrap_or_default();
+                tx.gas_price = Some(std::cmp::min(
+                    max_fee,
+                    base_fee.saturating_add(max_priority_fee),
+                ));
+            }
+        }
         let history_hashes = self.block.history_hashes.clone();
         let block_constants = geth_types::BlockConstants {
             coinbase: eth_block.author.expect("coinbase"),
@@ -131,7 +143,7 @@ impl CircuitWitness {
             number: eth_block.number.expect("number"),
             difficulty: eth_block.difficulty,
             gas_limit: eth_block.gas_limit,
-            base_fee: eth_block.base_fee_per_gas.unwrap_or_default(),
+            base_fee,
         };
         let prev_state_root = H256::from(self.block.prev_state_root.to_be_bytes());
 
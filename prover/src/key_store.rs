@@ -0,0 +1,136 @@
+use crate::params::LoadedParams;
+use crate::utils::hex_encode;
+use crate::utils::write_file_atomic;
+use crate::G1Affine;
+use halo2_proofs::halo2curves::bn256::Fr;
+use halo2_proofs::plonk::Circuit;
+use halo2_proofs::plonk::VerifyingKey;
+use sha2::Digest;
+use sha2::Sha256;
+use std::fs;
+use std::path::PathBuf;
+
+const MAGIC: &[u8; 8] = b"zkevm-vk";
+const VERSION: u8 = 1;
+/// magic | version | key id | sha256(payload)
+const HEADER_LEN: usize = 8 + 1 + 32 + 32;
+
+/// Stores verifying keys inside a local directory.
+///
+/// Keys are identified by the proving key cache key (circuit, params path, `CircuitConfig`)
+/// and the hash of the params.
+///
+/// Only verifying keys are stored. The halo2 revision in use (the workspace `[patch]` to
+/// `pinkiebell/halo2@5fc8ce89`, based on `v2022_10_22`) has no `ProvingKey::read/write`
+/// and the fields of `ProvingKey` are private, so it can not be serialized outside of halo2.
+/// The `ProvingKey` is still derived with `keygen_pk` on first use after a restart,
+/// a stored key only saves the `keygen_vk` part. Storing proving keys requires a halo2
+/// revision with `ProvingKey` serialization, together with matching versions of
+/// zkevm-circuits, halo2wrong and plonk-verifier.
+pub struct KeyStore {
+    path: PathBuf,
+}
+
+impl KeyStore {
+    /// Opens or creates the directory at `path`.
+    pub fn open<P: Into<PathBuf>>(path: P) -> Result<Self, String> {
+        let path = path.into();
+        fs::create_dir_all(&path).map_err(|e| format!("{:?}: {}", path, e))?;
+
+        Ok(Self { path })
+    }
+
+    /// Returns the path of the key file for `cache_key` and `params`.
+    pub fn key_path(&self, cache_key: &str, params: &LoadedParams) -> PathBuf {
+        let key_id = key_id(cache_key, params);
+
+        self.path.join(format!("{}.vk", hex_encode(&key_id)))
+    }
+
+    /// Reads the verifying key for `cache_key` and `params`.
+    /// Returns `None` if the key does not exist or fails the integrity checks.
+    pub fn load_vk<C: Circuit<Fr>>(
+        &self,
+        cache_key: &str,
+        params: &LoadedParams,
+    ) -> Result<Option<VerifyingKey<G1Affine>>, String> {
+        let key_id = key_id(cache_key, params);
+        let path = self.key_path(cache_key, params);
+        let data = match fs::read(&path) {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(format!("{:?}: {}", path, err)),
+            Ok(data) => data,
+        };
+
+        let payload = match check_key_file(&data, &key_id) {
+            Err(err) => {
+                log::warn!("KeyStore: ignoring {:?}: {}", path, err);
+                return Ok(None);
+            }
+            Ok(payload) => payload,
+        };
+        match VerifyingKey::<G1Affine>::read::<_, C>(&mut &payload[..], params.params.as_ref()) {
+            Err(err) => {
+                log::warn!("KeyStore: ignoring {:?}: {}", path, err);
+                Ok(None)
+            }
+            Ok(vk) => {
+                log::info!("KeyStore: loaded {:?} key={}", path, cache_key);
+                Ok(Some(vk))
+            }
+        }
+    }
+
+    /// Writes `vk` for `cache_key` and `params`, replacing any existing key.
+    pub fn save_vk(
+        &self,
+        cache_key: &str,
+        params: &LoadedParams,
+        vk: &VerifyingKey<G1Affine>,
+    ) -> Result<PathBuf, String> {
+        let key_id = key_id(cache_key, params);
+        let path = self.key_path(cache_key, params);
+
+        let mut payload = Vec::new();
+        vk.write(&mut payload).map_err(|e| e.to_string())?;
+        let mut data = Vec::with_capacity(HEADER_LEN + payload.len());
+        data.extend(MAGIC);
+        data.push(VERSION);
+        data.extend(key_id);
+        data.extend(Sha256::digest(&payload));
+        data.extend(payload);
+
+        write_file_atomic(&path, &data)?;
+        log::info!("KeyStore: saved {:?} key={}", path, cache_key);
+
+        Ok(path)
+    }
+}
+
+/// sha256(cache_key || params hash)
+fn key_id(cache_key: &str, params: &LoadedParams) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(cache_key.as_bytes());
+    hasher.update(params.hash.as_bytes());
+
+    hasher.finalize().into()
+}
+
+/// Returns the payload of a key file after checking the header and checksum.
+fn check_key_file<'a>(data: &'a [u8], key_id: &[u8; 32]) -> Result<&'a [u8], String> {
+    if data.len() < HEADER_LEN || &data[..8] != MAGIC {
+        return Err("not a key file".to_string());
+    }
+    if data[8] != VERSION {
+        return Err(format!("unsupported version {}", data[8]));
+    }
+    if &data[9..41] != key_id {
+        return Err("key id mismatch".to_string());
+    }
+    let payload = &data[HEADER_LEN..];
+    if data[41..HEADER_LEN] != Sha256::digest(payload)[..] {
+        return Err("checksum mismatch".to_string());
+    }
+
+    Ok(payload)
+}
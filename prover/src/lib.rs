@@ -12,8 +12,13 @@ pub mod aggregation_circuit;
 pub mod circuit_autogen;
 pub mod circuit_witness;
 pub mod dummy_circuit;
+pub mod key_store;
+pub mod params;
+pub mod peers;
 pub mod public_input_circuit;
 pub mod server;
 pub mod shared_state;
 pub mod super_circuit;
+pub mod task_store;
 pub mod utils;
+pub mod worker;
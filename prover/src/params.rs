@@ -0,0 +1,108 @@
+use crate::utils::fixed_rng;
+use crate::utils::hex_encode;
+use crate::ProverParams;
+use halo2_proofs::poly::commitment::Params;
+use sha2::Digest;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs::File;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Circuit parameters as returned by `ParamsManager`.
+#[derive(Clone)]
+pub struct LoadedParams {
+    pub params: Arc<ProverParams>,
+    /// The file the params were loaded from or `setup-<k>` if generated on the fly.
+    pub path: String,
+    /// Hex encoded sha256 of the serialized params.
+    pub hash: String,
+}
+
+/// Loads circuit parameters and keeps them in memory for subsequent proofs.
+pub struct ParamsManager {
+    /// If `true`, params are generated on the fly if no file is given.
+    /// The setup uses a fixed rng and is therefore insecure.
+    allow_setup: bool,
+    cache: Mutex<HashMap<String, LoadedParams>>,
+}
+
+impl ParamsManager {
+    pub fn new(allow_setup: bool) -> Self {
+        Self {
+            allow_setup,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the params for degree `k` from `param`, a file or a directory ending with `/`
+    /// which contains `<k>.bin` files.
+    /// A single file serves all circuits and must be at least of degree `k`,
+    /// files inside a directory must be exactly of degree `k`.
+    pub fn get(&self, param: &Option<String>, k: usize) -> Result<LoadedParams, String> {
+        let path = match param {
+            Some(path) => match path.ends_with('/') {
+                true => format!("{}{}.bin", path, k),
+                false => path.clone(),
+            },
+            None => {
+                if !self.allow_setup {
+                    return Err(
+                        "no params file given and the on-the-fly setup is not enabled".to_string(),
+                    );
+                }
+                format!("setup-{}", k)
+            }
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(loaded) = cache.get(&path) {
+            return Ok(loaded.clone());
+        }
+
+        let params = match param {
+            Some(_) => {
+                let file = File::open(&path).map_err(|e| format!("{}: {}", path, e))?;
+                let params = ProverParams::read(&mut std::io::BufReader::new(file))
+                    .map_err(|e| format!("{}: {}", path, e))?;
+                let params_k = params.k() as usize;
+                let dir = path != *param.as_ref().unwrap();
+                if params_k < k || (dir && params_k != k) {
+                    return Err(format!(
+                        "{}: expected params of k={} got k={}",
+                        path, k, params_k
+                    ));
+                }
+                params
+            }
+            None => {
+                log::warn!("ParamsManager: insecure setup for k={}", k);
+                ProverParams::setup(k as u32, fixed_rng())
+            }
+        };
+
+        let mut hasher = Sha256::new();
+        params.write(&mut hasher).map_err(|e| e.to_string())?;
+        let hash = hex_encode(&hasher.finalize());
+        log::info!(
+            "ParamsManager: loaded {} k={} sha256={}",
+            path,
+            params.k(),
+            hash
+        );
+
+        let loaded = LoadedParams {
+            params: Arc::new(params),
+            path: path.clone(),
+            hash,
+        };
+        cache.insert(path, loaded.clone());
+
+        Ok(loaded)
+    }
+
+    /// Drops all cached params.
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+}
@@ -0,0 +1,117 @@
+use crate::task_store::timestamp;
+use crate::utils::hex_encode;
+use hmac::Hmac;
+use hmac::Mac;
+use sha2::Sha256;
+use zkevm_common::prover::*;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Max. difference in seconds between the clocks of two nodes for a `PeerRequest`.
+pub const MAX_CLOCK_SKEW: u64 = 60;
+
+/// Signs a request to another node with the shared peer secret.
+/// `addr` is the address of this node and only used for `join`.
+pub fn sign_request(
+    secret: &str,
+    method: &str,
+    node_id: &str,
+    addr: Option<String>,
+) -> PeerRequest {
+    let mut req = PeerRequest {
+        node_id: node_id.to_string(),
+        addr,
+        nonce: crate::shared_state::SharedState::random_worker_id(),
+        timestamp: timestamp(),
+        signature: String::new(),
+    };
+    req.signature = sign(secret, &request_message(method, &req));
+
+    req
+}
+
+/// Checks the signature and timestamp of a request for `method`.
+pub fn verify_request(secret: &str, method: &str, req: &PeerRequest) -> Result<(), String> {
+    let now = timestamp();
+    if req.timestamp.abs_diff(now) > MAX_CLOCK_SKEW {
+        return Err(format!("{}: request from {} expired", method, req.node_id));
+    }
+    if !verify(secret, &request_message(method, req), &req.signature) {
+        return Err(format!(
+            "{}: invalid signature from {}",
+            method, req.node_id
+        ));
+    }
+
+    Ok(())
+}
+
+/// Signs the response to `info`, bound to the nonce of the request.
+pub fn sign_node_information(
+    secret: &str,
+    nonce: &str,
+    info: &NodeInformation,
+) -> Result<SignedNodeInformation, String> {
+    let payload = serde_json::to_string(info).map_err(|e| e.to_string())?;
+    let signature = sign(secret, &response_message(nonce, &payload));
+
+    Ok(SignedNodeInformation { payload, signature })
+}
+
+/// Returns the `NodeInformation` if the response to `info` carries a valid signature
+/// for the nonce of the request.
+pub fn verify_node_information(
+    secret: &str,
+    nonce: &str,
+    signed: &SignedNodeInformation,
+) -> Result<NodeInformation, String> {
+    if !verify(
+        secret,
+        &response_message(nonce, &signed.payload),
+        &signed.signature,
+    ) {
+        return Err("info: invalid signature".to_string());
+    }
+
+    serde_json::from_str(&signed.payload).map_err(|e| e.to_string())
+}
+
+fn request_message(method: &str, req: &PeerRequest) -> Vec<u8> {
+    serde_json::json!([method, req.node_id, req.addr, req.nonce, req.timestamp])
+        .to_string()
+        .into_bytes()
+}
+
+fn response_message(nonce: &str, payload: &str) -> Vec<u8> {
+    serde_json::json!(["info-response", nonce, payload])
+        .to_string()
+        .into_bytes()
+}
+
+/// Returns the hex encoded HMAC-SHA256 of `message`.
+fn sign(secret: &str, message: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("any key length");
+    mac.update(message);
+
+    hex_encode(&mac.finalize().into_bytes())
+}
+
+/// Checks the hex encoded HMAC-SHA256 `signature` of `message` in constant time.
+fn verify(secret: &str, message: &[u8], signature: &str) -> bool {
+    if signature.len() != 64 || !signature.is_ascii() {
+        return false;
+    }
+    let bytes: Result<Vec<u8>, _> = (0..signature.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&signature[i..i + 2], 16))
+        .collect();
+    let bytes = match bytes {
+        Err(_) => return false,
+        Ok(bytes) => bytes,
+    };
+
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("any key length");
+    mac.update(message);
+
+    mac.verify_slice(&bytes).is_ok()
+}
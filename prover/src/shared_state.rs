@@ -3,19 +3,29 @@ use crate::aggregation_circuit::PoseidonTranscript;
 use crate::aggregation_circuit::Snark;
 use crate::circuit_witness::CircuitWitness;
 use crate::dummy_circuit;
+use crate::key_store::KeyStore;
+use crate::params::LoadedParams;
+use crate::params::ParamsManager;
+use crate::peers::*;
 use crate::public_input_circuit;
 use crate::super_circuit;
+use crate::task_store::timestamp;
+use crate::task_store::TaskRetention;
+use crate::task_store::TaskStore;
+use crate::utils::aggregation_label;
 use crate::utils::collect_instance;
 use crate::utils::fixed_rng;
 use crate::utils::gen_num_instance;
 use crate::utils::gen_proof;
+use crate::utils::hex_encode;
+use crate::utils::verify_proof;
+use crate::worker::Worker;
 use crate::G1Affine;
 use crate::ProverKey;
-use crate::ProverParams;
 use halo2_proofs::dev::MockProver;
 use halo2_proofs::halo2curves::bn256::Fr;
 use halo2_proofs::plonk::Circuit;
-use halo2_proofs::plonk::{keygen_pk, keygen_vk};
+use halo2_proofs::plonk::{keygen_pk, keygen_vk, VerifyingKey};
 use halo2_proofs::poly::commitment::Params;
 use hyper::Uri;
 use plonk_verifier::loader::native::NativeLoader;
@@ -24,40 +34,40 @@ use plonk_verifier::system::halo2::transcript::evm::EvmTranscript;
 use plonk_verifier::system::halo2::Config as PlonkConfig;
 use rand::{thread_rng, Rng};
 use std::collections::HashMap;
-use std::fmt::Write;
-use std::fs::File;
+use std::collections::HashSet;
 use std::net::ToSocketAddrs;
 use std::sync::Arc;
+use std::time::Duration;
 use std::time::Instant;
 use tokio::sync::Mutex;
-use zkevm_common::json_rpc::jsonrpc_request_client;
+use tokio::sync::Notify;
+use zkevm_common::json_rpc::JsonRpcClient;
+use zkevm_common::json_rpc::RetryPolicy;
 use zkevm_common::prover::*;
 
-fn get_param_path(path: &String, k: usize) -> String {
-    // try to automatically choose a file if the path ends with a `/`.
-    match path.ends_with('/') {
-        true => {
-            format!("{}{}.bin", path, k)
-        }
-        false => path.clone(),
-    }
+/// Default for `RoState::lease_ttl`.
+pub const DEFAULT_LEASE_TTL: u64 = 60;
+
+/// Seconds until the verification of a result that could not be verified is retried,
+/// doubled with each attempt up to `MAX_VERIFICATION_BACKOFF`.
+const VERIFICATION_BACKOFF: u64 = 10;
+const MAX_VERIFICATION_BACKOFF: u64 = 600;
+
+/// The reasons why `SharedState::verify_proofs` does not accept proofs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationError {
+    /// The proofs are invalid or do not belong to the task.
+    Invalid(String),
+    /// The proofs could not be verified by this node, e.g. because the block rpc,
+    /// the circuit parameters or the verifying keys are not available.
+    Unavailable(String),
 }
 
-fn get_or_gen_param(task_options: &ProofRequestOptions, k: usize) -> (Arc<ProverParams>, String) {
-    match &task_options.param {
-        Some(v) => {
-            let path = get_param_path(v, k);
-            let file = File::open(&path).expect("couldn't open params");
-            let params = Arc::new(
-                ProverParams::read(&mut std::io::BufReader::new(file))
-                    .expect("Failed to read params"),
-            );
-
-            (params, path)
-        }
-        None => {
-            let param = Arc::new(ProverParams::setup(k as u32, fixed_rng()));
-            (param, format!("{}", k))
+impl std::fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerificationError::Invalid(err) => write!(f, "invalid proofs: {}", err),
+            VerificationError::Unavailable(err) => write!(f, "unable to verify: {}", err),
         }
     }
 }
@@ -78,9 +88,11 @@ macro_rules! gen_proof {
             task_options.circuit, CIRCUIT_CONFIG.block_gas_limit
         );
         let mut aggregation_proof = ProofResult::default();
-        aggregation_proof.label = format!(
-            "{}-{}-a",
-            task_options.circuit, CIRCUIT_CONFIG.block_gas_limit
+        aggregation_proof.label = aggregation_label(
+            &task_options.circuit,
+            CIRCUIT_CONFIG.block_gas_limit,
+            1,
+            task_options.expose_instances,
         );
 
         if task_options.mock {
@@ -99,8 +111,13 @@ macro_rules! gen_proof {
             prover.verify_par().expect("MockProver::verify_par");
             circuit_proof.duration = Instant::now().duration_since(time_started).as_millis() as u32;
         } else {
-            let (param, param_path) = get_or_gen_param(&task_options, CIRCUIT_CONFIG.min_k);
+            let loaded_param = shared_state
+                .ro
+                .params
+                .get(&task_options.param, CIRCUIT_CONFIG.min_k)?;
+            let param = loaded_param.params.clone();
             circuit_proof.k = param.k() as u8;
+            circuit_proof.params_hash = loaded_param.hash.clone();
             let circuit = $CIRCUIT::gen_circuit::<
                 { CIRCUIT_CONFIG.max_txs },
                 { CIRCUIT_CONFIG.max_calldata },
@@ -109,12 +126,15 @@ macro_rules! gen_proof {
             >(&witness, fixed_rng())?;
             // generate and cache the prover key
             let pk = {
-                let cache_key = format!(
-                    "{}{}{:?}",
-                    &task_options.circuit, &param_path, &CIRCUIT_CONFIG
+                let cache_key = pk_cache_key(
+                    &task_options.circuit,
+                    &loaded_param.path,
+                    &CIRCUIT_CONFIG,
+                    0,
+                    false,
                 );
                 shared_state
-                    .gen_pk(&cache_key, &param, &circuit)
+                    .gen_pk(&cache_key, &loaded_param, &circuit)
                     .await
                     .map_err(|e| e.to_string())?
             };
@@ -151,18 +171,26 @@ macro_rules! gen_proof {
                 );
                 let snark = Snark::new(protocol, circuit_instance, proof);
 
-                let (agg_params, agg_param_path) =
-                    get_or_gen_param(&task_options, CIRCUIT_CONFIG.min_k_aggregation);
+                let loaded_agg_params = shared_state
+                    .ro
+                    .params
+                    .get(&task_options.param, CIRCUIT_CONFIG.min_k_aggregation)?;
+                let agg_params = loaded_agg_params.params.clone();
                 aggregation_proof.k = agg_params.k() as u8;
+                aggregation_proof.params_hash = loaded_agg_params.hash.clone();
                 let agg_circuit =
-                    AggregationCircuit::new(agg_params.as_ref(), [snark], fixed_rng());
+                    AggregationCircuit::new(agg_params.as_ref(), [snark], fixed_rng())
+                        .with_exposed_instances(task_options.expose_instances);
                 let agg_pk = {
-                    let cache_key = format!(
-                        "{}{}{:?}ag",
-                        &task_options.circuit, &agg_param_path, &CIRCUIT_CONFIG
+                    let cache_key = pk_cache_key(
+                        &task_options.circuit,
+                        &loaded_agg_params.path,
+                        &CIRCUIT_CONFIG,
+                        1,
+                        task_options.expose_instances,
                     );
                     shared_state
-                        .gen_pk(&cache_key, &agg_params, &agg_circuit)
+                        .gen_pk(&cache_key, &loaded_agg_params, &agg_circuit)
                         .await
                         .map_err(|e| e.to_string())?
                 };
@@ -212,6 +240,345 @@ macro_rules! gen_proof {
     }};
 }
 
+/// Generates or loads the proving key of `$CIRCUIT` for `CIRCUIT_CONFIG`.
+macro_rules! gen_key {
+    ($shared_state:expr, $task_options:expr, $witness:expr, $CIRCUIT:ident) => {{
+        let loaded_param = $shared_state
+            .ro
+            .params
+            .get(&$task_options.param, CIRCUIT_CONFIG.min_k)?;
+        let circuit = $CIRCUIT::gen_circuit::<
+            { CIRCUIT_CONFIG.max_txs },
+            { CIRCUIT_CONFIG.max_calldata },
+            { CIRCUIT_CONFIG.max_rws },
+            _,
+        >($witness, fixed_rng())?;
+        let cache_key = pk_cache_key(
+            &$task_options.circuit,
+            &loaded_param.path,
+            &CIRCUIT_CONFIG,
+            0,
+            false,
+        );
+        $shared_state
+            .gen_pk(&cache_key, &loaded_param, &circuit)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        cache_key
+    }};
+}
+
+/// Verifies `Proofs` of `$CIRCUIT` against the locally generated verifying keys.
+/// `$circuits` are pairs of the witness and the circuit proof of each block,
+/// the instance of each circuit proof is derived from the witness.
+macro_rules! verify_proofs {
+    ($shared_state:expr, $task_options:expr, $proofs:expr, $circuits:expr, $CIRCUIT:ident) => {{
+        let shared_state = $shared_state;
+        let task_options = $task_options;
+        let proofs = $proofs;
+
+        if proofs.config.block_gas_limit != CIRCUIT_CONFIG.block_gas_limit {
+            return Err(VerificationError::Invalid(format!(
+                "expected circuit config for gas={} got {}",
+                CIRCUIT_CONFIG.block_gas_limit, proofs.config.block_gas_limit
+            )));
+        }
+        let loaded_param = shared_state
+            .ro
+            .params
+            .get(&task_options.param, CIRCUIT_CONFIG.min_k)
+            .map_err(VerificationError::Unavailable)?;
+        let param = loaded_param.params.as_ref();
+
+        let mut snarks = Vec::new();
+        for (witness, circuit_proof) in $circuits {
+            if circuit_proof.params_hash != loaded_param.hash {
+                return Err(VerificationError::Invalid(
+                    "circuit proof: params mismatch".to_string(),
+                ));
+            }
+            let circuit = $CIRCUIT::gen_circuit::<
+                { CIRCUIT_CONFIG.max_txs },
+                { CIRCUIT_CONFIG.max_calldata },
+                { CIRCUIT_CONFIG.max_rws },
+                _,
+            >(witness, fixed_rng())
+            .map_err(VerificationError::Unavailable)?;
+            let circuit_instance = circuit.instance();
+            if circuit_proof.instance != collect_instance(&circuit_instance) {
+                return Err(VerificationError::Invalid(
+                    "circuit proof: instance mismatch".to_string(),
+                ));
+            }
+            let vk = {
+                let cache_key = pk_cache_key(
+                    &task_options.circuit,
+                    &loaded_param.path,
+                    &CIRCUIT_CONFIG,
+                    0,
+                    false,
+                );
+                shared_state
+                    .gen_vk(&cache_key, &loaded_param, &circuit)
+                    .await
+                    .map_err(|e| VerificationError::Unavailable(e.to_string()))?
+            };
+
+            if task_options.aggregate {
+                verify_proof::<_, PoseidonTranscript<NativeLoader, _>>(
+                    param,
+                    &vk,
+                    &circuit_instance,
+                    &circuit_proof.proof,
+                )
+                .map_err(|e| VerificationError::Invalid(format!("circuit proof: {}", e)))?;
+
+                let protocol = compile(
+                    param,
+                    &vk,
+                    PlonkConfig::kzg().with_num_instance(gen_num_instance(&circuit_instance)),
+                );
+                snarks.push(Snark::new(
+                    protocol,
+                    circuit_instance,
+                    circuit_proof.proof.to_vec(),
+                ));
+            } else {
+                verify_proof::<_, EvmTranscript<G1Affine, _, _, _>>(
+                    param,
+                    &vk,
+                    &circuit_instance,
+                    &circuit_proof.proof,
+                )
+                .map_err(|e| VerificationError::Invalid(format!("circuit proof: {}", e)))?;
+            }
+        }
+
+        if task_options.aggregate {
+            let loaded_agg_params = shared_state
+                .ro
+                .params
+                .get(&task_options.param, CIRCUIT_CONFIG.min_k_aggregation)
+                .map_err(VerificationError::Unavailable)?;
+            if proofs.aggregation.params_hash != loaded_agg_params.hash {
+                return Err(VerificationError::Invalid(
+                    "aggregation proof: params mismatch".to_string(),
+                ));
+            }
+            let num_snarks = snarks.len();
+            // the circuit proofs are valid, this also yields the expected accumulator
+            let agg_circuit =
+                AggregationCircuit::new(loaded_agg_params.params.as_ref(), snarks, fixed_rng())
+                    .with_exposed_instances(task_options.expose_instances);
+            let agg_instance = agg_circuit.instance();
+            if proofs.aggregation.instance != collect_instance(&agg_instance) {
+                return Err(VerificationError::Invalid(
+                    "aggregation proof: instance mismatch".to_string(),
+                ));
+            }
+            let agg_vk = {
+                let cache_key = pk_cache_key(
+                    &task_options.circuit,
+                    &loaded_agg_params.path,
+                    &CIRCUIT_CONFIG,
+                    num_snarks,
+                    task_options.expose_instances,
+                );
+                shared_state
+                    .gen_vk(&cache_key, &loaded_agg_params, &agg_circuit)
+                    .await
+                    .map_err(|e| VerificationError::Unavailable(e.to_string()))?
+            };
+            verify_proof::<_, EvmTranscript<G1Affine, _, _, _>>(
+                loaded_agg_params.params.as_ref(),
+                &agg_vk,
+                &agg_instance,
+                &proofs.aggregation.proof,
+            )
+            .map_err(|e| VerificationError::Invalid(format!("aggregation proof: {}", e)))?;
+        }
+    }};
+}
+
+/// Generates the circuit proofs of `$CIRCUIT` for each of `$witnesses`
+/// and aggregates them into one proof.
+/// Returns the `BlockProof`s together with the aggregation proof.
+macro_rules! gen_aggregated_proof {
+    ($shared_state:expr, $task_options:expr, $witnesses:expr, $CIRCUIT:ident) => {{
+        let shared_state = $shared_state;
+        let task_options = $task_options;
+
+        log::info!("Using circuit parameters: {:#?}", CIRCUIT_CONFIG);
+
+        let mut aggregation_proof = ProofResult::default();
+        let loaded_param = match task_options.mock {
+            true => None,
+            false => Some(
+                shared_state
+                    .ro
+                    .params
+                    .get(&task_options.param, CIRCUIT_CONFIG.min_k)?,
+            ),
+        };
+
+        let mut blocks = Vec::new();
+        let mut snarks = Vec::new();
+        for (block, witness) in $witnesses {
+            let mut circuit_proof = ProofResult::default();
+            circuit_proof.label = format!(
+                "{}-{}",
+                task_options.circuit, CIRCUIT_CONFIG.block_gas_limit
+            );
+            let time_started = Instant::now();
+            let circuit = $CIRCUIT::gen_circuit::<
+                { CIRCUIT_CONFIG.max_txs },
+                { CIRCUIT_CONFIG.max_calldata },
+                { CIRCUIT_CONFIG.max_rws },
+                _,
+            >(witness, fixed_rng())?;
+            let circuit_instance = circuit.instance();
+            circuit_proof.instance = collect_instance(&circuit_instance);
+
+            match &loaded_param {
+                None => {
+                    // only run the mock prover
+                    circuit_proof.k = CIRCUIT_CONFIG.min_k as u8;
+                    let prover =
+                        MockProver::run(CIRCUIT_CONFIG.min_k as u32, &circuit, circuit_instance)
+                            .expect("MockProver::run");
+                    prover.verify_par().expect("MockProver::verify_par");
+                }
+                Some(loaded_param) => {
+                    let param = loaded_param.params.as_ref();
+                    circuit_proof.k = param.k() as u8;
+                    circuit_proof.params_hash = loaded_param.hash.clone();
+                    let pk = {
+                        let cache_key = pk_cache_key(
+                            &task_options.circuit,
+                            &loaded_param.path,
+                            &CIRCUIT_CONFIG,
+                            0,
+                            false,
+                        );
+                        shared_state
+                            .gen_pk(&cache_key, loaded_param, &circuit)
+                            .await
+                            .map_err(|e| e.to_string())?
+                    };
+                    let proof = gen_proof::<
+                        _,
+                        _,
+                        PoseidonTranscript<NativeLoader, _>,
+                        PoseidonTranscript<NativeLoader, _>,
+                        _,
+                    >(
+                        param,
+                        &pk,
+                        circuit,
+                        circuit_instance.clone(),
+                        fixed_rng(),
+                        task_options.mock_feedback,
+                    );
+                    circuit_proof.proof = proof.clone().into();
+
+                    let protocol = compile(
+                        param,
+                        pk.get_vk(),
+                        PlonkConfig::kzg().with_num_instance(gen_num_instance(&circuit_instance)),
+                    );
+                    snarks.push(Snark::new(protocol, circuit_instance, proof));
+                }
+            }
+            circuit_proof.duration = Instant::now().duration_since(time_started).as_millis() as u32;
+
+            blocks.push(BlockProof {
+                block,
+                gas: witness.gas_used(),
+                circuit: circuit_proof,
+            });
+        }
+
+        // the verifier depends on the number of aggregated blocks
+        aggregation_proof.label = aggregation_label(
+            &task_options.circuit,
+            CIRCUIT_CONFIG.block_gas_limit,
+            blocks.len(),
+            task_options.expose_instances,
+        );
+        if !snarks.is_empty() {
+            // aggregate the circuit proofs
+            let time_started = Instant::now();
+            let num_snarks = snarks.len();
+            let loaded_agg_params = shared_state
+                .ro
+                .params
+                .get(&task_options.param, CIRCUIT_CONFIG.min_k_aggregation)?;
+            let agg_params = loaded_agg_params.params.clone();
+            aggregation_proof.k = agg_params.k() as u8;
+            aggregation_proof.params_hash = loaded_agg_params.hash.clone();
+            let agg_circuit = AggregationCircuit::new(agg_params.as_ref(), snarks, fixed_rng())
+                .with_exposed_instances(task_options.expose_instances);
+            let agg_pk = {
+                let cache_key = pk_cache_key(
+                    &task_options.circuit,
+                    &loaded_agg_params.path,
+                    &CIRCUIT_CONFIG,
+                    num_snarks,
+                    task_options.expose_instances,
+                );
+                shared_state
+                    .gen_pk(&cache_key, &loaded_agg_params, &agg_circuit)
+                    .await
+                    .map_err(|e| e.to_string())?
+            };
+            let agg_instance = agg_circuit.instance();
+            aggregation_proof.instance = collect_instance(&agg_instance);
+            let proof = gen_proof::<
+                _,
+                _,
+                EvmTranscript<G1Affine, _, _, _>,
+                EvmTranscript<G1Affine, _, _, _>,
+                _,
+            >(
+                agg_params.as_ref(),
+                &agg_pk,
+                agg_circuit,
+                agg_instance,
+                fixed_rng(),
+                task_options.mock_feedback,
+            );
+            aggregation_proof.duration =
+                Instant::now().duration_since(time_started).as_millis() as u32;
+            aggregation_proof.proof = proof.into();
+        }
+
+        // return
+        (CIRCUIT_CONFIG, blocks, aggregation_proof)
+    }};
+}
+
+/// The key for `RwState::pk_cache` and the `KeyStore`.
+/// `aggregated` is the number of snarks of the aggregation circuit or `0` for the circuit itself.
+/// `expose_instances` only applies to the aggregation circuit.
+pub fn pk_cache_key(
+    circuit: &str,
+    param_path: &str,
+    circuit_config: &CircuitConfig,
+    aggregated: usize,
+    expose_instances: bool,
+) -> String {
+    let key = match aggregated {
+        0 => return format!("{}{}{:?}", circuit, param_path, circuit_config),
+        1 => format!("{}{}{:?}ag", circuit, param_path, circuit_config),
+        n => format!("{}{}{:?}ag{}", circuit, param_path, circuit_config, n),
+    };
+    match expose_instances {
+        true => format!("{}i", key),
+        false => key,
+    }
+}
+
 #[derive(Clone)]
 pub struct RoState {
     // a unique identifier
@@ -219,6 +586,33 @@ pub struct RoState {
     // a `HOSTNAME:PORT` conformant string that will be used for DNS service discovery of other
     // nodes
     pub node_lookup: Option<String>,
+    /// `HOST:PORT` addresses of other nodes.
+    pub static_peers: Vec<String>,
+    /// The `HOST:PORT` address other nodes can reach this node at, announced with `join`.
+    pub advertise_addr: Option<String>,
+    /// Shared secret to sign and verify peer messages, see `crate::peers`.
+    /// Defaults to a random value, such a node only trusts itself.
+    pub peer_secret: String,
+    /// json-rpc client for peer requests
+    pub http_client: JsonRpcClient,
+    /// If set, json-rpc requests to this and other nodes must be authenticated with this token.
+    pub auth_token: Option<String>,
+    /// If set, tasks and results are persisted and survive restarts.
+    pub task_store: Option<Arc<dyn TaskStore>>,
+    /// Pruning policy for completed tasks.
+    pub task_retention: TaskRetention,
+    /// If set, verifying keys are persisted and reused across restarts.
+    pub key_store: Option<Arc<KeyStore>>,
+    /// Circuit parameters in use.
+    pub params: Arc<ParamsManager>,
+    /// Notified if tasks got cancelled or removed.
+    pub task_cancelled: Arc<Notify>,
+    /// If set, proofs are computed inside a child process.
+    pub worker: Option<Arc<Worker>>,
+    /// Lifetime of task leases in seconds, see `TaskLease`.
+    pub lease_ttl: u64,
+    /// If `true`, proofs merged from other nodes are verified before they are accepted.
+    pub verify_results: bool,
 }
 
 pub struct RwState {
@@ -228,6 +622,21 @@ pub struct RwState {
     pub pending: Option<ProofRequestOptions>,
     /// `true` if this instance started working on `pending`
     pub obtained: bool,
+    /// Addresses of the nodes that announced themselves with `join`.
+    pub joined_peers: HashSet<String>,
+    /// Results of other nodes that could not be verified, see `merge_tasks`.
+    pub unverified_results: Vec<UnverifiedResult>,
+}
+
+/// A result of another node that could not be verified and is retried later.
+#[derive(Debug, Clone)]
+pub struct UnverifiedResult {
+    pub peer: String,
+    pub options: ProofRequestOptions,
+    pub edition: u64,
+    pub attempts: u32,
+    /// Unix timestamp in seconds.
+    pub retry_at: u64,
 }
 
 #[derive(Clone)]
@@ -242,16 +651,118 @@ impl SharedState {
             ro: RoState {
                 node_id,
                 node_lookup,
+                static_peers: Vec::new(),
+                advertise_addr: None,
+                peer_secret: Self::random_worker_id(),
+                http_client: JsonRpcClient::new(5000).with_retry(RetryPolicy {
+                    retries: 2,
+                    backoff: 250,
+                    max_backoff: 1000,
+                }),
+                auth_token: None,
+                task_store: None,
+                task_retention: TaskRetention::default(),
+                key_store: None,
+                params: Arc::new(ParamsManager::new(false)),
+                task_cancelled: Arc::new(Notify::new()),
+                worker: None,
+                lease_ttl: DEFAULT_LEASE_TTL,
+                verify_results: true,
             },
             rw: Arc::new(Mutex::new(RwState {
                 tasks: Vec::new(),
                 pk_cache: HashMap::new(),
                 pending: None,
                 obtained: false,
+                joined_peers: HashSet::new(),
+                unverified_results: Vec::new(),
             })),
         }
     }
 
+    /// Sets the bearer token for the json-rpc server and for requests to other nodes.
+    pub fn with_auth_token(mut self, auth_token: Option<String>) -> SharedState {
+        self.ro.http_client = self.ro.http_client.with_auth_token(auth_token.clone());
+        self.ro.auth_token = auth_token;
+        self
+    }
+
+    /// Adds `HOST:PORT` addresses of other nodes in addition to `node_lookup`.
+    pub fn with_static_peers(mut self, static_peers: Vec<String>) -> SharedState {
+        self.ro.static_peers = static_peers;
+        self
+    }
+
+    /// Sets the address that is announced to other nodes with `announce`.
+    pub fn with_advertise_addr(mut self, advertise_addr: Option<String>) -> SharedState {
+        self.ro.advertise_addr = advertise_addr;
+        self
+    }
+
+    /// Sets the secret shared by all nodes of the cluster.
+    /// Task information from nodes without the same secret is rejected.
+    pub fn with_peer_secret(mut self, peer_secret: String) -> SharedState {
+        self.ro.peer_secret = peer_secret;
+        self
+    }
+
+    /// Persists tasks to `task_store` and loads the tasks that were stored before.
+    pub fn with_task_store(
+        mut self,
+        task_store: Arc<dyn TaskStore>,
+    ) -> Result<SharedState, String> {
+        let tasks = task_store.load()?;
+        log::info!("TaskStore: loaded {} tasks", tasks.len());
+        self.rw
+            .try_lock()
+            .map_err(|e| e.to_string())?
+            .tasks
+            .extend(tasks);
+        self.ro.task_store = Some(task_store);
+
+        Ok(self)
+    }
+
+    /// Persists the keys generated by `gen_pk` to `key_store`.
+    pub fn with_key_store(mut self, key_store: Arc<KeyStore>) -> SharedState {
+        self.ro.key_store = Some(key_store);
+        self
+    }
+
+    /// Allows to generate insecure circuit parameters on the fly
+    /// if a `ProofRequestOptions` does not specify `param`.
+    pub fn with_params_setup(mut self, allow_setup: bool) -> SharedState {
+        self.ro.params = Arc::new(ParamsManager::new(allow_setup));
+        self
+    }
+
+    /// Computes proofs inside a child process with `worker`,
+    /// a crashing computation is recorded as an error of the task.
+    pub fn with_worker(mut self, worker: Worker) -> SharedState {
+        self.ro.worker = Some(Arc::new(worker));
+        self
+    }
+
+    /// Sets the lifetime of task leases in seconds.
+    /// Leases are renewed every `lease_ttl / 3` seconds while working on a task.
+    pub fn with_lease_ttl(mut self, lease_ttl: u64) -> SharedState {
+        self.ro.lease_ttl = lease_ttl;
+        self
+    }
+
+    /// Enables or disables the verification of proofs merged from other nodes, see `verify_proofs`.
+    /// Enabled by default.
+    pub fn with_result_verification(mut self, verify_results: bool) -> SharedState {
+        self.ro.verify_results = verify_results;
+        self
+    }
+
+    /// Sets the pruning policy for completed tasks, see `prune_tasks`.
+    pub fn with_task_retention(mut self, task_retention: TaskRetention) -> SharedState {
+        self.ro.task_retention = task_retention;
+        self
+    }
+
     /// Will return the result or error of the task if it's completed.
     /// Otherwise enqueues the task and returns `None`.
     /// `retry_if_error` enqueues the task again if it returned with an error
@@ -274,6 +785,9 @@ impl SharedState {
                     // will be a candidate in `duty_cycle` again
                     task.result = None;
                     task.edition += 1;
+                    task.completed_at = None;
+                    task.lease = None;
+                    self.persist_task(task);
                 } else {
                     log::debug!("completed: {:#?}", task);
                     return task.result.clone();
@@ -288,8 +802,12 @@ impl SharedState {
                 options: options.clone(),
                 result: None,
                 edition: 0,
+                completed_at: None,
+                lease: None,
+                verification: None,
             };
             log::debug!("enqueue: {:#?}", task);
+            self.persist_task(&task);
             rw.tasks.push(task);
         }
 
@@ -306,17 +824,22 @@ impl SharedState {
             log::error!("merge_tasks_from_peers failed with: {}", err);
             return;
         }
+        self.prune_tasks().await;
 
         let rw = self.rw.lock().await;
         if rw.pending.is_some() || rw.obtained {
             // already computing
             return;
         }
-        // find a pending task
+        // find a pending task without a valid lease of another node
+        let now = timestamp();
         let tasks: Vec<ProofRequestOptions> = rw
             .tasks
             .iter()
-            .filter(|&e| e.result.is_none())
+            .filter(|&e| {
+                e.result.is_none()
+                    && matches!(e.lease_owner(now), None | Some(owner) if owner == self.ro.node_id)
+            })
             .map(|e| e.options.clone())
             .collect();
         drop(rw);
@@ -324,28 +847,26 @@ impl SharedState {
         for task in tasks {
             // signals that this node wants to process this task
             log::debug!("trying to obtain {:#?}", task);
-            self.rw.lock().await.pending = Some(task);
-
-            // notify other peers
-            // wrap the object because it's important to clear `pending` on error
-            {
-                let self_copy = self.clone();
-                let obtain_task =
-                    tokio::spawn(
-                        async move { self_copy.obtain_task().await.expect("obtain_task") },
-                    )
-                    .await;
-
-                if obtain_task.is_err() || !obtain_task.unwrap() {
-                    self.rw.lock().await.pending = None;
-                    log::debug!("failed to obtain task");
-                    continue;
-                }
+            if !self.acquire_lease(&task).await {
+                continue;
+            }
+            self.rw.lock().await.pending = Some(task.clone());
 
-                // won the race
-                self.rw.lock().await.obtained = true;
-                break;
+            // concurrent claims of other nodes are resolved with `ProofRequest::supersedes`,
+            // check once for claims that happened in the meantime.
+            // Later conflicts abort the computation of the losing node.
+            if let Err(err) = self.merge_tasks_from_peers().await {
+                log::error!("merge_tasks_from_peers failed with: {}", err);
+            }
+            if self.is_obsolete(&task).await {
+                self.rw.lock().await.pending = None;
+                log::debug!("failed to obtain task");
+                continue;
             }
+
+            // won the race
+            self.rw.lock().await.obtained = true;
+            break;
         }
 
         // needs to be cloned because of long running tasks and
@@ -362,57 +883,54 @@ impl SharedState {
 
         // Note: this catches any panics for the task itself but will not help in the
         // situation when the process get itself OOM killed, stack overflows etc.
-        // This could be avoided by spawning a subprocess for the proof computation
-        // instead.
+        // Use `with_worker` to compute proofs inside a child process instead.
 
         // spawn a task to catch panics
         let task_result: Result<Result<Proofs, String>, tokio::task::JoinError> = {
             let task_options_copy = task_options.clone();
             let self_copy = self.clone();
 
-            tokio::spawn(async move {
-                let witness =
-                    CircuitWitness::from_rpc(&task_options_copy.block, &task_options_copy.rpc)
-                        .await
-                        .map_err(|e| e.to_string())?;
-
-                let (config, circuit_proof, aggregation_proof) = crate::match_circuit_params!(
-                    witness.gas_used(),
-                    {
-                        match task_options_copy.circuit.as_str() {
-                            "pi" => gen_proof!(
-                                self_copy,
-                                task_options_copy,
-                                &witness,
-                                public_input_circuit
-                            ),
-                            "super" => {
-                                gen_proof!(self_copy, task_options_copy, &witness, super_circuit)
-                            }
-                            "dummy" => {
-                                gen_proof!(self_copy, task_options_copy, &witness, dummy_circuit)
-                            }
-                            _ => panic!("unknown circuit"),
-                        }
-                    },
-                    {
-                        return Err(format!(
-                            "No circuit parameters found for block with gas used={}",
-                            witness.gas_used()
-                        ));
+            let mut handle = tokio::spawn(async move {
+                match self_copy.ro.worker.as_ref() {
+                    Some(worker) => worker.compute_proof(&task_options_copy).await,
+                    None => self_copy.compute_proof(&task_options_copy).await,
+                }
+            });
+
+            // renews the lease and interrupts the computation if the task gets cancelled,
+            // removed or taken over by another node in the meantime.
+            // Note: aborting kills the child process of a `Worker`. Otherwise it only takes
+            // effect at the next await point of the task, the blocking parts of the proof
+            // computation run to completion.
+            let mut heartbeat =
+                tokio::time::interval(Duration::from_secs(std::cmp::max(self.ro.lease_ttl / 3, 1)));
+            // registered up front to not miss notifications while the other branches run
+            let cancelled = self.ro.task_cancelled.notified();
+            tokio::pin!(cancelled);
+            cancelled.as_mut().enable();
+            loop {
+                let obsolete = tokio::select! {
+                    res = &mut handle => break res,
+                    _ = heartbeat.tick() => !self.renew_lease(&task_options).await,
+                    _ = &mut cancelled => {
+                        cancelled.set(self.ro.task_cancelled.notified());
+                        cancelled.as_mut().enable();
+                        self.is_obsolete(&task_options).await
                     }
-                );
-
-                let res = Proofs {
-                    config,
-                    circuit: circuit_proof,
-                    aggregation: aggregation_proof,
-                    gas: witness.gas_used(),
                 };
-
-                Ok(res)
-            })
-            .await
+                if obsolete {
+                    if self.ro.worker.is_none() {
+                        log::warn!(
+                            "unable to interrupt the computation without a worker, \
+                             the result will be discarded {:#?}",
+                            task_options
+                        );
+                    }
+                    log::info!("aborting {:#?}", task_options);
+                    handle.abort();
+                    break handle.await;
+                }
+            }
         };
 
         // convert the JoinError to string - if applicable
@@ -444,15 +962,19 @@ impl SharedState {
             rw.obtained = false;
             // insert task result
             let task = rw.tasks.iter_mut().find(|e| e.options == task_options);
-            if let Some(task) = task {
+            if let Some(task) = task.filter(|e| !e.is_cancelled()) {
                 // found our task, update result
                 task.result = Some(task_result);
                 task.edition += 1;
+                task.completed_at = Some(timestamp());
+                task.lease = None;
+                task.verification = None;
+                self.persist_task(task);
             } else {
-                // task was already removed in the meantime,
+                // task was already removed or cancelled in the meantime,
                 // assume it's obsolete and forget about it
                 log::info!(
-                    "task was already removed, ignoring result {:#?}",
+                    "task was already removed or cancelled, ignoring result {:#?}",
                     task_options
                 );
             }
@@ -472,29 +994,31 @@ impl SharedState {
     /// merges missing or updated tasks from these peers to
     /// preserve information in case individual nodes are going to be
     /// terminated.
+    /// Peers that fail to respond or to authenticate are skipped,
+    /// joined peers are forgotten in this case until they announce themselves again.
     ///
     /// Always returns `true` otherwise returns with error.
     pub async fn merge_tasks_from_peers(&self) -> Result<bool, String> {
         const LOG_TAG: &str = "merge_tasks_from_peers:";
 
-        if self.ro.node_lookup.is_none() {
-            return Ok(true);
-        }
-
-        let hyper_client = hyper::Client::new();
-        let addrs_iter = self
-            .ro
-            .node_lookup
-            .as_ref()
-            .unwrap()
-            .to_socket_addrs()
-            .map_err(|e| e.to_string())?;
-
-        for addr in addrs_iter {
+        for addr in self.peer_addrs().await? {
             let uri = Uri::try_from(format!("http://{}", addr)).map_err(|e| e.to_string())?;
-            let peer: NodeInformation =
-                jsonrpc_request_client(5000, &hyper_client, &uri, "info", serde_json::json!([]))
-                    .await?;
+            let req = sign_request(&self.ro.peer_secret, "info", &self.ro.node_id, None);
+            let peer = self
+                .ro
+                .http_client
+                .request::<_, SignedNodeInformation>(&uri, "info", [&req])
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|resp| verify_node_information(&self.ro.peer_secret, &req.nonce, &resp));
+            let peer = match peer {
+                Err(err) => {
+                    log::warn!("{} peer({}): {}", LOG_TAG, addr, err);
+                    self.rw.lock().await.joined_peers.remove(&addr);
+                    continue;
+                }
+                Ok(peer) => peer,
+            };
 
             if peer.id == self.ro.node_id {
                 log::debug!("{} skipping self({})", LOG_TAG, peer.id);
@@ -508,15 +1032,127 @@ impl SharedState {
         Ok(true)
     }
 
-    // TODO: can this be pre-generated to a file?
-    // related
-    // https://github.com/zcash/halo2/issues/443
-    // https://github.com/zcash/halo2/issues/449
+    /// Announces `advertise_addr` to the peers from `node_lookup` and `static_peers`
+    /// with `join`.
+    pub async fn announce(&self) -> Result<(), String> {
+        let addr = match &self.ro.advertise_addr {
+            None => return Ok(()),
+            Some(addr) => addr,
+        };
+        let joined_peers = self.rw.lock().await.joined_peers.clone();
+
+        for peer in self.peer_addrs().await? {
+            if peer == *addr || joined_peers.contains(&peer) {
+                continue;
+            }
+            let uri = Uri::try_from(format!("http://{}", peer)).map_err(|e| e.to_string())?;
+            let req = sign_request(
+                &self.ro.peer_secret,
+                "join",
+                &self.ro.node_id,
+                Some(addr.clone()),
+            );
+            if let Err(err) = self
+                .ro
+                .http_client
+                .request::<_, bool>(&uri, "join", [&req])
+                .await
+            {
+                log::warn!("announce: peer({}): {}", peer, err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles a `join` request, the node at `req.addr` becomes a peer of this node.
+    pub async fn join(&self, req: &PeerRequest) -> Result<bool, String> {
+        verify_request(&self.ro.peer_secret, "join", req)?;
+        let addr = req.addr.as_ref().ok_or("join: missing addr")?;
+        if Some(addr) == self.ro.advertise_addr.as_ref() {
+            return Ok(false);
+        }
+
+        let mut rw = self.rw.lock().await;
+        if rw.joined_peers.insert(addr.clone()) {
+            log::info!("join: peer({}) at {}", req.node_id, addr);
+        }
+
+        Ok(true)
+    }
+
+    /// Handles a `info` request by returning the signed `NodeInformation` of this node.
+    pub async fn signed_node_information(
+        &self,
+        req: &PeerRequest,
+    ) -> Result<SignedNodeInformation, String> {
+        verify_request(&self.ro.peer_secret, "info", req)?;
+
+        sign_node_information(
+            &self.ro.peer_secret,
+            &req.nonce,
+            &self.get_node_information().await,
+        )
+    }
+
+    /// Returns the deduplicated `HOST:PORT` addresses of `node_lookup`,
+    /// `static_peers` and the peers that joined.
+    async fn peer_addrs(&self) -> Result<Vec<String>, String> {
+        let mut addrs: Vec<String> = Vec::new();
+        if let Some(node_lookup) = &self.ro.node_lookup {
+            let addrs_iter = node_lookup.to_socket_addrs().map_err(|e| e.to_string())?;
+            addrs.extend(addrs_iter.map(|e| e.to_string()));
+        }
+        addrs.extend(self.ro.static_peers.iter().cloned());
+        addrs.extend(self.rw.lock().await.joined_peers.iter().cloned());
+
+        let mut ret: Vec<String> = Vec::with_capacity(addrs.len());
+        for addr in addrs {
+            if !ret.contains(&addr) {
+                ret.push(addr);
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Generates the proving key for the circuit `options.circuit` and the circuit parameters
+    /// for `gas_used` with a dummy witness. Persists the verifying key if `key_store` is set.
+    /// Returns the cache key.
+    pub async fn pregen_key(
+        &self,
+        options: &ProofRequestOptions,
+        gas_used: u64,
+    ) -> Result<String, String> {
+        let cache_key = crate::match_circuit_params!(
+            gas_used,
+            {
+                let witness = CircuitWitness::dummy(CIRCUIT_CONFIG)?;
+                match options.circuit.as_str() {
+                    "pi" => gen_key!(self, options, &witness, public_input_circuit),
+                    "super" => gen_key!(self, options, &witness, super_circuit),
+                    "dummy" => gen_key!(self, options, &witness, dummy_circuit),
+                    _ => return Err(format!("unknown circuit: {}", options.circuit)),
+                }
+            },
+            {
+                return Err(format!(
+                    "No circuit parameters found for block with gas used={}",
+                    gas_used
+                ));
+            }
+        );
+
+        Ok(cache_key)
+    }
+
     /// Compute or retrieve a proving key from cache.
+    /// If `key_store` is set, the verifying key is loaded from or written to it.
+    /// The proving key itself can not be stored, see `KeyStore`.
     async fn gen_pk<C: Circuit<Fr>>(
         &self,
         cache_key: &str,
-        param: &Arc<ProverParams>,
+        param: &LoadedParams,
         circuit: &C,
     ) -> Result<Arc<ProverKey>, Box<dyn std::error::Error>> {
         let mut rw = self.rw.lock().await;
@@ -524,8 +1160,8 @@ impl SharedState {
             // drop, potentially long running
             drop(rw);
 
-            let vk = keygen_vk(param.as_ref(), circuit)?;
-            let pk = keygen_pk(param.as_ref(), vk, circuit)?;
+            let vk = self.load_or_gen_vk(cache_key, param, circuit)?;
+            let pk = keygen_pk(param.params.as_ref(), vk, circuit)?;
             let pk = Arc::new(pk);
 
             // acquire lock and update
@@ -538,91 +1174,542 @@ impl SharedState {
         Ok(rw.pk_cache.get(cache_key).unwrap().clone())
     }
 
+    /// Retrieves the verifying key from the proving key cache or `key_store`, or computes it.
+    /// Unlike `gen_pk`, this does not generate the proving key and is meant for verification.
+    async fn gen_vk<C: Circuit<Fr>>(
+        &self,
+        cache_key: &str,
+        param: &LoadedParams,
+        circuit: &C,
+    ) -> Result<VerifyingKey<G1Affine>, Box<dyn std::error::Error>> {
+        if let Some(pk) = self.rw.lock().await.pk_cache.get(cache_key) {
+            return Ok(pk.get_vk().clone());
+        }
+
+        self.load_or_gen_vk(cache_key, param, circuit)
+    }
+
+    /// Loads the verifying key from `key_store` or computes and stores it.
+    fn load_or_gen_vk<C: Circuit<Fr>>(
+        &self,
+        cache_key: &str,
+        param: &LoadedParams,
+        circuit: &C,
+    ) -> Result<VerifyingKey<G1Affine>, Box<dyn std::error::Error>> {
+        let stored_vk = match &self.ro.key_store {
+            Some(key_store) => key_store.load_vk::<C>(cache_key, param)?,
+            None => None,
+        };
+        let vk = match stored_vk {
+            Some(vk) => vk,
+            None => {
+                let vk = keygen_vk(param.params.as_ref(), circuit)?;
+                if let Some(key_store) = &self.ro.key_store {
+                    key_store.save_vk(cache_key, param, &vk)?;
+                }
+                vk
+            }
+        };
+
+        Ok(vk)
+    }
+
     async fn merge_tasks(&self, node_info: &NodeInformation) {
         const LOG_TAG: &str = "merge_tasks:";
+
+        // collect the updates first, results are verified without holding the lock
+        let updates: Vec<&ProofRequest> = {
+            let rw = self.rw.lock().await;
+            node_info
+                .tasks
+                .iter()
+                .filter(|peer_task| {
+                    match rw.tasks.iter().find(|e| e.options == peer_task.options) {
+                        Some(existent_task) => {
+                            if !peer_task.supersedes(existent_task) {
+                                // fast case
+                                log::debug!("{} up to date {:#?}", LOG_TAG, existent_task);
+                                return false;
+                            }
+                            !existent_task.is_rejected(&node_info.id, peer_task.edition)
+                        }
+                        None => true,
+                    }
+                })
+                .collect()
+        };
+        let mut verified_updates = Vec::with_capacity(updates.len());
+        for peer_task in updates {
+            let verification = match &peer_task.result {
+                Some(Ok(proofs)) if self.ro.verify_results && !peer_task.options.mock => {
+                    if self
+                        .is_verification_deferred(&node_info.id, peer_task)
+                        .await
+                    {
+                        continue;
+                    }
+                    // potentially long running, keeps the runtime responsive
+                    let res = {
+                        let self_copy = self.clone();
+                        let options = peer_task.options.clone();
+                        let proofs = proofs.clone();
+                        tokio::task::spawn_blocking(move || {
+                            tokio::runtime::Handle::current()
+                                .block_on(self_copy.verify_proofs(&options, &proofs))
+                        })
+                        .await
+                        .unwrap_or_else(|err| Err(VerificationError::Unavailable(err.to_string())))
+                    };
+                    let error = match res {
+                        Ok(_) => None,
+                        Err(VerificationError::Invalid(err)) => {
+                            log::warn!(
+                                "{} rejecting result of peer({}): {} {:#?}",
+                                LOG_TAG,
+                                node_info.id,
+                                err,
+                                peer_task.options
+                            );
+                            Some(err)
+                        }
+                        Err(VerificationError::Unavailable(err)) => {
+                            // skipped, tried again after a while
+                            let delay = self.defer_verification(&node_info.id, peer_task).await;
+                            log::warn!(
+                                "{} unable to verify result of peer({}), retrying in {}s: {} {:#?}",
+                                LOG_TAG,
+                                node_info.id,
+                                delay,
+                                err,
+                                peer_task.options
+                            );
+                            continue;
+                        }
+                    };
+                    Some(ProofVerification {
+                        peer: node_info.id.clone(),
+                        edition: peer_task.edition,
+                        verified_at: timestamp(),
+                        error,
+                    })
+                }
+                _ => None,
+            };
+            verified_updates.push((peer_task, verification));
+        }
+
         let mut rw = self.rw.lock().await;
+        let pending = rw.pending.clone();
+        let mut pending_changed = false;
 
-        for peer_task in &node_info.tasks {
+        for (peer_task, verification) in verified_updates {
+            let rejected = matches!(&verification, Some(v) if v.error.is_some());
             let maybe_task = rw.tasks.iter_mut().find(|e| e.options == peer_task.options);
 
             if let Some(existent_task) = maybe_task {
-                if existent_task.edition >= peer_task.edition {
-                    // fast case
-                    log::debug!("{} up to date {:#?}", LOG_TAG, existent_task);
+                // may have changed in the meantime
+                if !peer_task.supersedes(existent_task) {
+                    continue;
+                }
+                if rejected {
+                    existent_task.verification = verification;
+                    self.persist_task(existent_task);
                     continue;
                 }
 
-                // update result, edition
+                // update result, lease, edition
                 existent_task.edition = peer_task.edition;
                 existent_task.result = peer_task.result.clone();
+                existent_task.lease = peer_task.lease.clone();
+                existent_task.completed_at = completed_at(peer_task);
+                existent_task.verification = verification;
+                self.persist_task(existent_task);
+                pending_changed |= pending.as_ref() == Some(&existent_task.options);
                 log::debug!("{} updated {:#?}", LOG_TAG, existent_task);
             } else {
-                // copy task
-                rw.tasks.push(peer_task.clone());
-                log::debug!("{} new task {:#?}", LOG_TAG, peer_task);
+                // copy task, without the result if it was rejected
+                let task = match rejected {
+                    true => ProofRequest {
+                        options: peer_task.options.clone(),
+                        result: None,
+                        edition: 0,
+                        completed_at: None,
+                        lease: None,
+                        verification,
+                    },
+                    false => ProofRequest {
+                        completed_at: completed_at(peer_task),
+                        verification,
+                        ..peer_task.clone()
+                    },
+                };
+                self.persist_task(&task);
+                log::debug!("{} new task {:#?}", LOG_TAG, task);
+                rw.tasks.push(task);
             }
         }
+
+        if pending_changed {
+            self.ro.task_cancelled.notify_waiters();
+        }
     }
 
-    /// Tries to obtain `self.rw.pending` by querying all other peers
-    /// about their current task item that resolves to either
-    /// winning or losing the task depending on the algorithm.
-    ///
-    /// Expects `self.rw.pending` to be not `None`
-    async fn obtain_task(&self) -> Result<bool, String> {
-        const LOG_TAG: &str = "obtain_task:";
+    /// Returns `true` if the result of `task` from `peer` could not be verified before
+    /// and the verification should not be retried yet.
+    async fn is_verification_deferred(&self, peer: &str, task: &ProofRequest) -> bool {
+        let now = timestamp();
+        let mut rw = self.rw.lock().await;
+        // forget about results that are not retried anymore
+        rw.unverified_results
+            .retain(|e| e.retry_at + MAX_VERIFICATION_BACKOFF > now);
+        rw.unverified_results.iter().any(|e| {
+            e.peer == peer
+                && e.edition == task.edition
+                && e.options == task.options
+                && e.retry_at > now
+        })
+    }
 
-        let task_options = self
-            .rw
-            .lock()
+    /// Defers the verification of the result of `task` from `peer` with an exponential backoff.
+    /// Returns the delay in seconds.
+    async fn defer_verification(&self, peer: &str, task: &ProofRequest) -> u64 {
+        let now = timestamp();
+        let mut rw = self.rw.lock().await;
+        let previous = rw
+            .unverified_results
+            .iter()
+            .position(|e| e.peer == peer && e.edition == task.edition && e.options == task.options);
+        let attempts = previous.map_or(0, |i| rw.unverified_results.remove(i).attempts) + 1;
+        let delay = std::cmp::min(
+            VERIFICATION_BACKOFF << std::cmp::min(attempts - 1, 16),
+            MAX_VERIFICATION_BACKOFF,
+        );
+        rw.unverified_results.push(UnverifiedResult {
+            peer: peer.to_string(),
+            options: task.options.clone(),
+            edition: task.edition,
+            attempts,
+            retry_at: now + delay,
+        });
+
+        delay
+    }
+
+    /// Verifies `proofs` of another node for `task_options` against the locally known
+    /// verifying keys and circuit parameters. The instance is derived from the block
+    /// of `task_options` to make sure that the proofs belong to this task.
+    /// Returns `VerificationError::Unavailable` if this node is not able to decide
+    /// about the validity of the proofs.
+    pub async fn verify_proofs(
+        &self,
+        task_options: &ProofRequestOptions,
+        proofs: &Proofs,
+    ) -> Result<(), VerificationError> {
+        let witnesses = fetch_witnesses(task_options)
             .await
-            .pending
-            .as_ref()
-            .expect("pending task")
-            .clone();
+            .map_err(VerificationError::Unavailable)?;
 
-        if self.ro.node_lookup.is_none() {
-            return Ok(true);
-        }
+        self.verify_proofs_with_witnesses(task_options, proofs, &witnesses)
+            .await
+    }
 
-        // resolve all other nodes for this service
-        let hyper_client = hyper::Client::new();
-        let addrs_iter = self
-            .ro
-            .node_lookup
-            .as_ref()
-            .unwrap()
-            .to_socket_addrs()
-            .map_err(|e| e.to_string())?;
-        for addr in addrs_iter {
-            let uri = Uri::try_from(format!("http://{}", addr)).map_err(|e| e.to_string())?;
-            let peer: NodeStatus =
-                jsonrpc_request_client(5000, &hyper_client, &uri, "status", serde_json::json!([]))
-                    .await?;
+    /// Verifies `proofs` for `task_options` against the witnesses of its blocks,
+    /// see `verify_proofs`.
+    pub async fn verify_proofs_with_witnesses(
+        &self,
+        task_options: &ProofRequestOptions,
+        proofs: &Proofs,
+        witnesses: &[(u64, CircuitWitness)],
+    ) -> Result<(), VerificationError> {
+        let gas = max_gas_used(witnesses);
+        if proofs.gas != gas {
+            return Err(VerificationError::Invalid(format!(
+                "expected gas={} got {}",
+                gas, proofs.gas
+            )));
+        }
+        let circuits: Vec<(&CircuitWitness, &ProofResult)> = match task_options.last_block {
+            None => vec![(&witnesses[0].1, &proofs.circuit)],
+            Some(_) => {
+                let blocks: Vec<u64> = proofs.blocks.iter().map(|e| e.block).collect();
+                if blocks != witnesses.iter().map(|e| e.0).collect::<Vec<u64>>() {
+                    return Err(VerificationError::Invalid(format!(
+                        "unexpected block proofs for blocks {:?}",
+                        blocks
+                    )));
+                }
+                witnesses
+                    .iter()
+                    .zip(&proofs.blocks)
+                    .map(|((_, witness), block_proof)| (witness, &block_proof.circuit))
+                    .collect()
+            }
+        };
 
-            if peer.id == self.ro.node_id {
-                log::debug!("{} skipping self({})", LOG_TAG, peer.id);
-                continue;
+        crate::match_circuit_params!(
+            gas,
+            {
+                match task_options.circuit.as_str() {
+                    "pi" => {
+                        verify_proofs!(self, task_options, proofs, circuits, public_input_circuit)
+                    }
+                    "super" => verify_proofs!(self, task_options, proofs, circuits, super_circuit),
+                    "dummy" => verify_proofs!(self, task_options, proofs, circuits, dummy_circuit),
+                    _ => {
+                        return Err(VerificationError::Unavailable(format!(
+                            "unknown circuit: {}",
+                            task_options.circuit
+                        )))
+                    }
+                }
+            },
+            {
+                return Err(VerificationError::Unavailable(format!(
+                    "No circuit parameters found for block with gas used={}",
+                    gas
+                )));
             }
+        );
 
-            if let Some(peer_task) = peer.task {
-                if peer_task == task_options {
-                    // a slight chance to 'win' the task
-                    if !peer.obtained && peer.id > self.ro.node_id {
-                        log::debug!("{} won task against {}", LOG_TAG, peer.id);
-                        // continue the race against the remaining peers
-                        continue;
+        Ok(())
+    }
+
+    /// Computes the proofs for `task_options` inside this process.
+    /// See `Worker` to compute proofs inside a child process instead.
+    pub async fn compute_proof(
+        &self,
+        task_options: &ProofRequestOptions,
+    ) -> Result<Proofs, String> {
+        let witnesses = fetch_witnesses(task_options).await?;
+
+        self.compute_proof_with_witnesses(task_options, witnesses)
+            .await
+    }
+
+    /// Computes the proofs for `task_options` from the witnesses of its blocks,
+    /// see `compute_proof`.
+    pub async fn compute_proof_with_witnesses(
+        &self,
+        task_options: &ProofRequestOptions,
+        mut witnesses: Vec<(u64, CircuitWitness)>,
+    ) -> Result<Proofs, String> {
+        if task_options.last_block.is_some() {
+            // the circuit parameters must cover all blocks
+            let gas = max_gas_used(&witnesses);
+            let witnesses = witnesses.iter().map(|(block, witness)| (*block, witness));
+            let (config, blocks, aggregation_proof) = crate::match_circuit_params!(
+                gas,
+                {
+                    match task_options.circuit.as_str() {
+                        "pi" => gen_aggregated_proof!(
+                            self,
+                            task_options,
+                            witnesses,
+                            public_input_circuit
+                        ),
+                        "super" => {
+                            gen_aggregated_proof!(self, task_options, witnesses, super_circuit)
+                        }
+                        "dummy" => {
+                            gen_aggregated_proof!(self, task_options, witnesses, dummy_circuit)
+                        }
+                        _ => return Err(format!("unknown circuit: {}", task_options.circuit)),
                     }
+                },
+                {
+                    return Err(format!(
+                        "No circuit parameters found for blocks with gas used={}",
+                        gas
+                    ));
+                }
+            );
+
+            return Ok(Proofs {
+                config,
+                circuit: ProofResult::default(),
+                aggregation: aggregation_proof,
+                gas,
+                blocks,
+            });
+        }
 
-                    log::debug!("{} lost task against {}", LOG_TAG, peer.id);
-                    // early return
-                    return Ok(false);
+        let (_, witness) = witnesses.remove(0);
+        let (config, circuit_proof, aggregation_proof) = crate::match_circuit_params!(
+            witness.gas_used(),
+            {
+                match task_options.circuit.as_str() {
+                    "pi" => gen_proof!(self, task_options, &witness, public_input_circuit),
+                    "super" => {
+                        gen_proof!(self, task_options, &witness, super_circuit)
+                    }
+                    "dummy" => {
+                        gen_proof!(self, task_options, &witness, dummy_circuit)
+                    }
+                    _ => panic!("unknown circuit"),
                 }
+            },
+            {
+                return Err(format!(
+                    "No circuit parameters found for block with gas used={}",
+                    witness.gas_used()
+                ));
             }
+        );
+
+        let res = Proofs {
+            config,
+            circuit: circuit_proof,
+            aggregation: aggregation_proof,
+            gas: witness.gas_used(),
+            blocks: Vec::new(),
+        };
+
+        Ok(res)
+    }
+
+    /// Cancels the task identified by `options`.
+    /// The task is kept with the `TASK_CANCELLED` error and a new edition
+    /// to propagate the cancellation to the peers with `merge_tasks`.
+    /// If this instance is working on the task, the computation is interrupted by killing
+    /// the child process of the `Worker`, see `with_worker`. Without a worker the proof is
+    /// computed in-process and can not be interrupted, it runs to completion and the result
+    /// is discarded.
+    /// Returns `false` if the task does not exist.
+    pub async fn cancel_task(&self, options: &ProofRequestOptions) -> bool {
+        let mut rw = self.rw.lock().await;
+        let task = match rw.tasks.iter_mut().find(|e| e.options == *options) {
+            None => return false,
+            Some(task) => task,
+        };
+
+        if !task.is_cancelled() {
+            log::info!("cancel: {:#?}", task.options);
+            task.result = Some(Err(TASK_CANCELLED.to_string()));
+            task.edition += 1;
+            task.completed_at = Some(timestamp());
+            task.lease = None;
+            self.persist_task(task);
         }
+        drop(rw);
+        self.ro.task_cancelled.notify_waiters();
 
-        // default
-        Ok(true)
+        true
+    }
+
+    /// Returns `true` if the task identified by `options` was removed, completed
+    /// or this node does not own its lease.
+    async fn is_obsolete(&self, options: &ProofRequestOptions) -> bool {
+        let now = timestamp();
+        self.rw
+            .lock()
+            .await
+            .tasks
+            .iter()
+            .find(|e| e.options == *options)
+            .map_or(true, |e| {
+                e.result.is_some() || e.lease_owner(now) != Some(self.ro.node_id.as_str())
+            })
+    }
+
+    /// Takes the lease of a pending task if it is not owned by another node.
+    /// Returns `false` otherwise.
+    async fn acquire_lease(&self, options: &ProofRequestOptions) -> bool {
+        let now = timestamp();
+        let mut rw = self.rw.lock().await;
+        let task = match rw.tasks.iter_mut().find(|e| e.options == *options) {
+            None => return false,
+            Some(task) => task,
+        };
+        if task.result.is_some()
+            || matches!(task.lease_owner(now), Some(owner) if owner != self.ro.node_id)
+        {
+            return false;
+        }
+
+        if let Some(lease) = &task.lease {
+            log::info!("reclaiming expired lease of {}", lease.owner);
+        }
+        task.lease = Some(TaskLease {
+            owner: self.ro.node_id.clone(),
+            expires_at: now + self.ro.lease_ttl,
+        });
+        task.edition += 1;
+        self.persist_task(task);
+
+        true
+    }
+
+    /// Extends the lease of a pending task owned by this node.
+    /// The edition is kept to not override concurrent changes of other nodes
+    /// like a cancellation, see `ProofRequest::supersedes`.
+    /// Returns `false` if the task was removed, completed or the lease was lost.
+    async fn renew_lease(&self, options: &ProofRequestOptions) -> bool {
+        let now = timestamp();
+        let mut rw = self.rw.lock().await;
+        let task = match rw.tasks.iter_mut().find(|e| e.options == *options) {
+            None => return false,
+            Some(task) => task,
+        };
+        if task.result.is_some() || task.lease_owner(now) != Some(self.ro.node_id.as_str()) {
+            return false;
+        }
+
+        task.lease = Some(TaskLease {
+            owner: self.ro.node_id.clone(),
+            expires_at: now + self.ro.lease_ttl,
+        });
+        self.persist_task(task);
+        log::debug!("renewed lease {:#?}", task.options);
+
+        true
+    }
+
+    /// Removes all tasks matching `predicate`, also from `task_store`.
+    pub async fn remove_tasks<F: Fn(&ProofRequest) -> bool>(&self, predicate: F) {
+        let mut rw = self.rw.lock().await;
+        let (removed, tasks): (Vec<ProofRequest>, Vec<ProofRequest>) =
+            rw.tasks.drain(..).partition(&predicate);
+        rw.tasks = tasks;
+        drop(rw);
+        self.ro.task_cancelled.notify_waiters();
+
+        if let Some(task_store) = &self.ro.task_store {
+            for task in removed {
+                if let Err(err) = task_store.remove(&task.options) {
+                    log::error!("TaskStore: remove failed with: {}", err);
+                }
+            }
+        }
+    }
+
+    /// Removes completed tasks according to `task_retention`.
+    pub async fn prune_tasks(&self) {
+        let now = timestamp();
+        let rw = self.rw.lock().await;
+        let expired: Vec<ProofRequestOptions> = self
+            .ro
+            .task_retention
+            .expired(&rw.tasks, now)
+            .into_iter()
+            .map(|e| e.options.clone())
+            .collect();
+        drop(rw);
+
+        if !expired.is_empty() {
+            log::info!("prune_tasks: removing {} completed tasks", expired.len());
+            self.remove_tasks(|e| e.result.is_some() && expired.contains(&e.options))
+                .await;
+        }
+    }
+
+    /// Writes `task` to `task_store`, if any.
+    /// Errors are only logged because the task is still kept in memory.
+    fn persist_task(&self, task: &ProofRequest) {
+        if let Some(task_store) = &self.ro.task_store {
+            if let Err(err) = task_store.save(task) {
+                log::error!("TaskStore: save failed with: {}", err);
+            }
+        }
     }
 
     pub fn random_worker_id() -> String {
@@ -630,11 +1717,51 @@ impl SharedState {
         const N: usize = 16;
         let mut arr = [0u8; N];
         thread_rng().fill(&mut arr[..]);
-        let mut node_id = String::with_capacity(N * 2);
-        for byte in arr {
-            write!(node_id, "{:02x}", byte).unwrap();
-        }
+        hex_encode(&arr)
+    }
+}
+
+/// Peers running an older version do not provide `completed_at`.
+fn completed_at(task: &ProofRequest) -> Option<u64> {
+    match task.result {
+        None => None,
+        Some(_) => task.completed_at.or_else(|| Some(timestamp())),
+    }
+}
 
-        node_id
+/// Fetches the witness of each block of `task_options`.
+async fn fetch_witnesses(
+    task_options: &ProofRequestOptions,
+) -> Result<Vec<(u64, CircuitWitness)>, String> {
+    let last_block = task_options.last_block.unwrap_or(task_options.block);
+    if last_block < task_options.block {
+        return Err(format!(
+            "last_block={} is lower than block={}",
+            last_block, task_options.block
+        ));
+    }
+    if task_options.last_block.is_some() && !task_options.aggregate {
+        return Err("proofs for multiple blocks require aggregate".to_string());
     }
+    if task_options.expose_instances && !task_options.aggregate {
+        return Err("expose_instances requires aggregate".to_string());
+    }
+
+    let mut witnesses = Vec::new();
+    for block in task_options.block..=last_block {
+        let witness = CircuitWitness::from_rpc(&block, &task_options.rpc)
+            .await
+            .map_err(|e| e.to_string())?;
+        witnesses.push((block, witness));
+    }
+
+    Ok(witnesses)
+}
+
+fn max_gas_used(witnesses: &[(u64, CircuitWitness)]) -> u64 {
+    witnesses
+        .iter()
+        .map(|(_, witness)| witness.gas_used())
+        .max()
+        .unwrap_or_default()
 }
@@ -0,0 +1,145 @@
+use crate::utils::write_file_atomic;
+use std::fs;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use zkevm_common::prover::*;
+
+/// Persists `ProofRequest`s, including their edition and result,
+/// across restarts of the node.
+pub trait TaskStore: Send + Sync {
+    /// Returns all stored tasks.
+    fn load(&self) -> Result<Vec<ProofRequest>, String>;
+    /// Inserts or replaces `task`.
+    fn save(&self, task: &ProofRequest) -> Result<(), String>;
+    /// Removes the task identified by `options`, if it exists.
+    fn remove(&self, options: &ProofRequestOptions) -> Result<(), String>;
+}
+
+/// A `TaskStore` that keeps one json file per task inside a local directory.
+pub struct DirTaskStore {
+    path: PathBuf,
+}
+
+impl DirTaskStore {
+    /// Opens or creates the directory at `path`.
+    pub fn open<P: Into<PathBuf>>(path: P) -> Result<Self, String> {
+        let path = path.into();
+        fs::create_dir_all(&path).map_err(|e| format!("{:?}: {}", path, e))?;
+
+        Ok(Self { path })
+    }
+
+    fn task_path(&self, options: &ProofRequestOptions) -> PathBuf {
+        // the fields that are compared by `ProofRequestOptions::eq`
+        let mut key = serde_json::json!([
+            options.block,
+            options.rpc,
+            options.param,
+            options.circuit,
+            options.mock,
+            options.aggregate,
+        ]);
+        // only if set to keep the paths of existing tasks
+        if let Some(last_block) = options.last_block {
+            key.as_array_mut().unwrap().push(last_block.into());
+        }
+        if options.expose_instances {
+            key.as_array_mut().unwrap().push(true.into());
+        }
+        self.path.join(format!(
+            "{}-{}-{:016x}.json",
+            options.block,
+            options.circuit,
+            fnv1a(key.to_string().as_bytes())
+        ))
+    }
+}
+
+impl TaskStore for DirTaskStore {
+    fn load(&self) -> Result<Vec<ProofRequest>, String> {
+        let mut tasks = Vec::new();
+        let entries = fs::read_dir(&self.path).map_err(|e| format!("{:?}: {}", self.path, e))?;
+
+        for entry in entries {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let file = File::open(&path).map_err(|e| format!("{:?}: {}", path, e))?;
+            match serde_json::from_reader::<_, ProofRequest>(BufReader::new(file)) {
+                Ok(task) => tasks.push(task),
+                // a partially written or outdated file should not prevent the node from starting
+                Err(err) => log::warn!("TaskStore: ignoring {:?}: {}", path, err),
+            }
+        }
+
+        Ok(tasks)
+    }
+
+    fn save(&self, task: &ProofRequest) -> Result<(), String> {
+        let path = self.task_path(&task.options);
+        let data = serde_json::to_vec(task).map_err(|e| e.to_string())?;
+
+        write_file_atomic(&path, &data)
+    }
+
+    fn remove(&self, options: &ProofRequestOptions) -> Result<(), String> {
+        let path = self.task_path(options);
+        match fs::remove_file(&path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("{:?}: {}", path, err))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Determines how long completed tasks are kept.
+/// Pending tasks are never pruned.
+#[derive(Clone, Debug, Default)]
+pub struct TaskRetention {
+    /// Removes completed tasks older than this amount of seconds.
+    pub max_age: Option<u64>,
+    /// Keeps at most this amount of completed tasks, the oldest are removed first.
+    pub max_completed: Option<usize>,
+}
+
+impl TaskRetention {
+    /// Returns the tasks that should be removed at unix timestamp `now`.
+    pub fn expired<'a>(&self, tasks: &'a [ProofRequest], now: u64) -> Vec<&'a ProofRequest> {
+        let mut completed: Vec<&ProofRequest> =
+            tasks.iter().filter(|e| e.result.is_some()).collect();
+        // newest first
+        completed.sort_by_key(|e| std::cmp::Reverse(e.completed_at.unwrap_or_default()));
+
+        completed
+            .into_iter()
+            .enumerate()
+            .filter(|(i, task)| {
+                let age = now.saturating_sub(task.completed_at.unwrap_or_default());
+                let too_old = matches!(self.max_age, Some(max_age) if age > max_age);
+                let too_many = matches!(self.max_completed, Some(max) if *i >= max);
+
+                too_old || too_many
+            })
+            .map(|(_, task)| task)
+            .collect()
+    }
+}
+
+/// Returns the current unix timestamp in seconds.
+pub fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("time")
+        .as_secs()
+}
+
+/// 64-bit FNV-1a, stable across builds unlike `DefaultHasher`.
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
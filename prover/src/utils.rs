@@ -6,9 +6,15 @@ use eth_types::U256;
 use halo2_proofs::dev::MockProver;
 use halo2_proofs::halo2curves::bn256::Fr;
 use halo2_proofs::plonk::create_proof;
+use halo2_proofs::plonk::verify_proof as verify_plonk_proof;
 use halo2_proofs::plonk::Circuit;
+use halo2_proofs::plonk::VerifyingKey;
 use halo2_proofs::poly::commitment::Params;
+use halo2_proofs::poly::commitment::ParamsProver;
 use halo2_proofs::poly::kzg::multiopen::ProverGWC;
+use halo2_proofs::poly::kzg::multiopen::VerifierGWC;
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::VerificationStrategy;
 use halo2_proofs::transcript::EncodedChallenge;
 use halo2_proofs::transcript::TranscriptReadBuffer;
 use halo2_proofs::transcript::TranscriptWriterBuffer;
@@ -18,7 +24,12 @@ use zkevm_circuits::tx_circuit::PrimeField;
 
 use rand::SeedableRng;
 use std::clone::Clone;
+use std::fmt::Write as _;
+use std::fs;
+use std::fs::File;
 use std::io::Cursor;
+use std::io::Write;
+use std::path::Path;
 
 /// Returns [<len>, ...] of `instance`
 pub fn gen_num_instance(instance: &[Vec<Fr>]) -> Vec<usize> {
@@ -66,11 +77,84 @@ pub fn gen_proof<
     transcript.finalize()
 }
 
+/// Verifies a transcript created with `gen_proof` against `vk` and `instance`.
+pub fn verify_proof<
+    E: EncodedChallenge<G1Affine>,
+    TR: TranscriptReadBuffer<Cursor<Vec<u8>>, G1Affine, E>,
+>(
+    params: &ProverParams,
+    vk: &VerifyingKey<G1Affine>,
+    instance: &[Vec<Fr>],
+    proof: &[u8],
+) -> Result<(), String> {
+    let mut transcript = TR::init(Cursor::new(proof.to_vec()));
+    let inputs: Vec<&[Fr]> = instance.iter().map(|v| v.as_slice()).collect();
+    let strategy = verify_plonk_proof::<ProverCommitmentScheme, VerifierGWC<_>, _, TR, _>(
+        params.verifier_params(),
+        vk,
+        SingleStrategy::new(params),
+        &[inputs.as_slice()],
+        &mut transcript,
+    )
+    .map_err(|e| format!("verify_proof: {:?}", e))?;
+
+    match strategy.finalize() {
+        true => Ok(()),
+        false => Err("verify_proof: invalid proof".to_string()),
+    }
+}
+
 /// Fixed rng for testing purposes
 pub fn fixed_rng() -> StdRng {
     StdRng::seed_from_u64(9)
 }
 
+/// Returns the lowercase hex encoding of `bytes`, without `0x` prefix.
+pub fn hex_encode(bytes: &[u8]) -> String {
+    let mut ret = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        write!(ret, "{:02x}", byte).unwrap();
+    }
+
+    ret
+}
+
+/// Writes `data` to `path` via a temporary file that is synced and renamed
+/// to not leave a truncated file behind.
+pub fn write_file_atomic(path: &Path, data: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    let mut file = File::create(&tmp).map_err(|e| format!("{:?}: {}", tmp, e))?;
+    file.write_all(data)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("{:?}: {}", tmp, e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("{:?}: {}", path, e))
+}
+
+/// Returns the label of an aggregation proof of `num_snarks` circuit proofs,
+/// which also identifies the matching verifier contract.
+/// E.g. `super-63000-a` for one block, `super-63000-a2` for two blocks
+/// and `super-63000-a2i` if the instances of the circuit proofs are exposed.
+pub fn aggregation_label(
+    circuit: &str,
+    block_gas_limit: usize,
+    num_snarks: usize,
+    expose_instances: bool,
+) -> String {
+    format!(
+        "{}-{}-a{}{}",
+        circuit,
+        block_gas_limit,
+        match num_snarks {
+            1 => String::new(),
+            n => n.to_string(),
+        },
+        match expose_instances {
+            true => "i",
+            false => "",
+        }
+    )
+}
+
 /// Collect circuit instance as flat vector
 pub fn collect_instance(instance: &[Vec<Fr>]) -> Vec<U256> {
     instance
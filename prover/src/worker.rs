@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::process::Child;
+use tokio::process::ChildStdin;
+use tokio::process::ChildStdout;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+use zkevm_common::prover::*;
+
+/// The argument that starts `prover_cmd` in worker mode.
+pub const WORKER_ARG: &str = "--worker";
+
+/// A running child process of a `Worker`.
+#[derive(Debug)]
+struct WorkerProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+/// Computes proofs inside a child process to protect the node from crashes
+/// (OOM, stack overflows, aborts) of the proof computation.
+///
+/// The child is started with `<cmd> --worker` and kept running for subsequent requests,
+/// so that caches like the circuit parameters and proving keys stay warm.
+/// Each request is a line with the `ProofRequestOptions` as json on stdin,
+/// answered by a line with the `Result<Proofs, String>` as json on stdout.
+/// stderr is inherited for logging.
+/// The child is started again for the next request if it crashed or got killed.
+#[derive(Debug)]
+pub struct Worker {
+    /// Path of the `prover_cmd` binary.
+    pub cmd: PathBuf,
+    /// Limits the virtual memory of the child process to this amount of bytes.
+    pub memory_limit: Option<u64>,
+    /// Environment variables for the child process, in addition to the inherited ones.
+    pub envs: Vec<(String, String)>,
+    process: Mutex<Option<WorkerProcess>>,
+}
+
+impl Worker {
+    pub fn new<P: Into<PathBuf>>(cmd: P) -> Self {
+        Self {
+            cmd: cmd.into(),
+            memory_limit: None,
+            envs: Vec::new(),
+            process: Mutex::new(None),
+        }
+    }
+
+    pub fn with_memory_limit(mut self, memory_limit: Option<u64>) -> Self {
+        self.memory_limit = memory_limit;
+        self
+    }
+
+    pub fn with_env(mut self, key: &str, val: &str) -> Self {
+        self.envs.push((key.to_string(), val.to_string()));
+        self
+    }
+
+    /// Computes the proofs for `options` inside the child process.
+    /// Returns an error if the child exits or returns invalid output.
+    /// The child gets killed if the returned future is dropped.
+    pub async fn compute_proof(&self, options: &ProofRequestOptions) -> Result<Proofs, String> {
+        let mut process_lock = self.process.lock().await;
+        // taken out for the duration of the request to kill the child if the future is dropped
+        let mut process = match process_lock.take() {
+            Some(mut process) => match process.child.try_wait() {
+                Ok(None) => process,
+                _ => self.spawn()?,
+            },
+            None => self.spawn()?,
+        };
+
+        let mut input = serde_json::to_vec(options).map_err(|e| e.to_string())?;
+        input.push(b'\n');
+        // the exit status and output decide about the result if the child does not read stdin
+        if let Err(err) = process.stdin.write_all(&input).await {
+            log::warn!("Worker: writing stdin: {}", err);
+        }
+        if let Err(err) = process.stdin.flush().await {
+            log::warn!("Worker: flushing stdin: {}", err);
+        }
+
+        let mut output = String::new();
+        let len = process
+            .stdout
+            .read_line(&mut output)
+            .await
+            .map_err(|e| e.to_string())?;
+        if len == 0 {
+            let status = process.child.wait().await.map_err(|e| e.to_string())?;
+            log::info!("Worker: {:?} {}", self.cmd, status);
+            return Err(format!("worker crashed: {}", status));
+        }
+
+        let result = serde_json::from_str::<Result<Proofs, String>>(&output)
+            .map_err(|e| format!("worker: invalid output: {}", e))?;
+        // keep the child for the next request
+        *process_lock = Some(process);
+
+        result
+    }
+
+    fn spawn(&self) -> Result<WorkerProcess, String> {
+        let mut cmd = match self.memory_limit {
+            // `ulimit -v` expects KiB
+            Some(limit) => {
+                let mut cmd = Command::new("sh");
+                cmd.arg("-c")
+                    .arg(format!("ulimit -v {} && exec \"$0\" \"$@\"", limit / 1024))
+                    .arg(&self.cmd);
+                cmd
+            }
+            None => Command::new(&self.cmd),
+        };
+        let mut child = cmd
+            .arg(WORKER_ARG)
+            .envs(self.envs.iter().cloned())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("{:?}: {}", self.cmd, e))?;
+        log::info!("Worker: started {:?} pid={:?}", self.cmd, child.id());
+
+        let stdin = child.stdin.take().expect("stdin");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout"));
+
+        Ok(WorkerProcess {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+}
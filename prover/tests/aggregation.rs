@@ -0,0 +1,85 @@
+use halo2_proofs::plonk::keygen_pk;
+use halo2_proofs::plonk::keygen_vk;
+use plonk_verifier::loader::native::NativeLoader;
+use plonk_verifier::system::halo2::{compile, Config};
+use prover::aggregation_circuit::AggregationCircuit;
+use prover::aggregation_circuit::PoseidonTranscript;
+use prover::aggregation_circuit::Snark;
+use prover::circuit_witness::CircuitWitness;
+use prover::dummy_circuit;
+use prover::utils::aggregation_label;
+use prover::utils::fixed_rng;
+use prover::utils::gen_num_instance;
+use prover::utils::gen_proof;
+use prover::ProverParams;
+use zkevm_common::prover::*;
+
+#[test]
+fn aggregation_label_blocks() {
+    assert_eq!(aggregation_label("super", 63000, 1, false), "super-63000-a");
+    assert_eq!(aggregation_label("super", 63000, 1, true), "super-63000-ai");
+    assert_eq!(
+        aggregation_label("super", 63000, 2, false),
+        "super-63000-a2"
+    );
+    assert_eq!(
+        aggregation_label("super", 63000, 2, true),
+        "super-63000-a2i"
+    );
+}
+
+#[test]
+fn aggregation_exposed_instances() {
+    prover::match_circuit_params!(
+        0,
+        {
+            let witness = CircuitWitness::dummy(CIRCUIT_CONFIG).expect("witness");
+            let circuit = dummy_circuit::gen_circuit::<
+                { CIRCUIT_CONFIG.max_txs },
+                { CIRCUIT_CONFIG.max_calldata },
+                { CIRCUIT_CONFIG.max_rws },
+                _,
+            >(&witness, fixed_rng())
+            .expect("circuit");
+            let instance = circuit.instance();
+
+            let params = ProverParams::setup(CIRCUIT_CONFIG.min_k as u32, fixed_rng());
+            let vk = keygen_vk(&params, &circuit).expect("vk");
+            let pk = keygen_pk(&params, vk, &circuit).expect("pk");
+            let proof = gen_proof::<
+                _,
+                _,
+                PoseidonTranscript<NativeLoader, _>,
+                PoseidonTranscript<NativeLoader, _>,
+                _,
+            >(&params, &pk, circuit, instance.clone(), fixed_rng(), false);
+            let protocol = compile(
+                &params,
+                pk.get_vk(),
+                Config::kzg().with_num_instance(gen_num_instance(&instance)),
+            );
+            let snark = Snark::new(protocol, instance.clone(), proof);
+
+            // two blocks, the params of the circuit suffice to compute the accumulator
+            let agg_circuit = AggregationCircuit::new(&params, vec![snark; 2], fixed_rng());
+            let accumulator = agg_circuit.instance();
+            assert_eq!(accumulator[0].len(), AGGREGATION_ACCUMULATOR_LEN);
+
+            // the instances of the blocks follow the accumulator limbs in order
+            let agg_circuit = agg_circuit.with_exposed_instances(true);
+            let agg_instance = agg_circuit.instance();
+            assert_eq!(agg_circuit.num_instance(), vec![agg_instance[0].len()]);
+            assert_eq!(
+                agg_instance[0][..AGGREGATION_ACCUMULATOR_LEN],
+                accumulator[0]
+            );
+            assert_eq!(
+                agg_instance[0][AGGREGATION_ACCUMULATOR_LEN..],
+                [instance.concat(), instance.concat()].concat()
+            );
+        },
+        {
+            unreachable!();
+        }
+    );
+}
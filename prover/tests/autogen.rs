@@ -501,10 +501,14 @@ fn autogen_circuit_config() {
         estimate_all!(max_unused_gas, gen_bytecode_mload, callback);
     }
 
+    // the generated code expects the parameters ordered by `block_gas_limit`
+    let mut params: Vec<CircuitConfig> = params.into_values().collect();
+    params.sort_by_key(|config| config.block_gas_limit);
+
     // generate `circuit_autogen.rs`
     let mut prev_gas = 0;
     let mut str = String::new();
-    for config in params.values() {
+    for config in params.iter() {
         write!(
             str,
             "{}..={} => {{
@@ -537,8 +541,31 @@ macro_rules! match_circuit_params {{
         .write_all(str.as_bytes())
         .expect("write circuit_autogen.rs");
 
+    // generate the circuit parameters table for `zkevm_common`, formatted like `cargo fmt`
+    let mut str = String::new();
+    for config in params.iter() {
+        for line in format!("{:#?},", config).lines() {
+            writeln!(str, "    {}", line).expect("fmt write");
+        }
+    }
+    let str = format!(
+        "use crate::prover::CircuitConfig;
+
+/// The circuit parameters ordered by `block_gas_limit`.
+pub const CIRCUIT_CONFIGS: [CircuitConfig; {}] = [
+{}];
+",
+        params.len(),
+        str
+    );
+
+    File::create("../common/src/circuit_autogen.rs")
+        .expect("create common/src/circuit_autogen.rs")
+        .write_all(str.as_bytes())
+        .expect("write common/src/circuit_autogen.rs");
+
     let mut str = String::new();
-    for config in params.values() {
+    for config in params.iter() {
         write!(
             str,
             "
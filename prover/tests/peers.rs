@@ -0,0 +1,43 @@
+use prover::peers::*;
+use zkevm_common::prover::*;
+
+#[test]
+fn peer_request() {
+    let req = sign_request("secret", "join", "a", Some("127.0.0.1:1234".to_string()));
+    assert!(verify_request("secret", "join", &req).is_ok());
+    // wrong secret or method
+    assert!(verify_request("wrong", "join", &req).is_err());
+    assert!(verify_request("secret", "info", &req).is_err());
+
+    // tampered
+    let mut tampered = req.clone();
+    tampered.addr = Some("127.0.0.1:4321".to_string());
+    assert!(verify_request("secret", "join", &tampered).is_err());
+    let mut tampered = req.clone();
+    tampered.signature = "zz".repeat(32);
+    assert!(verify_request("secret", "join", &tampered).is_err());
+
+    // expired
+    let mut req = sign_request("secret", "info", "a", None);
+    req.timestamp -= MAX_CLOCK_SKEW + 1;
+    assert!(verify_request("secret", "info", &req).is_err());
+}
+
+#[test]
+fn peer_node_information() {
+    let info = NodeInformation {
+        id: "a".to_string(),
+        tasks: vec![],
+    };
+    let signed = sign_node_information("secret", "nonce", &info).expect("sign");
+    let verified = verify_node_information("secret", "nonce", &signed).expect("verify");
+    assert_eq!(verified.id, info.id);
+
+    // replayed for another request
+    assert!(verify_node_information("secret", "other", &signed).is_err());
+    assert!(verify_node_information("wrong", "nonce", &signed).is_err());
+
+    let mut tampered = signed;
+    tampered.payload = tampered.payload.replace("\"a\"", "\"b\"");
+    assert!(verify_node_information("secret", "nonce", &tampered).is_err());
+}
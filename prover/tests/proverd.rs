@@ -1,5 +1,7 @@
+use prover::circuit_witness::CircuitWitness;
 use prover::server::serve;
 use prover::shared_state::SharedState;
+use prover::shared_state::VerificationError;
 use tokio::time::{sleep, Duration};
 use zkevm_common::prover::*;
 
@@ -13,8 +15,10 @@ fn init_logger() {
 async fn proverd_simple_signaling() {
     init_logger();
 
-    let node_a = SharedState::new("a".to_string(), Some("127.0.0.1:11111".to_string()));
-    let node_b = SharedState::new("b".to_string(), Some("127.0.0.1:11112".to_string()));
+    let node_a = SharedState::new("a".to_string(), Some("127.0.0.1:11111".to_string()))
+        .with_peer_secret("secret".to_string());
+    let node_b = SharedState::new("b".to_string(), Some("127.0.0.1:11112".to_string()))
+        .with_peer_secret("secret".to_string());
     // start http servers
     {
         let _ = serve(&node_a, node_b.ro.node_lookup.as_ref().unwrap());
@@ -68,3 +72,418 @@ async fn proverd_simple_signaling() {
     // check again
     assert!(node_a.get_or_enqueue(&proof_b).await.is_some());
 }
+
+#[tokio::test]
+async fn proverd_cancel() {
+    init_logger();
+
+    let node_a = SharedState::new("a".to_string(), Some("127.0.0.1:11113".to_string()))
+        .with_peer_secret("secret".to_string());
+    let node_b = SharedState::new("b".to_string(), Some("127.0.0.1:11114".to_string()))
+        .with_peer_secret("secret".to_string());
+    // start http servers
+    {
+        let _ = serve(&node_a, node_b.ro.node_lookup.as_ref().unwrap());
+        let _ = serve(&node_b, node_a.ro.node_lookup.as_ref().unwrap());
+    }
+
+    // wait a bit for the rpc server to start
+    sleep(Duration::from_millis(300)).await;
+
+    let proof = ProofRequestOptions {
+        circuit: "super".to_string(),
+        block: 1,
+        retry: false,
+        rpc: "http://localhost:1111".to_string(),
+        ..Default::default()
+    };
+
+    // unknown task
+    assert!(!node_a.cancel_task(&proof).await);
+
+    // enqueue on both nodes
+    assert!(node_a.get_or_enqueue(&proof).await.is_none());
+    assert!(node_b.get_or_enqueue(&proof).await.is_none());
+    assert!(node_a.cancel_task(&proof).await);
+    assert!(matches!(
+        node_a.get_or_enqueue(&proof).await,
+        Some(Err(err)) if err == TASK_CANCELLED
+    ));
+
+    // node_b picks up the cancellation and does not work on the task anymore
+    let _ = node_b.merge_tasks_from_peers().await;
+    assert!(matches!(
+        node_b.get_or_enqueue(&proof).await,
+        Some(Err(err)) if err == TASK_CANCELLED
+    ));
+    node_b.duty_cycle().await;
+    assert!(matches!(
+        node_b.get_or_enqueue(&proof).await,
+        Some(Err(err)) if err == TASK_CANCELLED
+    ));
+}
+
+#[tokio::test]
+async fn proverd_lease() {
+    init_logger();
+
+    let node_a = SharedState::new("a".to_string(), Some("127.0.0.1:11115".to_string()))
+        .with_peer_secret("secret".to_string())
+        .with_lease_ttl(1);
+    let node_b = SharedState::new("b".to_string(), Some("127.0.0.1:11116".to_string()))
+        .with_peer_secret("secret".to_string())
+        .with_lease_ttl(1);
+    // start http servers
+    {
+        let _ = serve(&node_a, node_b.ro.node_lookup.as_ref().unwrap());
+        let _ = serve(&node_b, node_a.ro.node_lookup.as_ref().unwrap());
+    }
+
+    // wait a bit for the rpc server to start
+    sleep(Duration::from_millis(300)).await;
+
+    let proof = ProofRequestOptions {
+        circuit: "super".to_string(),
+        block: 1,
+        retry: false,
+        rpc: "http://localhost:1111".to_string(),
+        ..Default::default()
+    };
+
+    // a node that died while working on the task
+    assert!(node_a.get_or_enqueue(&proof).await.is_none());
+    {
+        let mut rw = node_a.rw.lock().await;
+        let task = &mut rw.tasks[0];
+        task.lease = Some(TaskLease {
+            owner: "c".to_string(),
+            expires_at: prover::task_store::timestamp() + 2,
+        });
+        task.edition += 1;
+    }
+
+    // the lease is still valid
+    node_b.duty_cycle().await;
+    assert!(node_b.get_or_enqueue(&proof).await.is_none());
+    assert!(node_b.rw.lock().await.pending.is_none());
+
+    // and gets reclaimed after expiry
+    sleep(Duration::from_millis(2500)).await;
+    node_b.duty_cycle().await;
+    assert!(node_b.get_or_enqueue(&proof).await.is_some());
+    let _ = node_a.merge_tasks_from_peers().await;
+    let info = node_a.get_node_information().await;
+    assert!(info.tasks[0].result.is_some());
+    assert!(info.tasks[0].lease.is_none());
+}
+
+#[test]
+fn proverd_lease_conflict() {
+    let task = |edition: u64, owner: Option<&str>, result: bool| ProofRequest {
+        options: ProofRequestOptions::default(),
+        result: result.then(|| Err("failed".to_string())),
+        edition,
+        completed_at: None,
+        lease: owner.map(|owner| TaskLease {
+            owner: owner.to_string(),
+            expires_at: 0,
+        }),
+        verification: None,
+    };
+    let renewed = |task: ProofRequest| ProofRequest {
+        lease: task.lease.map(|lease| TaskLease {
+            expires_at: lease.expires_at + 1,
+            ..lease
+        }),
+        ..task
+    };
+
+    assert!(task(2, Some("b"), false).supersedes(&task(1, Some("a"), false)));
+    assert!(!task(1, Some("a"), false).supersedes(&task(2, Some("b"), false)));
+    // concurrent claims, the lowest node id wins
+    assert!(task(1, Some("a"), false).supersedes(&task(1, Some("b"), false)));
+    assert!(!task(1, Some("b"), false).supersedes(&task(1, Some("a"), false)));
+    assert!(task(1, Some("b"), false).supersedes(&task(1, None, false)));
+    // results win over leases
+    assert!(task(1, None, true).supersedes(&task(1, Some("a"), false)));
+    assert!(!task(1, Some("a"), false).supersedes(&task(1, None, true)));
+    assert!(!task(1, None, true).supersedes(&task(1, None, true)));
+    // renewals keep the edition
+    assert!(renewed(task(1, Some("a"), false)).supersedes(&task(1, Some("a"), false)));
+    assert!(!task(1, Some("a"), false).supersedes(&renewed(task(1, Some("a"), false))));
+    assert!(!renewed(task(1, Some("b"), false)).supersedes(&task(1, Some("a"), false)));
+    assert!(!renewed(task(1, Some("a"), false)).supersedes(&task(2, None, true)));
+}
+
+#[tokio::test]
+async fn proverd_cancel_lease() {
+    init_logger();
+
+    let node_a = SharedState::new("a".to_string(), Some("127.0.0.1:11122".to_string()))
+        .with_peer_secret("secret".to_string());
+    let node_b = SharedState::new("b".to_string(), Some("127.0.0.1:11123".to_string()))
+        .with_peer_secret("secret".to_string());
+    // start http servers
+    {
+        let _ = serve(&node_a, node_b.ro.node_lookup.as_ref().unwrap());
+        let _ = serve(&node_b, node_a.ro.node_lookup.as_ref().unwrap());
+    }
+
+    // wait a bit for the rpc server to start
+    sleep(Duration::from_millis(300)).await;
+
+    let proof = ProofRequestOptions {
+        circuit: "super".to_string(),
+        block: 1,
+        retry: false,
+        rpc: "http://localhost:1111".to_string(),
+        ..Default::default()
+    };
+
+    // node_a works on the task
+    assert!(node_a.get_or_enqueue(&proof).await.is_none());
+    {
+        let mut rw = node_a.rw.lock().await;
+        let task = &mut rw.tasks[0];
+        task.lease = Some(TaskLease {
+            owner: "a".to_string(),
+            expires_at: prover::task_store::timestamp() + 60,
+        });
+        task.edition += 1;
+    }
+    node_b.merge_tasks_from_peers().await.expect("merge");
+
+    // cancelled by node_b
+    assert!(node_b.cancel_task(&proof).await);
+
+    // node_a renews its lease before it merges the cancellation
+    {
+        let mut rw = node_a.rw.lock().await;
+        let lease = rw.tasks[0].lease.as_mut().expect("lease");
+        lease.expires_at += 60;
+    }
+    node_b.merge_tasks_from_peers().await.expect("merge");
+    assert!(matches!(
+        node_b.get_or_enqueue(&proof).await,
+        Some(Err(err)) if err == TASK_CANCELLED
+    ));
+    node_a.merge_tasks_from_peers().await.expect("merge");
+    assert!(matches!(
+        node_a.get_or_enqueue(&proof).await,
+        Some(Err(err)) if err == TASK_CANCELLED
+    ));
+}
+
+#[tokio::test]
+async fn proverd_peers() {
+    init_logger();
+
+    let node_a = SharedState::new("a".to_string(), None).with_peer_secret("secret".to_string());
+    let node_b = SharedState::new("b".to_string(), None)
+        .with_peer_secret("secret".to_string())
+        .with_static_peers(vec!["127.0.0.1:11117".to_string()])
+        .with_advertise_addr(Some("127.0.0.1:11118".to_string()));
+    // does not know the secret
+    let node_c = SharedState::new("c".to_string(), None)
+        .with_peer_secret("wrong".to_string())
+        .with_static_peers(vec!["127.0.0.1:11117".to_string()])
+        .with_advertise_addr(Some("127.0.0.1:11119".to_string()));
+    // start http servers
+    {
+        let _ = serve(&node_a, "127.0.0.1:11117");
+        let _ = serve(&node_b, "127.0.0.1:11118");
+        let _ = serve(&node_c, "127.0.0.1:11119");
+    }
+
+    // wait a bit for the rpc server to start
+    sleep(Duration::from_millis(300)).await;
+
+    let proof = |block: u64| ProofRequestOptions {
+        circuit: "super".to_string(),
+        block,
+        retry: false,
+        rpc: "http://localhost:1111".to_string(),
+        ..Default::default()
+    };
+    assert!(node_a.get_or_enqueue(&proof(1)).await.is_none());
+    assert!(node_b.get_or_enqueue(&proof(2)).await.is_none());
+    assert!(node_c.get_or_enqueue(&proof(3)).await.is_none());
+
+    // only node_b is able to join
+    node_b.announce().await.expect("announce");
+    node_c.announce().await.expect("announce");
+    let joined_peers = node_a.rw.lock().await.joined_peers.clone();
+    assert_eq!(joined_peers.len(), 1);
+    assert!(joined_peers.contains("127.0.0.1:11118"));
+
+    // static peer
+    node_b.merge_tasks_from_peers().await.expect("merge");
+    // joined peer
+    node_a.merge_tasks_from_peers().await.expect("merge");
+    // rejected by node_a
+    node_c.merge_tasks_from_peers().await.expect("merge");
+
+    let blocks = |info: NodeInformation| {
+        let mut blocks: Vec<u64> = info.tasks.iter().map(|e| e.options.block).collect();
+        blocks.sort_unstable();
+        blocks
+    };
+    assert_eq!(blocks(node_a.get_node_information().await), vec![1, 2]);
+    assert_eq!(blocks(node_b.get_node_information().await), vec![1, 2]);
+    assert_eq!(blocks(node_c.get_node_information().await), vec![3]);
+}
+
+#[tokio::test]
+async fn proverd_verify_results() {
+    init_logger();
+
+    let node_a = SharedState::new("a".to_string(), Some("127.0.0.1:11120".to_string()))
+        .with_peer_secret("secret".to_string());
+    let node_b = SharedState::new("b".to_string(), Some("127.0.0.1:11121".to_string()))
+        .with_peer_secret("secret".to_string());
+    let node_c = SharedState::new("c".to_string(), Some("127.0.0.1:11121".to_string()))
+        .with_peer_secret("secret".to_string())
+        .with_result_verification(false);
+    // start http servers
+    {
+        let _ = serve(&node_a, node_b.ro.node_lookup.as_ref().unwrap());
+        let _ = serve(&node_b, node_a.ro.node_lookup.as_ref().unwrap());
+    }
+
+    // wait a bit for the rpc server to start
+    sleep(Duration::from_millis(300)).await;
+
+    let proof = ProofRequestOptions {
+        circuit: "super".to_string(),
+        block: 1,
+        retry: false,
+        rpc: "http://localhost:1111".to_string(),
+        ..Default::default()
+    };
+
+    // garbage proofs on node_a
+    assert!(node_a.get_or_enqueue(&proof).await.is_none());
+    {
+        let mut rw = node_a.rw.lock().await;
+        let task = &mut rw.tasks[0];
+        task.result = Some(Ok(Proofs::default()));
+        task.edition += 1;
+    }
+
+    // node_b is unable to verify without the block rpc,
+    // the update is skipped and retried later instead of being rejected
+    node_b.merge_tasks_from_peers().await.expect("merge");
+    let info = node_b.get_node_information().await;
+    assert!(info.tasks.is_empty());
+
+    // and not retried before the backoff expired
+    node_b.merge_tasks_from_peers().await.expect("merge");
+    {
+        let rw = node_b.rw.lock().await;
+        assert_eq!(rw.unverified_results.len(), 1);
+        assert_eq!(rw.unverified_results[0].peer, "a");
+        assert_eq!(rw.unverified_results[0].attempts, 1);
+    }
+
+    // accepted without verification
+    node_c.merge_tasks_from_peers().await.expect("merge");
+    let info = node_c.get_node_information().await;
+    assert!(matches!(info.tasks[0].result, Some(Ok(_))));
+    assert!(info.tasks[0].verification.is_none());
+
+    // real proofs of the dummy circuit
+    let witness = || {
+        prover::match_circuit_params!(
+            0,
+            { CircuitWitness::dummy(CIRCUIT_CONFIG).expect("witness") },
+            { unreachable!() }
+        )
+    };
+    let proof = ProofRequestOptions {
+        circuit: "dummy".to_string(),
+        ..proof
+    };
+    let node_d = SharedState::new("d".to_string(), None).with_params_setup(true);
+    let mut proofs = node_d
+        .compute_proof_with_witnesses(&proof, vec![(1, witness())])
+        .await
+        .expect("proofs");
+    node_d
+        .verify_proofs_with_witnesses(&proof, &proofs, &[(1, witness())])
+        .await
+        .expect("valid proofs");
+
+    // tampered proof
+    let mut tampered = proofs.circuit.proof.to_vec();
+    let len = tampered.len();
+    tampered[len / 2] ^= 1;
+    proofs.circuit.proof = tampered.into();
+    let err = node_d
+        .verify_proofs_with_witnesses(&proof, &proofs, &[(1, witness())])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, VerificationError::Invalid(_)), "{}", err);
+
+    // no circuit parameters
+    let err = node_a
+        .verify_proofs_with_witnesses(&proof, &proofs, &[(1, witness())])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, VerificationError::Unavailable(_)), "{}", err);
+}
+
+#[tokio::test]
+async fn proverd_multi_block_options() {
+    let node = SharedState::new("a".to_string(), None);
+    let proof = ProofRequestOptions {
+        circuit: "super".to_string(),
+        block: 2,
+        rpc: "http://localhost:1111".to_string(),
+        aggregate: true,
+        last_block: Some(1),
+        ..Default::default()
+    };
+    let err = node.compute_proof(&proof).await.unwrap_err();
+    assert!(err.contains("last_block"));
+
+    let proof = ProofRequestOptions {
+        aggregate: false,
+        last_block: Some(3),
+        ..proof
+    };
+    let err = node.compute_proof(&proof).await.unwrap_err();
+    assert!(err.contains("aggregate"));
+
+    // a range is a different task than its first block
+    let single = ProofRequestOptions {
+        last_block: None,
+        ..proof.clone()
+    };
+    assert!(proof != single);
+}
+
+#[tokio::test]
+async fn proverd_expose_instances_options() {
+    let node = SharedState::new("a".to_string(), None);
+    let proof = ProofRequestOptions {
+        circuit: "super".to_string(),
+        block: 1,
+        rpc: "http://localhost:1111".to_string(),
+        aggregate: false,
+        expose_instances: true,
+        ..Default::default()
+    };
+    let err = node.compute_proof(&proof).await.unwrap_err();
+    assert!(err.contains("expose_instances"));
+
+    // the aggregation circuit differs, so does the task
+    let aggregated = ProofRequestOptions {
+        aggregate: true,
+        ..proof.clone()
+    };
+    let unexposed = ProofRequestOptions {
+        expose_instances: false,
+        ..aggregated.clone()
+    };
+    assert!(aggregated != unexposed);
+}
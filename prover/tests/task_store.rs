@@ -0,0 +1,171 @@
+use prover::shared_state::SharedState;
+use prover::task_store::*;
+use std::sync::Arc;
+use zkevm_common::prover::*;
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "proverd-{}-{}",
+        name,
+        SharedState::random_worker_id()
+    ));
+    let _ = std::fs::remove_dir_all(&path);
+
+    path
+}
+
+fn task(block: u64, result: Option<Result<Proofs, String>>, completed_at: u64) -> ProofRequest {
+    ProofRequest {
+        options: ProofRequestOptions {
+            circuit: "super".to_string(),
+            block,
+            rpc: "http://localhost:1111".to_string(),
+            ..Default::default()
+        },
+        completed_at: result.as_ref().map(|_| completed_at),
+        result,
+        edition: 1,
+        lease: None,
+        verification: None,
+    }
+}
+
+#[test]
+fn task_store_dir() {
+    let path = temp_dir("store");
+    let store = DirTaskStore::open(&path).expect("open");
+    assert!(store.load().expect("load").is_empty());
+
+    let pending = task(1, None, 0);
+    let mut completed = task(2, Some(Err("failed".to_string())), 100);
+    store.save(&pending).expect("save");
+    store.save(&completed).expect("save");
+
+    // replaces the existing task
+    completed.edition += 1;
+    completed.result = Some(Ok(Proofs::default()));
+    store.save(&completed).expect("save");
+
+    // tasks that only differ in options not covered by `eq` share the same file
+    let mut retry = pending.clone();
+    retry.options.retry = true;
+    store.save(&retry).expect("save");
+
+    let mut tasks = DirTaskStore::open(&path)
+        .expect("open")
+        .load()
+        .expect("load");
+    tasks.sort_by_key(|e| e.options.block);
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0].options, pending.options);
+    assert!(tasks[0].result.is_none());
+    assert_eq!(tasks[1].edition, completed.edition);
+    assert_eq!(tasks[1].completed_at, Some(100));
+    assert!(matches!(tasks[1].result, Some(Ok(_))));
+
+    store.remove(&pending.options).expect("remove");
+    // removing twice is fine
+    store.remove(&pending.options).expect("remove");
+    let tasks = store.load().expect("load");
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].options, completed.options);
+
+    // multi-block tasks do not share the file of their first block
+    let mut range = completed.clone();
+    range.options.last_block = Some(3);
+    store.save(&range).expect("save");
+    assert_eq!(store.load().expect("load").len(), 2);
+
+    let _ = std::fs::remove_dir_all(&path);
+}
+
+#[test]
+fn task_retention() {
+    let tasks = vec![
+        task(1, None, 0),
+        task(2, Some(Ok(Proofs::default())), 100),
+        task(3, Some(Err("failed".to_string())), 200),
+        task(4, Some(Ok(Proofs::default())), 300),
+    ];
+    let blocks = |retention: TaskRetention, now: u64| {
+        let mut blocks: Vec<u64> = retention
+            .expired(&tasks, now)
+            .iter()
+            .map(|e| e.options.block)
+            .collect();
+        blocks.sort_unstable();
+        blocks
+    };
+
+    assert!(blocks(TaskRetention::default(), 1000).is_empty());
+    assert_eq!(
+        blocks(
+            TaskRetention {
+                max_age: Some(150),
+                max_completed: None
+            },
+            400
+        ),
+        vec![2, 3]
+    );
+    assert_eq!(
+        blocks(
+            TaskRetention {
+                max_age: None,
+                max_completed: Some(1)
+            },
+            400
+        ),
+        vec![2, 3]
+    );
+    assert_eq!(
+        blocks(
+            TaskRetention {
+                max_age: Some(250),
+                max_completed: Some(2)
+            },
+            400
+        ),
+        vec![2]
+    );
+    // pending tasks are never pruned
+    assert_eq!(
+        blocks(
+            TaskRetention {
+                max_age: Some(0),
+                max_completed: Some(0)
+            },
+            400
+        ),
+        vec![2, 3, 4]
+    );
+}
+
+#[tokio::test]
+async fn task_store_reload() {
+    let path = temp_dir("reload");
+    let options = task(1, None, 0).options;
+
+    {
+        let store = DirTaskStore::open(&path).expect("open");
+        let node = SharedState::new("a".to_string(), None)
+            .with_task_store(Arc::new(store))
+            .expect("with_task_store");
+        assert!(node.get_or_enqueue(&options).await.is_none());
+    }
+
+    // a restarted node picks up the task again
+    let store = DirTaskStore::open(&path).expect("open");
+    let node = SharedState::new("a".to_string(), None)
+        .with_task_store(Arc::new(store))
+        .expect("with_task_store");
+    let info = node.get_node_information().await;
+    assert_eq!(info.tasks.len(), 1);
+    assert_eq!(info.tasks[0].options, options);
+
+    node.remove_tasks(|_| true).await;
+    let store = DirTaskStore::open(&path).expect("open");
+    assert!(store.load().expect("load").is_empty());
+
+    let _ = std::fs::remove_dir_all(&path);
+}
@@ -22,6 +22,7 @@ use prover::circuit_witness::CircuitWitness;
 use prover::dummy_circuit;
 use prover::public_input_circuit;
 use prover::super_circuit;
+use prover::utils::aggregation_label;
 use prover::utils::collect_instance;
 use prover::utils::fixed_rng;
 use prover::utils::gen_num_instance;
@@ -67,6 +68,12 @@ fn write_bytes(name: &str, vec: &[u8]) {
         .unwrap_or_else(|_| panic!("write {}", &path));
 }
 
+/// The maximum number of blocks of an aggregation proof,
+/// configurable with `MAX_AGGREGATED_BLOCKS`.
+fn max_aggregated_blocks() -> usize {
+    var("MAX_AGGREGATED_BLOCKS").map_or(2, |e| e.parse().expect("MAX_AGGREGATED_BLOCKS"))
+}
+
 fn gen_verifier(params: &ProverParams, vk: &VerifyingKey<G1Affine>, config: Config) -> Vec<u8> {
     let num_instance = config.num_instance.clone();
     let svk = params.get_g()[0].into();
@@ -169,43 +176,62 @@ macro_rules! gen_match {
 
                 let agg_params =
                     ProverParams::setup(CIRCUIT_CONFIG.min_k_aggregation as u32, fixed_rng());
-                let agg_circuit = AggregationCircuit::new(&agg_params, [snark], fixed_rng());
-                let agg_vk = keygen_vk(&agg_params, &agg_circuit).expect("vk");
-
-                let mut data = Verifier::default();
-                data.label = format!("{}-{}-a", $LABEL, CIRCUIT_CONFIG.block_gas_limit);
-                data.config = CIRCUIT_CONFIG;
-                data.runtime_code = gen_verifier(
-                    &agg_params,
-                    &agg_vk,
-                    Config::kzg()
-                        .with_num_instance(AggregationCircuit::num_instance())
-                        .with_accumulator_indices(Some(AggregationCircuit::accumulator_indices())),
-                )
-                .into();
-
-                if log::log_enabled!(log::Level::Debug) {
-                    let agg_pk = keygen_pk(&agg_params, agg_vk, &agg_circuit).expect("pk");
-                    let proof = gen_proof::<
-                        _,
-                        _,
-                        EvmTranscript<G1Affine, _, _, _>,
-                        EvmTranscript<G1Affine, _, _, _>,
-                        _,
-                    >(
+                // one verifier for each number of aggregated blocks,
+                // with and without exposing the instances of the circuit proofs
+                for num_snarks in 1..=max_aggregated_blocks() {
+                    let agg_circuit = AggregationCircuit::new(
                         &agg_params,
-                        &agg_pk,
-                        agg_circuit.clone(),
-                        agg_circuit.instance(),
+                        vec![snark.clone(); num_snarks],
                         fixed_rng(),
-                        true,
                     );
-                    data.instance = collect_instance(&agg_circuit.instance());
-                    data.proof = proof.into();
-                }
+                    for expose_instances in [false, true] {
+                        let agg_circuit =
+                            agg_circuit.clone().with_exposed_instances(expose_instances);
+                        let agg_vk = keygen_vk(&agg_params, &agg_circuit).expect("vk");
+
+                        let mut data = Verifier::default();
+                        data.label = aggregation_label(
+                            $LABEL,
+                            CIRCUIT_CONFIG.block_gas_limit,
+                            num_snarks,
+                            expose_instances,
+                        );
+                        data.config = CIRCUIT_CONFIG;
+                        data.runtime_code = gen_verifier(
+                            &agg_params,
+                            &agg_vk,
+                            Config::kzg()
+                                .with_num_instance(agg_circuit.num_instance())
+                                .with_accumulator_indices(Some(
+                                    AggregationCircuit::accumulator_indices(),
+                                )),
+                        )
+                        .into();
+
+                        if log::log_enabled!(log::Level::Debug) {
+                            let agg_pk = keygen_pk(&agg_params, agg_vk, &agg_circuit).expect("pk");
+                            let proof = gen_proof::<
+                                _,
+                                _,
+                                EvmTranscript<G1Affine, _, _, _>,
+                                EvmTranscript<G1Affine, _, _, _>,
+                                _,
+                            >(
+                                &agg_params,
+                                &agg_pk,
+                                agg_circuit.clone(),
+                                agg_circuit.instance(),
+                                fixed_rng(),
+                                true,
+                            );
+                            data.instance = collect_instance(&agg_circuit.instance());
+                            data.proof = proof.into();
+                        }
 
-                let data = data.build();
-                write_bytes(&data.label, &serde_json::to_vec(data).unwrap());
+                        let data = data.build();
+                        write_bytes(&data.label, &serde_json::to_vec(data).unwrap());
+                    }
+                }
             },
             {
                 panic!("no circuit parameters found");
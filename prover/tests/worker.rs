@@ -0,0 +1,120 @@
+use prover::shared_state::SharedState;
+use prover::worker::Worker;
+use std::os::unix::fs::PermissionsExt;
+use zkevm_common::prover::*;
+
+/// Writes an executable shell script with `body` and returns its path.
+fn script(name: &str, body: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "proverd-{}-{}.sh",
+        name,
+        SharedState::random_worker_id()
+    ));
+    std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).expect("write");
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+
+    path
+}
+
+#[tokio::test]
+async fn worker_result() {
+    let options = ProofRequestOptions {
+        circuit: "super".to_string(),
+        block: 1,
+        ..Default::default()
+    };
+
+    // forwards the result of the child
+    let path = script(
+        "result",
+        r#"test "$1" = "--worker" && read line && echo "$line" | grep -q '"block":1' && echo '{"Err":"failed"}'"#,
+    );
+    let res = Worker::new(&path).compute_proof(&options).await;
+    assert_eq!(res.unwrap_err(), "failed");
+    let _ = std::fs::remove_file(&path);
+
+    // environment and memory limit
+    let path = script(
+        "env",
+        r#"read line; echo "{\"Err\":\"$FOO $(ulimit -v)\"}""#,
+    );
+    let res = Worker::new(&path)
+        .with_env("FOO", "bar")
+        .with_memory_limit(Some(512 * 1024 * 1024))
+        .compute_proof(&options)
+        .await;
+    assert_eq!(res.unwrap_err(), "bar 524288");
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn worker_reuse() {
+    let options = ProofRequestOptions::default();
+    let slow_options = ProofRequestOptions {
+        block: 1,
+        ..Default::default()
+    };
+
+    // answers with the number of requests handled by this child
+    let path = script(
+        "reuse",
+        r#"while read line; do
+            n=$((n+1))
+            case "$line" in *'"block":1'*) sleep 10;; esac
+            echo "{\"Err\":\"$n\"}"
+        done"#,
+    );
+    let worker = Worker::new(&path);
+    assert_eq!(worker.compute_proof(&options).await.unwrap_err(), "1");
+    assert_eq!(worker.compute_proof(&options).await.unwrap_err(), "2");
+
+    // a dropped request kills the child and the next request starts a new one
+    let res = tokio::time::timeout(
+        std::time::Duration::from_millis(100),
+        worker.compute_proof(&slow_options),
+    )
+    .await;
+    assert!(res.is_err());
+    assert_eq!(worker.compute_proof(&options).await.unwrap_err(), "1");
+    let _ = std::fs::remove_file(&path);
+
+    // a new child is started after a crash
+    let marker = std::env::temp_dir().join(format!(
+        "proverd-marker-{}",
+        SharedState::random_worker_id()
+    ));
+    let path = script(
+        "restart",
+        &format!(
+            r#"read line; test -e '{0}' || {{ touch '{0}'; kill -9 $$; }}; echo '{{"Err":"restarted"}}'"#,
+            marker.display()
+        ),
+    );
+    let worker = Worker::new(&path);
+    let res = worker.compute_proof(&options).await;
+    assert!(res.unwrap_err().starts_with("worker crashed"));
+    let res = worker.compute_proof(&options).await;
+    assert_eq!(res.unwrap_err(), "restarted");
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&marker);
+}
+
+#[tokio::test]
+async fn worker_crash() {
+    let options = ProofRequestOptions::default();
+
+    let path = script("crash", "kill -9 $$");
+    let res = Worker::new(&path).compute_proof(&options).await;
+    assert!(res.unwrap_err().starts_with("worker crashed"));
+    let _ = std::fs::remove_file(&path);
+
+    let path = script("garbage", "read line; echo garbage");
+    let res = Worker::new(&path).compute_proof(&options).await;
+    assert!(res.unwrap_err().starts_with("worker: invalid output"));
+    let _ = std::fs::remove_file(&path);
+
+    let res = Worker::new("/nonexistent/prover_cmd")
+        .compute_proof(&options)
+        .await;
+    assert!(res.is_err());
+}